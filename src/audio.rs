@@ -0,0 +1,272 @@
+//! Audio Device Class 1.0 (UAC1)
+//!
+//! For more details see (USBAUDIO1.0)
+
+use core::num::NonZeroU8;
+
+use crate::{
+    bmrequesttype::{bmRequestType, Direction, Recipient, Type},
+    Endpoint,
+};
+
+pub mod control;
+pub mod streaming;
+
+/// Audio interface class
+#[derive(Clone, Copy)]
+pub struct Class {
+    /// Interface subclass
+    pub subclass: SubClass,
+}
+
+impl Class {
+    /// Class byte
+    pub fn class(&self) -> NonZeroU8 {
+        unsafe { NonZeroU8::new_unchecked(0x01) }
+    }
+
+    /// SubClass byte
+    pub fn subclass(&self) -> u8 {
+        self.subclass as u8
+    }
+
+    /// Protocol byte
+    pub fn protocol(&self) -> u8 {
+        0
+    }
+}
+
+/// Audio Interface Subclass codes
+///
+/// See section A.2 of (USBAUDIO1.0)
+#[derive(Clone, Copy)]
+pub enum SubClass {
+    /// AudioControl interface
+    AudioControl = 0x01,
+    /// AudioStreaming interface
+    AudioStreaming = 0x02,
+    /// MIDIStreaming interface
+    MidiStreaming = 0x03,
+}
+
+const CS_INTERFACE: u8 = 0x24;
+const CS_ENDPOINT: u8 = 0x25;
+
+/// Audio class-specific request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Request {
+    /// Request code
+    pub kind: Kind,
+    /// Addressed control
+    pub control: Control,
+}
+
+/// Audio request code
+///
+/// See section 5.2.1 of (USBAUDIO1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// SET_CUR -- the new value follows in the data stage
+    SetCur,
+    /// GET_CUR -- returns the current value
+    GetCur,
+    /// GET_MIN -- returns the minimum value
+    GetMin,
+    /// GET_MAX -- returns the maximum value
+    GetMax,
+    /// GET_RES -- returns the resolution
+    GetRes,
+}
+
+/// Control addressed by an audio request
+///
+/// The values are little-endian: mute is 1 byte (`0` or `1`), volume is a 2-byte signed value in
+/// units of 1/256 dB and the sampling frequency is a 3-byte value in Hz (see `sampling_frequency`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+    /// Mute control of a feature unit; only supports SET_CUR and GET_CUR
+    Mute {
+        /// AudioControl interface
+        interface: u8,
+        /// Feature unit ID
+        unit: NonZeroU8,
+        /// Logical channel; `0` is the master channel
+        channel: u8,
+    },
+    /// Volume control of a feature unit
+    Volume {
+        /// AudioControl interface
+        interface: u8,
+        /// Feature unit ID
+        unit: NonZeroU8,
+        /// Logical channel; `0` is the master channel
+        channel: u8,
+    },
+    /// Sampling frequency control of an isochronous endpoint
+    SamplingFrequency {
+        /// Isochronous data endpoint
+        endpoint: Endpoint,
+    },
+}
+
+const SET_CUR: u8 = 0x01;
+const GET_CUR: u8 = 0x81;
+const GET_MIN: u8 = 0x82;
+const GET_MAX: u8 = 0x83;
+const GET_RES: u8 = 0x84;
+
+const MUTE_CONTROL: u8 = 0x01;
+const VOLUME_CONTROL: u8 = 0x02;
+const SAMPLING_FREQ_CONTROL: u8 = 0x01;
+
+impl Request {
+    /// Parses an audio request
+    pub fn parse(
+        bmrequesttype: u8,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        let bmrequesttype = bmRequestType::parse(bmrequesttype)?;
+
+        if bmrequesttype.ty != Type::Class {
+            return Err(());
+        }
+
+        Self::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+    }
+
+    pub(crate) fn parse2(
+        bmRequestType {
+            direction,
+            recipient,
+            // ty must be `Class`
+            ..
+        }: bmRequestType,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        let kind = match (brequest, direction) {
+            (SET_CUR, Direction::HostToDevice) => Kind::SetCur,
+            (GET_CUR, Direction::DeviceToHost) => Kind::GetCur,
+            (GET_MIN, Direction::DeviceToHost) => Kind::GetMin,
+            (GET_MAX, Direction::DeviceToHost) => Kind::GetMax,
+            (GET_RES, Direction::DeviceToHost) => Kind::GetRes,
+            _ => return Err(()),
+        };
+
+        let selector = (wvalue >> 8) as u8;
+        let control = match recipient {
+            Recipient::Interface => {
+                let interface = windex as u8;
+                let unit = NonZeroU8::new((windex >> 8) as u8).ok_or(())?;
+                let channel = wvalue as u8;
+
+                match (selector, wlength) {
+                    (MUTE_CONTROL, 1) if kind == Kind::SetCur || kind == Kind::GetCur => {
+                        Control::Mute {
+                            interface,
+                            unit,
+                            channel,
+                        }
+                    }
+                    (VOLUME_CONTROL, 2) => Control::Volume {
+                        interface,
+                        unit,
+                        channel,
+                    },
+                    _ => return Err(()),
+                }
+            }
+
+            Recipient::Endpoint if selector == SAMPLING_FREQ_CONTROL && wvalue as u8 == 0 => {
+                if wlength != 3 {
+                    return Err(());
+                }
+
+                Control::SamplingFrequency {
+                    endpoint: crate::windex2endpoint(windex)?,
+                }
+            }
+
+            _ => return Err(()),
+        };
+
+        Ok(Request { kind, control })
+    }
+}
+
+/// Parses the 3-byte value of a sampling frequency request
+pub fn sampling_frequency(bytes: &[u8]) -> Result<u32, ()> {
+    match bytes {
+        &[a, b, c] => Ok(u32::from_le_bytes([a, b, c, 0])),
+        _ => Err(()),
+    }
+}
+
+/// Returns the 3-byte representation of a sampling frequency, in Hz
+pub fn sampling_frequency_bytes(hz: u32) -> [u8; 3] {
+    [hz as u8, (hz >> 8) as u8, (hz >> 16) as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU8;
+
+    use super::{Control, Kind, Request};
+    use crate::{Direction, Endpoint};
+
+    #[test]
+    fn requests() {
+        // GET_CUR(volume, unit 2, master channel) on interface 0
+        assert_eq!(
+            crate::Request::parse(0b1010_0001, 0x81, 0x0200, 0x0200, 2),
+            Ok(crate::Request::Audio(Request {
+                kind: Kind::GetCur,
+                control: Control::Volume {
+                    interface: 0,
+                    unit: NonZeroU8::new(2).unwrap(),
+                    channel: 0,
+                },
+            }))
+        );
+
+        // SET_CUR(mute, unit 2, channel 1)
+        assert_eq!(
+            crate::Request::parse(0b0010_0001, 0x01, 0x0101, 0x0200, 1),
+            Ok(crate::Request::Audio(Request {
+                kind: Kind::SetCur,
+                control: Control::Mute {
+                    interface: 0,
+                    unit: NonZeroU8::new(2).unwrap(),
+                    channel: 1,
+                },
+            }))
+        );
+
+        // SET_CUR(sampling frequency) on endpoint 0x81
+        assert_eq!(
+            crate::Request::parse(0b0010_0010, 0x01, 0x0100, 0x0081, 3),
+            Ok(crate::Request::Audio(Request {
+                kind: Kind::SetCur,
+                control: Control::SamplingFrequency {
+                    endpoint: Endpoint {
+                        direction: Direction::In,
+                        number: 1,
+                    },
+                },
+            }))
+        );
+
+        // mute has no range
+        assert!(Request::parse(0b1010_0001, 0x82, 0x0100, 0x0200, 1).is_err());
+        // unit 0 doesn't exist
+        assert!(Request::parse(0b1010_0001, 0x81, 0x0200, 0x0000, 2).is_err());
+
+        assert_eq!(super::sampling_frequency(&[0x80, 0xBB, 0x00]), Ok(48_000));
+        assert_eq!(super::sampling_frequency_bytes(44_100), [0x44, 0xAC, 0x00]);
+    }
+}
@@ -0,0 +1,541 @@
+//! AudioControl interface descriptors
+//!
+//! See section 4.3 of (USBAUDIO1.0)
+
+use core::num::NonZeroU8;
+
+const HEADER: u8 = 0x01;
+const INPUT_TERMINAL: u8 = 0x02;
+const OUTPUT_TERMINAL: u8 = 0x03;
+const MIXER_UNIT: u8 = 0x04;
+const SELECTOR_UNIT: u8 = 0x05;
+const FEATURE_UNIT: u8 = 0x06;
+
+/// Class-specific AudioControl interface header descriptor
+///
+/// See section 4.3.2 of (USBAUDIO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Header<'a> {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Audio Device Class specification release number (Binary-coded Decimal); `0x0100`
+    pub bcdADC: u16,
+    /// Total size of the class-specific AudioControl descriptors, including this header
+    pub wTotalLength: u16,
+    // bInCollection: u8,
+    /// AudioStreaming and MIDIStreaming interfaces of the audio function
+    pub baInterfaceNr: &'a [u8],
+}
+
+impl<'a> Header<'a> {
+    /// Size of this descriptor on the wire
+    pub fn size(&self) -> usize {
+        8 + self.baInterfaceNr.len()
+    }
+
+    /// Writes the wire representation of this descriptor into `buf`
+    ///
+    /// Returns the size of the descriptor, or an error if `buf` is too small or there are too many
+    /// interfaces
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let size = self.size();
+        if size > usize::from(u8::MAX) {
+            return Err(());
+        }
+
+        let buf = buf.get_mut(..size).ok_or(())?;
+        buf[0] = size as u8;
+        buf[1] = super::CS_INTERFACE;
+        buf[2] = HEADER;
+        buf[3..5].copy_from_slice(&self.bcdADC.to_le_bytes());
+        buf[5..7].copy_from_slice(&self.wTotalLength.to_le_bytes());
+        buf[7] = self.baInterfaceNr.len() as u8;
+        buf[8..].copy_from_slice(self.baInterfaceNr);
+
+        Ok(size)
+    }
+}
+
+/// Input Terminal descriptor
+///
+/// See section 4.3.2.1 of (USBAUDIO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct InputTerminal {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Terminal ID
+    pub bTerminalID: NonZeroU8,
+    /// Terminal type
+    pub wTerminalType: TerminalType,
+    /// Associated output terminal; `0` if none
+    pub bAssocTerminal: u8,
+    /// Number of logical output channels
+    pub bNrChannels: u8,
+    /// Spatial location of the logical channels
+    pub wChannelConfig: ChannelConfig,
+    /// String descriptor index of the name of the first logical channel
+    pub iChannelNames: Option<NonZeroU8>,
+    /// Terminal string descriptor index
+    pub iTerminal: Option<NonZeroU8>,
+}
+
+impl InputTerminal {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 12;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let ty = self.wTerminalType as u16;
+        let config = self.wChannelConfig.word();
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            INPUT_TERMINAL,
+            self.bTerminalID.get(),
+            ty as u8,
+            (ty >> 8) as u8,
+            self.bAssocTerminal,
+            self.bNrChannels,
+            config as u8,
+            (config >> 8) as u8,
+            self.iChannelNames.map(|nz| nz.get()).unwrap_or(0),
+            self.iTerminal.map(|nz| nz.get()).unwrap_or(0),
+        ]
+    }
+}
+
+/// Output Terminal descriptor
+///
+/// See section 4.3.2.2 of (USBAUDIO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct OutputTerminal {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Terminal ID
+    pub bTerminalID: NonZeroU8,
+    /// Terminal type
+    pub wTerminalType: TerminalType,
+    /// Associated input terminal; `0` if none
+    pub bAssocTerminal: u8,
+    /// ID of the unit or terminal this terminal is connected to
+    pub bSourceID: NonZeroU8,
+    /// Terminal string descriptor index
+    pub iTerminal: Option<NonZeroU8>,
+}
+
+impl OutputTerminal {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 9;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let ty = self.wTerminalType as u16;
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            OUTPUT_TERMINAL,
+            self.bTerminalID.get(),
+            ty as u8,
+            (ty >> 8) as u8,
+            self.bAssocTerminal,
+            self.bSourceID.get(),
+            self.iTerminal.map(|nz| nz.get()).unwrap_or(0),
+        ]
+    }
+}
+
+/// Mixer Unit descriptor
+///
+/// See section 4.3.2.3 of (USBAUDIO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct MixerUnit<'a> {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Unit ID
+    pub bUnitID: NonZeroU8,
+    // bNrInPins: u8,
+    /// IDs of the units or terminals connected to the input pins
+    pub baSourceID: &'a [u8],
+    /// Number of logical output channels
+    pub bNrChannels: u8,
+    /// Spatial location of the logical output channels
+    pub wChannelConfig: ChannelConfig,
+    /// String descriptor index of the name of the first logical output channel
+    pub iChannelNames: Option<NonZeroU8>,
+    /// Bitmap of the programmable mixer controls, one bit per (input channel, output channel)
+    /// pair
+    pub bmControls: &'a [u8],
+    /// Mixer unit string descriptor index
+    pub iMixer: Option<NonZeroU8>,
+}
+
+impl<'a> MixerUnit<'a> {
+    /// Size of this descriptor on the wire
+    pub fn size(&self) -> usize {
+        10 + self.baSourceID.len() + self.bmControls.len()
+    }
+
+    /// Writes the wire representation of this descriptor into `buf`
+    ///
+    /// Returns the size of the descriptor, or an error if `buf` is too small or the descriptor is
+    /// too large
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let size = self.size();
+        if size > usize::from(u8::MAX) {
+            return Err(());
+        }
+
+        let pins = self.baSourceID.len();
+        let buf = buf.get_mut(..size).ok_or(())?;
+        buf[0] = size as u8;
+        buf[1] = super::CS_INTERFACE;
+        buf[2] = MIXER_UNIT;
+        buf[3] = self.bUnitID.get();
+        buf[4] = pins as u8;
+        buf[5..5 + pins].copy_from_slice(self.baSourceID);
+        buf[5 + pins] = self.bNrChannels;
+        buf[6 + pins..8 + pins].copy_from_slice(&self.wChannelConfig.word().to_le_bytes());
+        buf[8 + pins] = self.iChannelNames.map(|nz| nz.get()).unwrap_or(0);
+        buf[9 + pins..size - 1].copy_from_slice(self.bmControls);
+        buf[size - 1] = self.iMixer.map(|nz| nz.get()).unwrap_or(0);
+
+        Ok(size)
+    }
+}
+
+/// Selector Unit descriptor
+///
+/// See section 4.3.2.4 of (USBAUDIO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct SelectorUnit<'a> {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Unit ID
+    pub bUnitID: NonZeroU8,
+    // bNrInPins: u8,
+    /// IDs of the units or terminals connected to the input pins
+    pub baSourceID: &'a [u8],
+    /// Selector unit string descriptor index
+    pub iSelector: Option<NonZeroU8>,
+}
+
+impl<'a> SelectorUnit<'a> {
+    /// Size of this descriptor on the wire
+    pub fn size(&self) -> usize {
+        6 + self.baSourceID.len()
+    }
+
+    /// Writes the wire representation of this descriptor into `buf`
+    ///
+    /// Returns the size of the descriptor, or an error if `buf` is too small or there are too many
+    /// input pins
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let size = self.size();
+        if size > usize::from(u8::MAX) {
+            return Err(());
+        }
+
+        let buf = buf.get_mut(..size).ok_or(())?;
+        buf[0] = size as u8;
+        buf[1] = super::CS_INTERFACE;
+        buf[2] = SELECTOR_UNIT;
+        buf[3] = self.bUnitID.get();
+        buf[4] = self.baSourceID.len() as u8;
+        buf[5..size - 1].copy_from_slice(self.baSourceID);
+        buf[size - 1] = self.iSelector.map(|nz| nz.get()).unwrap_or(0);
+
+        Ok(size)
+    }
+}
+
+/// Feature Unit descriptor
+///
+/// See section 4.3.2.5 of (USBAUDIO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct FeatureUnit<'a> {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Unit ID
+    pub bUnitID: NonZeroU8,
+    /// ID of the unit or terminal this unit is connected to
+    pub bSourceID: NonZeroU8,
+    // bControlSize: u8,
+    /// Controls of the master channel (first element) and of each logical channel
+    pub bmaControls: &'a [FeatureControls],
+    /// Feature unit string descriptor index
+    pub iFeature: Option<NonZeroU8>,
+}
+
+impl<'a> FeatureUnit<'a> {
+    fn control_size(&self) -> usize {
+        if self
+            .bmaControls
+            .iter()
+            .any(|controls| controls.word() >> 8 != 0)
+        {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Size of this descriptor on the wire
+    pub fn size(&self) -> usize {
+        7 + self.bmaControls.len() * self.control_size()
+    }
+
+    /// Writes the wire representation of this descriptor into `buf`
+    ///
+    /// `bControlSize` is 1 unless a control above bit 7 is used. Returns the size of the
+    /// descriptor, or an error if `buf` is too small or the number of channels is out of range
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let size = self.size();
+        if self.bmaControls.is_empty() || size > usize::from(u8::MAX) {
+            return Err(());
+        }
+
+        let control_size = self.control_size();
+        let buf = buf.get_mut(..size).ok_or(())?;
+        buf[0] = size as u8;
+        buf[1] = super::CS_INTERFACE;
+        buf[2] = FEATURE_UNIT;
+        buf[3] = self.bUnitID.get();
+        buf[4] = self.bSourceID.get();
+        buf[5] = control_size as u8;
+        for (chunk, controls) in buf[6..size - 1]
+            .chunks_exact_mut(control_size)
+            .zip(self.bmaControls)
+        {
+            chunk.copy_from_slice(&controls.word().to_le_bytes()[..control_size]);
+        }
+        buf[size - 1] = self.iFeature.map(|nz| nz.get()).unwrap_or(0);
+
+        Ok(size)
+    }
+}
+
+/// Controls of a feature unit channel
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeatureControls {
+    /// Mute
+    pub mute: bool,
+    /// Volume
+    pub volume: bool,
+    /// Bass
+    pub bass: bool,
+    /// Mid
+    pub mid: bool,
+    /// Treble
+    pub treble: bool,
+    /// Graphic equalizer
+    pub graphic_equalizer: bool,
+    /// Automatic gain
+    pub automatic_gain: bool,
+    /// Delay
+    pub delay: bool,
+    /// Bass boost
+    pub bass_boost: bool,
+    /// Loudness
+    pub loudness: bool,
+}
+
+impl FeatureControls {
+    fn word(&self) -> u16 {
+        let mut word = 0;
+        for (i, control) in [
+            self.mute,
+            self.volume,
+            self.bass,
+            self.mid,
+            self.treble,
+            self.graphic_equalizer,
+            self.automatic_gain,
+            self.delay,
+            self.bass_boost,
+            self.loudness,
+        ]
+        .iter()
+        .enumerate()
+        {
+            if *control {
+                word |= 1 << i;
+            }
+        }
+        word
+    }
+}
+
+/// Spatial location of logical channels
+///
+/// See section 3.7.2.3 of (USBAUDIO1.0)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChannelConfig {
+    /// Left Front (L)
+    pub left_front: bool,
+    /// Right Front (R)
+    pub right_front: bool,
+    /// Center Front (C)
+    pub center_front: bool,
+    /// Low Frequency Enhancement (LFE)
+    pub low_frequency_enhancement: bool,
+    /// Left Surround (LS)
+    pub left_surround: bool,
+    /// Right Surround (RS)
+    pub right_surround: bool,
+    /// Left of Center (LC)
+    pub left_of_center: bool,
+    /// Right of Center (RC)
+    pub right_of_center: bool,
+    /// Surround (S)
+    pub surround: bool,
+    /// Side Left (SL)
+    pub side_left: bool,
+    /// Side Right (SR)
+    pub side_right: bool,
+    /// Top (T)
+    pub top: bool,
+}
+
+impl ChannelConfig {
+    fn word(&self) -> u16 {
+        let mut word = 0;
+        for (i, channel) in [
+            self.left_front,
+            self.right_front,
+            self.center_front,
+            self.low_frequency_enhancement,
+            self.left_surround,
+            self.right_surround,
+            self.left_of_center,
+            self.right_of_center,
+            self.surround,
+            self.side_left,
+            self.side_right,
+            self.top,
+        ]
+        .iter()
+        .enumerate()
+        {
+            if *channel {
+                word |= 1 << i;
+            }
+        }
+        word
+    }
+}
+
+repr!(u16,
+      /// Terminal types (see USBAUDIOTERM1.0)
+      TerminalType {
+    /// USB streaming
+    UsbStreaming = 0x0101,
+    /// USB vendor specific
+    UsbVendorSpecific = 0x01FF,
+    /// Microphone
+    Microphone = 0x0201,
+    /// Desktop microphone
+    DesktopMicrophone = 0x0202,
+    /// Personal microphone
+    PersonalMicrophone = 0x0203,
+    /// Omni-directional microphone
+    OmniDirectionalMicrophone = 0x0204,
+    /// Microphone array
+    MicrophoneArray = 0x0205,
+    /// Speaker
+    Speaker = 0x0301,
+    /// Headphones
+    Headphones = 0x0302,
+    /// Desktop speaker
+    DesktopSpeaker = 0x0304,
+    /// Headset
+    Headset = 0x0402,
+    /// Analog connector
+    AnalogConnector = 0x0601,
+    /// Digital audio interface
+    DigitalAudioInterface = 0x0602,
+    /// Line connector
+    LineConnector = 0x0603,
+    /// S/PDIF interface
+    SpdifInterface = 0x0605,
+});
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU8;
+
+    use super::{ChannelConfig, FeatureControls, FeatureUnit, Header, InputTerminal, TerminalType};
+
+    fn id(n: u8) -> NonZeroU8 {
+        NonZeroU8::new(n).unwrap()
+    }
+
+    #[test]
+    fn microphone() {
+        let mut buf = [0; 32];
+
+        let n = Header {
+            bcdADC: 0x0100,
+            wTotalLength: 30,
+            baInterfaceNr: &[1],
+        }
+        .bytes(&mut buf)
+        .unwrap();
+        assert_eq!(&buf[..n], &[9, 0x24, 0x01, 0x00, 0x01, 30, 0, 1, 1]);
+
+        let terminal = InputTerminal {
+            bTerminalID: id(1),
+            wTerminalType: TerminalType::Microphone,
+            bAssocTerminal: 0,
+            bNrChannels: 1,
+            wChannelConfig: ChannelConfig::default(),
+            iChannelNames: None,
+            iTerminal: None,
+        };
+        assert_eq!(
+            terminal.bytes(),
+            [12, 0x24, 0x02, 1, 0x01, 0x02, 0, 1, 0, 0, 0, 0]
+        );
+
+        let master = FeatureControls {
+            mute: true,
+            volume: true,
+            ..FeatureControls::default()
+        };
+        let n = FeatureUnit {
+            bUnitID: id(2),
+            bSourceID: id(1),
+            bmaControls: &[master, FeatureControls::default()],
+            iFeature: None,
+        }
+        .bytes(&mut buf)
+        .unwrap();
+        assert_eq!(&buf[..n], &[9, 0x24, 0x06, 2, 1, 1, 0b11, 0, 0]);
+
+        // loudness needs 2-byte controls
+        let loudness = FeatureControls {
+            loudness: true,
+            ..FeatureControls::default()
+        };
+        let n = FeatureUnit {
+            bUnitID: id(2),
+            bSourceID: id(1),
+            bmaControls: &[loudness],
+            iFeature: None,
+        }
+        .bytes(&mut buf)
+        .unwrap();
+        assert_eq!(&buf[..n], &[9, 0x24, 0x06, 2, 1, 2, 0, 0b10, 0]);
+    }
+}
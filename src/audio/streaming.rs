@@ -0,0 +1,330 @@
+//! AudioStreaming interface and endpoint descriptors
+//!
+//! See sections 4.5 and 4.6 of (USBAUDIO1.0), and (USBAUDIOFMT1.0)
+
+use core::num::NonZeroU8;
+
+use crate::endpoint;
+
+const AS_GENERAL: u8 = 0x01;
+const FORMAT_TYPE: u8 = 0x02;
+const EP_GENERAL: u8 = 0x01;
+
+const FORMAT_TYPE_I: u8 = 0x01;
+
+/// Class-specific AudioStreaming general interface descriptor
+///
+/// See section 4.5.2 of (USBAUDIO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct General {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Terminal ID of the terminal this interface is connected to
+    pub bTerminalLink: NonZeroU8,
+    /// Delay introduced by the data path, in frames
+    pub bDelay: u8,
+    /// Audio data format
+    pub wFormatTag: FormatTag,
+}
+
+impl General {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 7;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let tag = self.wFormatTag as u16;
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            AS_GENERAL,
+            self.bTerminalLink.get(),
+            self.bDelay,
+            tag as u8,
+            (tag >> 8) as u8,
+        ]
+    }
+}
+
+repr!(u16,
+      /// Type I audio data formats (see section A.1.1 of USBAUDIOFMT1.0)
+      FormatTag {
+    /// PCM
+    Pcm = 0x0001,
+    /// 8-bit PCM
+    Pcm8 = 0x0002,
+    /// IEEE floating point
+    IeeeFloat = 0x0003,
+    /// A-law
+    Alaw = 0x0004,
+    /// mu-law
+    Mulaw = 0x0005,
+});
+
+/// Type I format type descriptor
+///
+/// See section 2.2.5 of (USBAUDIOFMT1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct FormatTypeI<'a> {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    // bFormatType: u8,
+    /// Number of physical channels
+    pub bNrChannels: u8,
+    /// Number of bytes occupied by one audio subframe (1, 2, 3 or 4)
+    pub bSubframeSize: u8,
+    /// Number of effectively used bits in an audio subframe
+    pub bBitResolution: u8,
+    // bSamFreqType: u8,
+    /// Supported sampling frequencies
+    pub tSamFreq: SamplingFrequencies<'a>,
+}
+
+/// Sampling frequencies supported by an AudioStreaming interface, in Hz
+#[derive(Clone, Copy)]
+pub enum SamplingFrequencies<'a> {
+    /// Continuous range of sampling frequencies
+    Continuous {
+        /// Lower bound
+        min: u32,
+        /// Upper bound
+        max: u32,
+    },
+    /// Discrete set of sampling frequencies
+    Discrete(&'a [u32]),
+}
+
+impl<'a> FormatTypeI<'a> {
+    /// Size of this descriptor on the wire
+    pub fn size(&self) -> usize {
+        let n = match self.tSamFreq {
+            SamplingFrequencies::Continuous { .. } => 2,
+            SamplingFrequencies::Discrete(freqs) => freqs.len(),
+        };
+        8 + 3 * n
+    }
+
+    /// Writes the wire representation of this descriptor into `buf`
+    ///
+    /// Returns the size of the descriptor, or an error if `buf` is too small or the list of
+    /// sampling frequencies is empty or too long
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let size = self.size();
+        if size > usize::from(u8::MAX) {
+            return Err(());
+        }
+
+        let buf = buf.get_mut(..size).ok_or(())?;
+        buf[0] = size as u8;
+        buf[1] = super::CS_INTERFACE;
+        buf[2] = FORMAT_TYPE;
+        buf[3] = FORMAT_TYPE_I;
+        buf[4] = self.bNrChannels;
+        buf[5] = self.bSubframeSize;
+        buf[6] = self.bBitResolution;
+        match self.tSamFreq {
+            SamplingFrequencies::Continuous { min, max } => {
+                buf[7] = 0;
+                buf[8..11].copy_from_slice(&super::sampling_frequency_bytes(min));
+                buf[11..14].copy_from_slice(&super::sampling_frequency_bytes(max));
+            }
+
+            SamplingFrequencies::Discrete(freqs) => {
+                if freqs.is_empty() {
+                    return Err(());
+                }
+
+                buf[7] = freqs.len() as u8;
+                for (chunk, freq) in buf[8..].chunks_exact_mut(3).zip(freqs) {
+                    chunk.copy_from_slice(&super::sampling_frequency_bytes(*freq));
+                }
+            }
+        }
+
+        Ok(size)
+    }
+}
+
+/// Standard AudioStreaming isochronous audio data endpoint descriptor
+///
+/// This is the endpoint descriptor with the two extra fields (USBAUDIO1.0) adds to it
+///
+/// See section 4.6.1.1 of (USBAUDIO1.0)
+#[allow(non_snake_case)]
+pub struct EndpointDescriptor {
+    /// Standard endpoint descriptor fields
+    pub endpoint: endpoint::Descriptor,
+    /// Rate at which synchronization feedback is provided, as a power of 2 (synch endpoints
+    /// only; must be `0` for data endpoints)
+    pub bRefresh: u8,
+    /// Address of the endpoint used to communicate synchronization information; `0` if none
+    pub bSynchAddress: u8,
+}
+
+impl EndpointDescriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 9;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[..endpoint::Descriptor::SIZE as usize].copy_from_slice(&self.endpoint.bytes());
+        bytes[0] = Self::SIZE;
+        bytes[7] = self.bRefresh;
+        bytes[8] = self.bSynchAddress;
+        bytes
+    }
+}
+
+/// Class-specific AudioStreaming isochronous audio data endpoint descriptor
+///
+/// See section 4.6.1.2 of (USBAUDIO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct IsochronousEndpoint {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Supported controls
+    pub bmAttributes: Attributes,
+    /// Units of `wLockDelay`
+    pub bLockDelayUnits: LockDelayUnits,
+    /// Time it takes the endpoint to reliably lock its internal clock recovery circuitry
+    pub wLockDelay: u16,
+}
+
+impl IsochronousEndpoint {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 7;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            super::CS_ENDPOINT,
+            EP_GENERAL,
+            self.bmAttributes.byte(),
+            self.bLockDelayUnits as u8,
+            self.wLockDelay as u8,
+            (self.wLockDelay >> 8) as u8,
+        ]
+    }
+}
+
+/// Class-specific isochronous endpoint attributes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Attributes {
+    /// The endpoint has a sampling frequency control
+    pub sampling_frequency: bool,
+    /// The endpoint has a pitch control
+    pub pitch: bool,
+    /// Packets must always be `wMaxPacketSize` long
+    pub max_packets_only: bool,
+}
+
+impl Attributes {
+    fn byte(&self) -> u8 {
+        let mut byte = 0;
+        if self.sampling_frequency {
+            byte |= 1;
+        }
+        if self.pitch {
+            byte |= 1 << 1;
+        }
+        if self.max_packets_only {
+            byte |= 1 << 7;
+        }
+        byte
+    }
+}
+
+repr!(u8,
+      /// Units of the lock delay
+      LockDelayUnits {
+    /// Undefined
+    Undefined = 0,
+    /// Milliseconds
+    Milliseconds = 1,
+    /// Decoded PCM samples
+    Samples = 2,
+});
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU8;
+
+    use super::{
+        Attributes, EndpointDescriptor, FormatTag, FormatTypeI, General, IsochronousEndpoint,
+        LockDelayUnits, SamplingFrequencies,
+    };
+    use crate::{
+        endpoint::{self, SynchronizationType, Transactions, UsageType},
+        Direction, Endpoint,
+    };
+
+    #[test]
+    fn microphone() {
+        let general = General {
+            bTerminalLink: NonZeroU8::new(3).unwrap(),
+            bDelay: 1,
+            wFormatTag: FormatTag::Pcm,
+        };
+        assert_eq!(general.bytes(), [7, 0x24, 0x01, 3, 1, 0x01, 0x00]);
+
+        let mut buf = [0; 32];
+        let n = FormatTypeI {
+            bNrChannels: 1,
+            bSubframeSize: 2,
+            bBitResolution: 16,
+            tSamFreq: SamplingFrequencies::Discrete(&[16_000, 48_000]),
+        }
+        .bytes(&mut buf)
+        .unwrap();
+        assert_eq!(
+            &buf[..n],
+            &[14, 0x24, 0x02, 0x01, 1, 2, 16, 2, 0x80, 0x3E, 0x00, 0x80, 0xBB, 0x00]
+        );
+
+        assert!(FormatTypeI {
+            bNrChannels: 1,
+            bSubframeSize: 2,
+            bBitResolution: 16,
+            tSamFreq: SamplingFrequencies::Discrete(&[]),
+        }
+        .bytes(&mut buf)
+        .is_err());
+
+        let ep = EndpointDescriptor {
+            endpoint: endpoint::Descriptor {
+                bEndpointAddress: Endpoint {
+                    direction: Direction::In,
+                    number: 1,
+                },
+                ty: endpoint::Type::Isochronous {
+                    synchronization_type: SynchronizationType::Asynchronous,
+                    usage_type: UsageType::DataEndpoint,
+                    transactions_per_microframe: Transactions::_1,
+                },
+                max_packet_size: 96,
+                bInterval: 1,
+            },
+            bRefresh: 0,
+            bSynchAddress: 0,
+        };
+        assert_eq!(ep.bytes(), [9, 0x05, 0x81, 0x05, 96, 0, 1, 0, 0]);
+
+        let cs = IsochronousEndpoint {
+            bmAttributes: Attributes {
+                sampling_frequency: true,
+                ..Attributes::default()
+            },
+            bLockDelayUnits: LockDelayUnits::Undefined,
+            wLockDelay: 0,
+        };
+        assert_eq!(cs.bytes(), [7, 0x25, 0x01, 0x01, 0, 0, 0]);
+    }
+}
@@ -4,7 +4,20 @@ use core::num::NonZeroU8;
 
 pub mod acm;
 pub mod call;
+pub mod command_set;
+pub mod country;
+pub mod direct_line;
+pub mod ecm;
+pub mod eem;
 pub mod header;
+pub mod line_state;
+pub mod mbim;
+pub mod ncm;
+pub mod notification;
+pub mod ntb;
+pub mod obex;
+pub mod operational;
+pub mod ringer;
 pub mod union;
 
 /// Communication Device Class
@@ -51,17 +64,65 @@ impl Class {
 }
 
 /// Communications Class Subclass codes
+///
+/// See section 4.3 of (USBCDC1.2)
 #[derive(Clone, Copy)]
 pub enum SubClass {
+    /// Direct Line Control Model
+    DirectLineControlModel = 0x01,
     /// Abstract Control Model
     AbstractControlModel = 0x02,
+    /// Telephone Control Model
+    TelephoneControlModel = 0x03,
+    /// Multi-Channel Control Model
+    MultiChannelControlModel = 0x04,
+    /// CAPI Control Model
+    CapiControlModel = 0x05,
+    /// Ethernet Networking Control Model
+    EthernetNetworkingControlModel = 0x06,
+    /// ATM Networking Control Model
+    AtmNetworkingControlModel = 0x07,
+    /// Wireless Handset Control Model
+    WirelessHandsetControlModel = 0x08,
+    /// Device Management
+    DeviceManagement = 0x09,
+    /// Mobile Direct Line Model
+    MobileDirectLineModel = 0x0A,
+    /// OBEX
+    Obex = 0x0B,
+    /// Ethernet Emulation Model
+    EthernetEmulationModel = 0x0C,
+    /// Network Control Model
+    NetworkControlModel = 0x0D,
+    /// Mobile Broadband Interface Model
+    MobileBroadbandInterfaceModel = 0x0E,
 }
 
 /// Communications Class Protocol codes
+///
+/// See section 4.4 of (USBCDC1.2)
 #[derive(Clone, Copy)]
 pub enum Protocol {
-    /// AT Commands
-    ATCommands = 1,
+    /// No class specific protocol required
+    None = 0x00,
+    /// AT Commands: V.250 etc
+    ATCommands = 0x01,
+    /// AT Commands defined by PCCA-101
+    ATCommandsPcca101 = 0x02,
+    /// AT Commands defined by PCCA-101 & Annex O
+    ATCommandsPcca101AnnexO = 0x03,
+    /// AT Commands defined by GSM 07.07
+    ATCommandsGsm0707 = 0x04,
+    /// AT Commands defined by 3GPP 27.007
+    ATCommands3gpp27007 = 0x05,
+    /// AT Commands defined by TIA for CDMA
+    ATCommandsCdma = 0x06,
+    /// Ethernet Emulation Model
+    EthernetEmulationModel = 0x07,
+    /// External Protocol: commands defined by a Command Set functional descriptor
+    External = 0xFE,
+    /// Vendor-specific
+    VendorSpecific = 0xFF,
 }
 
 const CS_INTERFACE: u8 = 0x24;
@@ -69,4 +130,96 @@ const CS_INTERFACE: u8 = 0x24;
 const SUBTYPE_HEADER: u8 = 0x00;
 const SUBTYPE_CALL: u8 = 0x01;
 const SUBTYPE_ACM: u8 = 0x02;
+const SUBTYPE_DIRECT_LINE: u8 = 0x03;
+const SUBTYPE_RINGER: u8 = 0x04;
+const SUBTYPE_LINE_STATE: u8 = 0x05;
 const SUBTYPE_UNION: u8 = 0x06;
+const SUBTYPE_COUNTRY: u8 = 0x07;
+const SUBTYPE_OPERATIONAL_MODES: u8 = 0x08;
+const SUBTYPE_ETHERNET: u8 = 0x0F;
+const SUBTYPE_OBEX: u8 = 0x15;
+const SUBTYPE_COMMAND_SET: u8 = 0x16;
+const SUBTYPE_NCM: u8 = 0x1A;
+const SUBTYPE_MBIM: u8 = 0x1B;
+const SUBTYPE_MBIM_EXTENDED: u8 = 0x1C;
+
+/// Communications Class notification
+///
+/// See section 6.3 of (USBCDC1.2)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Notification {
+    /// NETWORK_CONNECTION
+    NetworkConnection(notification::NetworkConnection),
+    /// RESPONSE_AVAILABLE
+    ResponseAvailable(notification::ResponseAvailable),
+    /// SERIAL_STATE
+    SerialState(acm::SerialState),
+    /// CONNECTION_SPEED_CHANGE
+    ConnectionSpeedChange(notification::ConnectionSpeedChange),
+}
+
+impl Notification {
+    /// Parses a notification read from the interrupt IN endpoint
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        match bytes.get(1) {
+            Some(&notification::NETWORK_CONNECTION) => {
+                notification::NetworkConnection::parse(bytes).map(Notification::NetworkConnection)
+            }
+            Some(&notification::RESPONSE_AVAILABLE) => {
+                notification::ResponseAvailable::parse(bytes).map(Notification::ResponseAvailable)
+            }
+            Some(&notification::SERIAL_STATE) => {
+                acm::SerialState::parse(bytes).map(Notification::SerialState)
+            }
+            Some(&notification::CONNECTION_SPEED_CHANGE) => {
+                notification::ConnectionSpeedChange::parse(bytes)
+                    .map(Notification::ConnectionSpeedChange)
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+// Validates the header of a fixed-size functional descriptor and returns its bytes
+fn parse_functional(bytes: &[u8], subtype: u8, size: u8) -> Result<&[u8], ()> {
+    let bytes = bytes.get(..usize::from(size)).ok_or(())?;
+
+    if bytes[0] != size || bytes[1] != CS_INTERFACE || bytes[2] != subtype {
+        return Err(());
+    }
+
+    Ok(bytes)
+}
+
+// bmRequestType of all notifications: device to host, class, interface
+const NOTIFICATION_REQUEST_TYPE: u8 = 0b1010_0001;
+
+// Returns the 8-byte header of a notification
+fn header(bnotification: u8, wvalue: u16, interface: u8, wlength: u16) -> [u8; 8] {
+    [
+        NOTIFICATION_REQUEST_TYPE,
+        bnotification,
+        wvalue as u8,
+        (wvalue >> 8) as u8,
+        interface,
+        0,
+        wlength as u8,
+        (wlength >> 8) as u8,
+    ]
+}
+
+// Validates the header of a notification and returns its wValue and interface
+fn parse_header(bytes: &[u8], bnotification: u8, wlength: u16) -> Result<(u16, u8), ()> {
+    if bytes.len() != 8 + usize::from(wlength)
+        || bytes[0] != NOTIFICATION_REQUEST_TYPE
+        || bytes[1] != bnotification
+        || u16::from_le_bytes([bytes[6], bytes[7]]) != wlength
+    {
+        return Err(());
+    }
+
+    let wvalue = u16::from_le_bytes([bytes[2], bytes[3]]);
+    let interface = crate::windex2interface(u16::from_le_bytes([bytes[4], bytes[5]]))?;
+
+    Ok((wvalue, interface))
+}
@@ -1,6 +1,16 @@
 //! Abstract Control Management functional descriptor
 
-use crate::bmrequesttype::{bmRequestType, Direction, Recipient, Type};
+use core::{
+    convert::TryFrom,
+    num::{NonZeroU16, NonZeroU8},
+};
+
+use crate::{
+    bmrequesttype::{bmRequestType, Direction, Recipient, Type},
+    endpoint, ia, interface, Endpoint,
+};
+
+use super::{call, header, notification, union, Class, Protocol, SubClass};
 
 /// ACM request
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -12,8 +22,26 @@ pub struct Request {
 }
 
 /// ACM Request kind
+///
+/// See section 6.3 of (USBPTSN1.2)
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Kind {
+    /// SEND_ENCAPSULATED_COMMAND -- the command follows in the data stage
+    SendEncapsulatedCommand {
+        /// Length of the command (never zero)
+        length: u16,
+    },
+    /// GET_ENCAPSULATED_RESPONSE
+    GetEncapsulatedResponse {
+        /// Maximum number of bytes to return
+        length: u16,
+    },
+    /// SET_COMM_FEATURE -- the feature value (2 bytes) follows in the data stage
+    SetCommFeature(CommFeature),
+    /// GET_COMM_FEATURE -- returns the feature value (2 bytes)
+    GetCommFeature(CommFeature),
+    /// CLEAR_COMM_FEATURE
+    ClearCommFeature(CommFeature),
     /// GET_LINE_CODING
     GetLineCoding,
     /// SET_LINE_CODING
@@ -25,13 +53,80 @@ pub enum Kind {
         /// Carrier control for half-duplex modems. `true` = activate RTS carrier; `false` = deactivate
         rts: bool,
     },
+    /// SEND_BREAK
+    SendBreak(Break),
 }
 
+/// Duration of a SEND_BREAK request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Break {
+    /// Stop the break signal
+    Stop,
+    /// Send a break signal for the specified number of milliseconds
+    Milliseconds(NonZeroU16),
+    /// Send a break signal until a `Stop` break is received
+    UntilCleared,
+}
+
+repr!(u16,
+      /// Communications feature selector
+      CommFeature {
+    /// Abstract State -- see `AbstractState`
+    AbstractState = 0x01,
+    /// Country Setting -- ISO 3166 country code
+    CountrySetting = 0x02,
+});
+
+/// Value of the `AbstractState` communications feature
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AbstractState {
+    /// All the endpoints of this interface stop accepting / providing data
+    pub idle: bool,
+    /// Data of multiple protocols is multiplexed over the Data Class interface
+    pub data_multiplexing: bool,
+}
+
+impl AbstractState {
+    /// The size of this structure on the wire
+    pub const SIZE: u8 = 2;
+
+    /// Returns the wire representation of this structure
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut byte = 0;
+        if self.idle {
+            byte |= 1 << 0;
+        }
+        if self.data_multiplexing {
+            byte |= 1 << 1;
+        }
+        [byte, 0]
+    }
+
+    /// Parses the data stage of a SET_COMM_FEATURE(ABSTRACT_STATE) request
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) || bytes[0] & !0b11 != 0 || bytes[1] != 0 {
+            return Err(());
+        }
+
+        Ok(AbstractState {
+            idle: bytes[0] & (1 << 0) != 0,
+            data_multiplexing: bytes[0] & (1 << 1) != 0,
+        })
+    }
+}
+
+const SEND_ENCAPSULATED_COMMAND: u8 = 0x00;
+const GET_ENCAPSULATED_RESPONSE: u8 = 0x01;
+const SET_COMM_FEATURE: u8 = 0x02;
+const GET_COMM_FEATURE: u8 = 0x03;
+const CLEAR_COMM_FEATURE: u8 = 0x04;
 const SET_LINE_CODING: u8 = 0x20;
 const GET_LINE_CODING: u8 = 0x21;
 const SET_CONTROL_LINE_STATE: u8 = 0x22;
+const SEND_BREAK: u8 = 0x23;
 
 /// Serial state notification
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct SerialState {
     /// Interface index
     pub interface: u8,
@@ -59,8 +154,6 @@ impl SerialState {
 
     /// Returns the wire representation of this notification
     pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
-        const SERIAL_STATE: u8 = 0x20;
-
         let mut bitmap = 0;
         if self.bOverRun {
             bitmap |= 1 << 6;
@@ -83,25 +176,42 @@ impl SerialState {
         if self.bRxCarrier {
             bitmap |= 1 << 0;
         }
-        [
-            // header
-            0b1010_0001,  // bmRequestType
-            SERIAL_STATE, // bNotification
+
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[..8].copy_from_slice(&super::header(
+            notification::SERIAL_STATE,
             0,
-            0, // wValue
             self.interface,
-            0, // wIndex
             2,
-            0, // wLength
-            // data
-            bitmap,
-            0,
-        ]
+        ));
+        bytes[8] = bitmap;
+        bytes
+    }
+
+    /// Parses a SERIAL_STATE notification; reserved bits are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let (wvalue, interface) = super::parse_header(bytes, notification::SERIAL_STATE, 2)?;
+
+        if wvalue != 0 {
+            return Err(());
+        }
+
+        let bitmap = bytes[8];
+        Ok(SerialState {
+            interface,
+            bOverRun: bitmap & (1 << 6) != 0,
+            bParity: bitmap & (1 << 5) != 0,
+            bFraming: bitmap & (1 << 4) != 0,
+            bRingSignal: bitmap & (1 << 3) != 0,
+            bBreak: bitmap & (1 << 2) != 0,
+            bTxCarrier: bitmap & (1 << 1) != 0,
+            bRxCarrier: bitmap & (1 << 0) != 0,
+        })
     }
 }
 
 /// Line Coding structure
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct LineCoding {
     /// Data terminal rate, in bits per second
     pub dwDTERate: u32,
@@ -129,22 +239,86 @@ impl LineCoding {
             self.bDataBits as u8,
         ]
     }
+
+    /// Parses the data stage of a SET_LINE_CODING request
+    pub fn parse(bytes: &[u8; Self::SIZE as usize]) -> Result<Self, LineCodingError> {
+        let bCharFormat =
+            bCharFormat::_from(bytes[4]).ok_or(LineCodingError::CharFormat(bytes[4]))?;
+        let bParityType =
+            bParityType::_from(bytes[5]).ok_or(LineCodingError::ParityType(bytes[5]))?;
+        let bDataBits = bDataBits::_from(bytes[6]).ok_or(LineCodingError::DataBits(bytes[6]))?;
+
+        Ok(LineCoding {
+            dwDTERate: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            bCharFormat,
+            bParityType,
+            bDataBits,
+        })
+    }
+
+    /// Returns the UART configuration as a (baud rate, data bits, parity, stop bits) tuple
+    pub fn uart(&self) -> (u32, u8, bParityType, bCharFormat) {
+        (
+            self.dwDTERate,
+            self.bDataBits as u8,
+            self.bParityType,
+            self.bCharFormat,
+        )
+    }
+
+    /// Builds a line coding from a (baud rate, data bits, parity, stop bits) UART configuration
+    pub fn from_uart(
+        baud_rate: u32,
+        data_bits: u8,
+        parity: bParityType,
+        stop_bits: bCharFormat,
+    ) -> Result<Self, LineCodingError> {
+        Ok(LineCoding {
+            dwDTERate: baud_rate,
+            bCharFormat: stop_bits,
+            bParityType: parity,
+            bDataBits: bDataBits::_from(data_bits).ok_or(LineCodingError::DataBits(data_bits))?,
+        })
+    }
 }
 
-/// Stop bits
-#[derive(Clone, Copy)]
-pub enum bCharFormat {
+impl Default for LineCoding {
+    /// 115200 8N1
+    fn default() -> Self {
+        LineCoding {
+            dwDTERate: 115_200,
+            bCharFormat: bCharFormat::Stop1,
+            bParityType: bParityType::None,
+            bDataBits: bDataBits::_8,
+        }
+    }
+}
+
+/// Error returned when parsing a `LineCoding`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineCodingError {
+    /// Invalid `bCharFormat` (stop bits) code
+    CharFormat(u8),
+    /// Invalid `bParityType` code
+    ParityType(u8),
+    /// Invalid `bDataBits` value
+    DataBits(u8),
+}
+
+repr!(u8,
+      /// Stop bits
+      bCharFormat {
     /// 1 stop bit
     Stop1 = 0,
     /// 1.5 stop bit
     Stop1_5 = 1,
     /// 2 stop bits
     Stop2 = 2,
-}
+});
 
-/// Parity
-#[derive(Clone, Copy)]
-pub enum bParityType {
+repr!(u8,
+      /// Parity
+      bParityType {
     /// None
     None = 0,
     /// Odd
@@ -155,11 +329,24 @@ pub enum bParityType {
     Mark = 3,
     /// Space
     Space = 4,
+});
+
+impl bParityType {
+    /// Returns the letter used for this parity in the "8N1" notation
+    pub fn letter(&self) -> char {
+        match self {
+            bParityType::None => 'N',
+            bParityType::Odd => 'O',
+            bParityType::Even => 'E',
+            bParityType::Mark => 'M',
+            bParityType::Space => 'S',
+        }
+    }
 }
 
-/// Data bits
-#[derive(Clone, Copy)]
-pub enum bDataBits {
+repr!(u8,
+      /// Data bits
+      bDataBits {
     /// 5 bits
     _5 = 5,
     /// 6 bits
@@ -170,7 +357,7 @@ pub enum bDataBits {
     _8 = 8,
     /// 16 bits
     _16 = 16,
-}
+});
 
 impl Request {
     /// Parses an ACM request
@@ -203,6 +390,65 @@ impl Request {
         wlength: u16,
     ) -> Result<Self, ()> {
         match (brequest, direction) {
+            // the command is carried in the data stage; without one there's nothing to send
+            (SEND_ENCAPSULATED_COMMAND, Direction::HostToDevice)
+                if recipient == Recipient::Interface && wvalue == 0 && wlength != 0 =>
+            {
+                let interface = crate::windex2interface(windex)?;
+
+                Ok(Request {
+                    interface,
+                    kind: Kind::SendEncapsulatedCommand { length: wlength },
+                })
+            }
+
+            (GET_ENCAPSULATED_RESPONSE, Direction::DeviceToHost)
+                if recipient == Recipient::Interface && wvalue == 0 =>
+            {
+                let interface = crate::windex2interface(windex)?;
+
+                Ok(Request {
+                    interface,
+                    kind: Kind::GetEncapsulatedResponse { length: wlength },
+                })
+            }
+
+            (SET_COMM_FEATURE, Direction::HostToDevice)
+                if recipient == Recipient::Interface && wlength == 2 =>
+            {
+                let interface = crate::windex2interface(windex)?;
+                let feature = CommFeature::_from(wvalue).ok_or(())?;
+
+                Ok(Request {
+                    interface,
+                    kind: Kind::SetCommFeature(feature),
+                })
+            }
+
+            (GET_COMM_FEATURE, Direction::DeviceToHost)
+                if recipient == Recipient::Interface && wlength == 2 =>
+            {
+                let interface = crate::windex2interface(windex)?;
+                let feature = CommFeature::_from(wvalue).ok_or(())?;
+
+                Ok(Request {
+                    interface,
+                    kind: Kind::GetCommFeature(feature),
+                })
+            }
+
+            (CLEAR_COMM_FEATURE, Direction::HostToDevice)
+                if recipient == Recipient::Interface && wlength == 0 =>
+            {
+                let interface = crate::windex2interface(windex)?;
+                let feature = CommFeature::_from(wvalue).ok_or(())?;
+
+                Ok(Request {
+                    interface,
+                    kind: Kind::ClearCommFeature(feature),
+                })
+            }
+
             (SET_LINE_CODING, Direction::HostToDevice)
                 if recipient == Recipient::Interface && wvalue == 0 && wlength == 7 =>
             {
@@ -242,6 +488,22 @@ impl Request {
                 })
             }
 
+            (SEND_BREAK, Direction::HostToDevice)
+                if recipient == Recipient::Interface && wlength == 0 =>
+            {
+                let interface = crate::windex2interface(windex)?;
+                let duration = match wvalue {
+                    0 => Break::Stop,
+                    0xFFFF => Break::UntilCleared,
+                    ms => Break::Milliseconds(unsafe { NonZeroU16::new_unchecked(ms) }),
+                };
+
+                Ok(Request {
+                    interface,
+                    kind: Kind::SendBreak(duration),
+                })
+            }
+
             _ => Err(()),
         }
     }
@@ -304,3 +566,565 @@ impl Descriptor {
         ]
     }
 }
+
+/// A complete ACM function (virtual serial port)
+///
+/// Emits the Interface Association Descriptor followed by the communications interface (with its
+/// Header, Call Management, ACM and Union functional descriptors and the notification endpoint)
+/// and the data interface (with its bulk IN and OUT endpoints)
+#[derive(Clone, Copy)]
+pub struct Function {
+    /// Number of the communications interface; the data interface uses the next number
+    pub first_interface: u8,
+    /// Communications class protocol
+    pub protocol: Protocol,
+    /// ACM capabilities
+    pub capabilities: Capabilities,
+    /// Index of the string descriptor that describes this function
+    pub iFunction: Option<NonZeroU8>,
+    /// Number of the interrupt IN endpoint used for notifications
+    pub notification_endpoint: u8,
+    /// Maximum packet size of the notification endpoint
+    pub notification_max_packet_size: u16,
+    /// Polling interval of the notification endpoint
+    pub notification_interval: u8,
+    /// Number of the bulk IN endpoint
+    pub data_in_endpoint: u8,
+    /// Number of the bulk OUT endpoint
+    pub data_out_endpoint: u8,
+    /// Maximum packet size of the bulk endpoints
+    pub data_max_packet_size: u16,
+}
+
+impl Function {
+    /// Number of interfaces used by this function
+    pub const INTERFACES: u8 = 2;
+
+    /// Size of the descriptors of this function on the wire
+    pub const SIZE: u8 = ia::Descriptor::SIZE
+        + 2 * interface::Descriptor::SIZE
+        + header::Descriptor::SIZE
+        + call::Descriptor::SIZE
+        + Descriptor::SIZE
+        + UNION_SIZE as u8
+        + 3 * endpoint::Descriptor::SIZE;
+
+    /// Returns the wire representation of the descriptors of this function
+    ///
+    /// Returns an error if `first_interface` is the last interface number (there's no room for
+    /// the data interface)
+    pub fn bytes(&self) -> Result<[u8; Self::SIZE as usize], ()> {
+        let comm = Class::Communications {
+            subclass: SubClass::AbstractControlModel,
+            protocol: self.protocol,
+        };
+        let data = Class::CdcData;
+        let data_interface = self.first_interface.checked_add(1).ok_or(())?;
+
+        let mut union = [0; UNION_SIZE];
+        union::Descriptor {
+            bControlInterface: self.first_interface,
+            bSubordinateInterface: &[data_interface],
+        }
+        .bytes(&mut union)?;
+
+        let bulk = |direction, number| {
+            endpoint::Descriptor {
+                bEndpointAddress: Endpoint { direction, number },
+                ty: endpoint::Type::Bulk,
+                max_packet_size: self.data_max_packet_size,
+                bInterval: 0,
+            }
+            .bytes()
+        };
+
+        let mut bytes = [0; Self::SIZE as usize];
+        let mut pos = 0;
+        for part in &[
+            &ia::Descriptor {
+                bFirstInterface: self.first_interface,
+                bInterfaceCount: unsafe { NonZeroU8::new_unchecked(Self::INTERFACES) },
+                bFunctionClass: comm.class(),
+                bFunctionSubClass: comm.subclass(),
+                bFunctionProtocol: comm.protocol(),
+                iFunction: self.iFunction,
+            }
+            .bytes()[..],
+            &interface::Descriptor {
+                bInterfaceNumber: self.first_interface,
+                bAlternativeSetting: 0,
+                bNumEndpoints: 1,
+                bInterfaceClass: comm.class().get(),
+                bInterfaceSubClass: comm.subclass(),
+                bInterfaceProtocol: comm.protocol(),
+                iInterface: None,
+            }
+            .bytes(),
+            &header::Descriptor { bcdCDC: 0x0120 }.bytes(),
+            &call::Descriptor {
+                bmCapabilities: call::Capabilities {
+                    call_management: false,
+                    data_class: false,
+                },
+                bDataInterface: data_interface,
+            }
+            .bytes(),
+            &Descriptor {
+                bmCapabilities: self.capabilities,
+            }
+            .bytes(),
+            &union,
+            &endpoint::Descriptor {
+                bEndpointAddress: Endpoint {
+                    direction: crate::Direction::In,
+                    number: self.notification_endpoint,
+                },
+                ty: endpoint::Type::Interrupt {
+                    transactions_per_microframe: endpoint::Transactions::_1,
+                },
+                max_packet_size: self.notification_max_packet_size,
+                bInterval: self.notification_interval,
+            }
+            .bytes(),
+            &interface::Descriptor {
+                bInterfaceNumber: data_interface,
+                bAlternativeSetting: 0,
+                bNumEndpoints: 2,
+                bInterfaceClass: data.class().get(),
+                bInterfaceSubClass: data.subclass(),
+                bInterfaceProtocol: data.protocol(),
+                iInterface: None,
+            }
+            .bytes(),
+            &bulk(crate::Direction::Out, self.data_out_endpoint),
+            &bulk(crate::Direction::In, self.data_in_endpoint),
+        ] {
+            bytes[pos..pos + part.len()].copy_from_slice(part);
+            pos += part.len();
+        }
+
+        Ok(bytes)
+    }
+}
+
+// Size of the Union functional descriptor of a `Function` (one subordinate interface)
+const UNION_SIZE: usize = union::Descriptor {
+    bControlInterface: 0,
+    bSubordinateInterface: &[0],
+}
+.size();
+
+/// Irregular UART events reported to the host with a SERIAL_STATE notification
+///
+/// These are one-shot: they are reported once and then cleared
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    /// Received data has been discarded due to an overrun
+    Overrun,
+    /// A parity error has occurred
+    Parity,
+    /// A framing error has occurred
+    Framing,
+    /// A ring signal has been detected
+    RingSignal,
+    /// A break has been detected
+    Break,
+}
+
+/// Sans-IO state of a serial port emulated over ACM
+///
+/// Feed it the ACM requests addressed to the communications interface, the UART events and the
+/// passage of time, and read back the line configuration the UART must use and the SERIAL_STATE
+/// notifications that must be sent to the host
+pub struct Port {
+    interface: u8,
+    line_coding: LineCoding,
+    dtr: bool,
+    rts: bool,
+    // `None`: no break; `Some(None)`: break until cleared; `Some(Some(ms))`: remaining time
+    send_break: Option<Option<u32>>,
+    dsr: bool,
+    dcd: bool,
+    // one-shot events not yet reported
+    events: SerialState,
+    // carrier state in the last notification
+    reported: (bool, bool),
+}
+
+impl Port {
+    /// Creates the state of the port served by the specified communications interface
+    pub fn new(interface: u8) -> Self {
+        Port {
+            interface,
+            line_coding: LineCoding::default(),
+            dtr: false,
+            rts: false,
+            send_break: None,
+            dsr: false,
+            dcd: false,
+            events: SerialState {
+                interface,
+                bOverRun: false,
+                bParity: false,
+                bFraming: false,
+                bRingSignal: false,
+                bBreak: false,
+                bTxCarrier: false,
+                bRxCarrier: false,
+            },
+            reported: (false, false),
+        }
+    }
+
+    /// Updates the port with a request; `data` is the data stage of host-to-device requests
+    ///
+    /// Returns the reply to GET_LINE_CODING, `None` for other requests, or an error if the request
+    /// is not supported or is addressed to another interface; in that case the request must be
+    /// stalled
+    pub fn handle(
+        &mut self,
+        request: &Request,
+        data: &[u8],
+    ) -> Result<Option<[u8; LineCoding::SIZE as usize]>, ()> {
+        if request.interface != self.interface {
+            return Err(());
+        }
+
+        match request.kind {
+            Kind::GetLineCoding => return Ok(Some(self.line_coding.bytes())),
+
+            Kind::SetLineCoding => {
+                let bytes = <&[u8; LineCoding::SIZE as usize]>::try_from(data).map_err(|_| ())?;
+                self.line_coding = LineCoding::parse(bytes).map_err(|_| ())?;
+            }
+
+            Kind::SetControlLineState { dtr, rts } => {
+                self.dtr = dtr;
+                self.rts = rts;
+            }
+
+            Kind::SendBreak(duration) => {
+                self.send_break = match duration {
+                    Break::Stop => None,
+                    Break::Milliseconds(ms) => Some(Some(u32::from(ms.get()))),
+                    Break::UntilCleared => Some(None),
+                }
+            }
+
+            _ => return Err(()),
+        }
+
+        Ok(None)
+    }
+
+    /// Advances the break timer by `ms` milliseconds
+    pub fn advance(&mut self, ms: u32) {
+        if let Some(Some(remaining)) = self.send_break {
+            self.send_break = remaining
+                .checked_sub(ms)
+                .filter(|left| *left != 0)
+                .map(Some);
+        }
+    }
+
+    /// Line coding the UART must use
+    pub fn line_coding(&self) -> LineCoding {
+        self.line_coding
+    }
+
+    /// Whether the host asserts DTR
+    pub fn dtr(&self) -> bool {
+        self.dtr
+    }
+
+    /// Whether the host asserts RTS
+    pub fn rts(&self) -> bool {
+        self.rts
+    }
+
+    /// Whether the UART must send a break
+    pub fn is_break(&self) -> bool {
+        self.send_break.is_some()
+    }
+
+    /// Records an irregular UART event
+    pub fn event(&mut self, event: Event) {
+        match event {
+            Event::Overrun => self.events.bOverRun = true,
+            Event::Parity => self.events.bParity = true,
+            Event::Framing => self.events.bFraming = true,
+            Event::RingSignal => self.events.bRingSignal = true,
+            Event::Break => self.events.bBreak = true,
+        }
+    }
+
+    /// Updates the state of the DSR line (transmission carrier)
+    pub fn set_dsr(&mut self, dsr: bool) {
+        self.dsr = dsr;
+    }
+
+    /// Updates the state of the DCD line (receiver carrier)
+    pub fn set_dcd(&mut self, dcd: bool) {
+        self.dcd = dcd;
+    }
+
+    /// Returns the SERIAL_STATE notification that must be sent to the host, if any
+    ///
+    /// A notification is produced when a carrier changed or an event occurred since the last
+    /// notification. Events are cleared once reported
+    pub fn notification(&mut self) -> Option<[u8; SerialState::SIZE as usize]> {
+        let events = self.events;
+        let pending = events.bOverRun
+            || events.bParity
+            || events.bFraming
+            || events.bRingSignal
+            || events.bBreak;
+
+        if !pending && self.reported == (self.dsr, self.dcd) {
+            return None;
+        }
+
+        self.reported = (self.dsr, self.dcd);
+        self.events = SerialState {
+            bOverRun: false,
+            bParity: false,
+            bFraming: false,
+            bRingSignal: false,
+            bBreak: false,
+            ..events
+        };
+
+        Some(
+            SerialState {
+                bTxCarrier: self.dsr,
+                bRxCarrier: self.dcd,
+                ..events
+            }
+            .bytes(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU16;
+
+    use crate::cdc::Protocol;
+
+    use super::{
+        bCharFormat, bDataBits, bParityType, AbstractState, Break, Capabilities, CommFeature,
+        Event, Function, Kind, LineCoding, LineCodingError, Port, Request, SerialState,
+    };
+
+    #[test]
+    fn line_coding() {
+        let coding = LineCoding::default();
+        assert_eq!(coding.bytes(), [0x00, 0xC2, 0x01, 0x00, 0, 0, 8]);
+        assert_eq!(LineCoding::parse(&coding.bytes()), Ok(coding));
+        assert_eq!(
+            coding.uart(),
+            (115_200, 8, bParityType::None, bCharFormat::Stop1)
+        );
+        assert_eq!(coding.bParityType.letter(), 'N');
+
+        assert_eq!(
+            LineCoding::from_uart(9600, 7, bParityType::Even, bCharFormat::Stop2)
+                .map(|lc| lc.bDataBits),
+            Ok(bDataBits::_7)
+        );
+
+        assert_eq!(
+            LineCoding::parse(&[0, 0, 0, 0, 3, 0, 8]),
+            Err(LineCodingError::CharFormat(3))
+        );
+        assert_eq!(
+            LineCoding::parse(&[0, 0, 0, 0, 0, 5, 8]),
+            Err(LineCodingError::ParityType(5))
+        );
+        assert_eq!(
+            LineCoding::parse(&[0, 0, 0, 0, 0, 0, 9]),
+            Err(LineCodingError::DataBits(9))
+        );
+    }
+
+    #[test]
+    fn send_break() {
+        assert_eq!(
+            Request::parse(0b0010_0001, 0x23, 250, 0, 0),
+            Ok(Request {
+                interface: 0,
+                kind: Kind::SendBreak(Break::Milliseconds(NonZeroU16::new(250).unwrap())),
+            })
+        );
+
+        assert_eq!(
+            Request::parse(0b0010_0001, 0x23, 0xFFFF, 0, 0).map(|req| req.kind),
+            Ok(Kind::SendBreak(Break::UntilCleared))
+        );
+
+        assert_eq!(
+            Request::parse(0b0010_0001, 0x23, 0, 0, 0).map(|req| req.kind),
+            Ok(Kind::SendBreak(Break::Stop))
+        );
+    }
+
+    #[test]
+    fn comm_feature() {
+        assert_eq!(
+            Request::parse(0b1010_0001, 0x03, 0x01, 0, 2).map(|req| req.kind),
+            Ok(Kind::GetCommFeature(CommFeature::AbstractState))
+        );
+
+        // unknown feature selector
+        assert!(Request::parse(0b0010_0001, 0x02, 0x03, 0, 2).is_err());
+
+        // wrong length
+        assert!(Request::parse(0b0010_0001, 0x02, 0x02, 0, 0).is_err());
+
+        let state = AbstractState {
+            idle: true,
+            data_multiplexing: false,
+        };
+        assert_eq!(AbstractState::parse(&state.bytes()), Ok(state));
+    }
+
+    #[test]
+    fn encapsulated() {
+        assert_eq!(
+            Request::parse(0b0010_0001, 0x00, 0, 1, 16),
+            Ok(Request {
+                interface: 1,
+                kind: Kind::SendEncapsulatedCommand { length: 16 },
+            })
+        );
+
+        assert_eq!(
+            Request::parse(0b1010_0001, 0x01, 0, 1, 64).map(|req| req.kind),
+            Ok(Kind::GetEncapsulatedResponse { length: 64 })
+        );
+
+        // no command in the data stage
+        assert!(Request::parse(0b0010_0001, 0x00, 0, 1, 0).is_err());
+    }
+
+    #[test]
+    fn function() {
+        let function = Function {
+            first_interface: 2,
+            protocol: Protocol::ATCommands,
+            capabilities: Capabilities {
+                comm_features: false,
+                line_serial: true,
+                send_break: true,
+                network_connection: false,
+            },
+            iFunction: None,
+            notification_endpoint: 3,
+            notification_max_packet_size: 8,
+            notification_interval: 255,
+            data_in_endpoint: 4,
+            data_out_endpoint: 4,
+            data_max_packet_size: 64,
+        };
+
+        let bytes = function.bytes().unwrap();
+        assert_eq!(bytes.len(), 66);
+
+        // walk the descriptors
+        let mut types = [0; 10];
+        let mut pos = 0;
+        for ty in types.iter_mut() {
+            *ty = bytes[pos + 1];
+            pos += usize::from(bytes[pos]);
+        }
+        assert_eq!(pos, bytes.len());
+        assert_eq!(types, [11, 4, 0x24, 0x24, 0x24, 0x24, 5, 4, 5, 5]);
+
+        // IAD
+        assert_eq!(&bytes[..8], &[8, 11, 2, 2, 2, 2, 1, 0]);
+        // union: control interface 2, subordinate interface 3
+        assert_eq!(&bytes[31..36], &[5, 0x24, 0x06, 2, 3]);
+        // data interface
+        assert_eq!(&bytes[43..52], &[9, 4, 3, 0, 2, 10, 0, 0, 0]);
+        // bulk endpoints
+        assert_eq!(&bytes[52..55], &[7, 5, 0x04]);
+        assert_eq!(&bytes[59..62], &[7, 5, 0x84]);
+
+        // no room for the data interface
+        let function = Function {
+            first_interface: 255,
+            ..function
+        };
+        assert!(function.bytes().is_err());
+    }
+
+    #[test]
+    fn port() {
+        let mut port = Port::new(0);
+        let request = |bmrequesttype, brequest, wvalue, wlength| {
+            Request::parse(bmrequesttype, brequest, wvalue, 0, wlength).unwrap()
+        };
+
+        // line coding
+        let coding = [0x80, 0x25, 0, 0, 0, 2, 7];
+        assert_eq!(port.handle(&request(0x21, 0x20, 0, 7), &coding), Ok(None));
+        assert_eq!(
+            port.line_coding().uart(),
+            (9600, 7, bParityType::Even, bCharFormat::Stop1)
+        );
+        assert_eq!(
+            port.handle(&request(0xa1, 0x21, 0, 7), &[]),
+            Ok(Some(coding))
+        );
+        assert!(port
+            .handle(&request(0x21, 0x20, 0, 7), &coding[..6])
+            .is_err());
+
+        // control lines
+        port.handle(&request(0x21, 0x22, 0b01, 0), &[]).unwrap();
+        assert!(port.dtr() && !port.rts());
+
+        // timed break
+        port.handle(&request(0x21, 0x23, 100, 0), &[]).unwrap();
+        assert!(port.is_break());
+        port.advance(60);
+        assert!(port.is_break());
+        port.advance(40);
+        assert!(!port.is_break());
+
+        // break until cleared
+        port.handle(&request(0x21, 0x23, 0xffff, 0), &[]).unwrap();
+        port.advance(1_000);
+        assert!(port.is_break());
+        port.handle(&request(0x21, 0x23, 0, 0), &[]).unwrap();
+        assert!(!port.is_break());
+
+        // another interface
+        assert!(port
+            .handle(&Request::parse(0x21, 0x22, 0, 1, 0).unwrap(), &[])
+            .is_err());
+    }
+
+    #[test]
+    fn serial_state() {
+        let mut port = Port::new(0);
+        assert_eq!(port.notification(), None);
+
+        port.set_dcd(true);
+        port.set_dsr(true);
+        let state = SerialState::parse(&port.notification().unwrap()).unwrap();
+        assert!(state.bRxCarrier && state.bTxCarrier && !state.bOverRun);
+        assert_eq!(port.notification(), None);
+
+        // one-shot events are reported once
+        port.event(Event::Overrun);
+        port.event(Event::Framing);
+        let state = SerialState::parse(&port.notification().unwrap()).unwrap();
+        assert!(state.bOverRun && state.bFraming && !state.bParity && state.bRxCarrier);
+        assert_eq!(port.notification(), None);
+
+        port.set_dsr(false);
+        let state = SerialState::parse(&port.notification().unwrap()).unwrap();
+        assert!(!state.bTxCarrier && !state.bOverRun);
+    }
+}
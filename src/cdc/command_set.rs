@@ -0,0 +1,72 @@
+//! Command Set functional descriptor
+
+/// Command Set functional descriptor
+///
+/// Identifies the command set used by an interface whose protocol is `Protocol::External`
+///
+/// See section 6.5.2.2 of (USBWMC1.1)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Descriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Version of the Command Set functional descriptor (Binary-coded Decimal); currently 0x0100
+    pub bcdVersion: u16,
+    /// Index of the string descriptor that describes the command set
+    pub iCommandSet: u8,
+    /// GUID of the command set
+    pub guidCommandSet: [u8; 16],
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 22;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[0] = Self::SIZE;
+        bytes[1] = super::CS_INTERFACE;
+        bytes[2] = super::SUBTYPE_COMMAND_SET;
+        bytes[3..5].copy_from_slice(&self.bcdVersion.to_le_bytes());
+        bytes[5] = self.iCommandSet;
+        bytes[6..].copy_from_slice(&self.guidCommandSet);
+        bytes
+    }
+
+    /// Parses a Command Set functional descriptor
+    ///
+    /// `bytes` may contain more descriptors after this one; they are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let bytes = super::parse_functional(bytes, super::SUBTYPE_COMMAND_SET, Self::SIZE)?;
+
+        let mut guidCommandSet = [0; 16];
+        guidCommandSet.copy_from_slice(&bytes[6..]);
+
+        Ok(Descriptor {
+            bcdVersion: u16::from_le_bytes([bytes[3], bytes[4]]),
+            iCommandSet: bytes[5],
+            guidCommandSet,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Descriptor;
+
+    #[test]
+    fn round_trip() {
+        let desc = Descriptor {
+            bcdVersion: 0x0100,
+            iCommandSet: 4,
+            guidCommandSet: [0xab; 16],
+        };
+
+        let bytes = desc.bytes();
+        assert_eq!(&bytes[..6], &[22, 0x24, 0x16, 0x00, 0x01, 4]);
+        assert_eq!(Descriptor::parse(&bytes), Ok(desc));
+        assert!(Descriptor::parse(&bytes[..21]).is_err());
+    }
+}
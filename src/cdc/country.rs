@@ -0,0 +1,97 @@
+//! Country Selection functional descriptor
+
+/// Country Selection functional descriptor
+///
+/// See section 5.2.3.9 of (USBCDC1.2)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Descriptor<'a> {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Index of the string descriptor that holds the release date of the ISO 3166 country codes
+    pub iCountryCodeRelDate: u8,
+    /// Supported country codes, as little endian 16-bit values; there must be at least one. See
+    /// `country_codes`
+    pub wCountryCode: &'a [u8],
+}
+
+impl<'a> Descriptor<'a> {
+    /// Size of this descriptor on the wire
+    pub fn size(&self) -> usize {
+        4 + self.wCountryCode.len()
+    }
+
+    /// Returns an iterator over the supported country codes
+    pub fn country_codes(&self) -> impl Iterator<Item = u16> + 'a {
+        self.wCountryCode
+            .chunks_exact(2)
+            .map(|code| u16::from_le_bytes([code[0], code[1]]))
+    }
+
+    /// Writes the wire representation of this descriptor into `buf`
+    ///
+    /// Returns the size of the descriptor, or an error if `buf` is too small or the country codes
+    /// are malformed
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let size = self.size();
+        if self.wCountryCode.is_empty()
+            || self.wCountryCode.len() % 2 != 0
+            || size > usize::from(u8::MAX)
+        {
+            return Err(());
+        }
+
+        let buf = buf.get_mut(..size).ok_or(())?;
+        buf[0] = size as u8;
+        buf[1] = super::CS_INTERFACE;
+        buf[2] = super::SUBTYPE_COUNTRY;
+        buf[3] = self.iCountryCodeRelDate;
+        buf[4..].copy_from_slice(self.wCountryCode);
+
+        Ok(size)
+    }
+
+    /// Parses a Country Selection functional descriptor
+    ///
+    /// `bytes` may contain more descriptors after this one; they are ignored
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ()> {
+        let size = usize::from(*bytes.first().ok_or(())?);
+        let bytes = bytes.get(..size).ok_or(())?;
+
+        if size < 6
+            || size % 2 != 0
+            || bytes[1] != super::CS_INTERFACE
+            || bytes[2] != super::SUBTYPE_COUNTRY
+        {
+            return Err(());
+        }
+
+        Ok(Descriptor {
+            iCountryCodeRelDate: bytes[3],
+            wCountryCode: &bytes[4..],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Descriptor;
+
+    #[test]
+    fn round_trip() {
+        let desc = Descriptor {
+            iCountryCodeRelDate: 5,
+            wCountryCode: b"SUED",
+        };
+
+        let mut buf = [0; 16];
+        let n = desc.bytes(&mut buf).unwrap();
+        assert_eq!(&buf[..n], &[8, 0x24, 0x07, 5, b'S', b'U', b'E', b'D']);
+        assert_eq!(Descriptor::parse(&buf[..n]), Ok(desc));
+        assert!(desc.country_codes().eq([0x5553, 0x4445].iter().cloned()));
+
+        // odd number of bytes
+        assert!(Descriptor::parse(&[7, 0x24, 0x07, 5, 1, 2, 3]).is_err());
+    }
+}
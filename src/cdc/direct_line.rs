@@ -0,0 +1,106 @@
+//! Direct Line Management functional descriptor
+
+/// Direct Line Management functional descriptor
+///
+/// See section 5.3.1 of (USBPTSN1.2)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Descriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Capabilities
+    pub bmCapabilities: Capabilities,
+}
+
+/// Capabilities
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Capabilities {
+    /// Device supports the PULSE_SETUP, SEND_PULSE and SET_PULSE_TIME requests
+    pub pulse: bool,
+    /// Device supports the SET_AUX_LINE_STATE and RING_AUX_JACK requests and the
+    /// AUX_JACK_HOOK_STATE notification
+    pub aux: bool,
+    /// Device requires an extra PULSE_SETUP request during pulse dialing to disengage the holding
+    /// circuit
+    pub extra_pulse_setup: bool,
+}
+
+impl Capabilities {
+    fn byte(&self) -> u8 {
+        let mut byte = 0;
+        if self.pulse {
+            byte |= 1 << 0;
+        }
+        if self.aux {
+            byte |= 1 << 1;
+        }
+        if self.extra_pulse_setup {
+            byte |= 1 << 2;
+        }
+        byte
+    }
+
+    fn parse(byte: u8) -> Result<Self, ()> {
+        if byte & !0b111 != 0 {
+            return Err(());
+        }
+
+        Ok(Capabilities {
+            pulse: byte & (1 << 0) != 0,
+            aux: byte & (1 << 1) != 0,
+            extra_pulse_setup: byte & (1 << 2) != 0,
+        })
+    }
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 4;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            super::SUBTYPE_DIRECT_LINE,
+            self.bmCapabilities.byte(),
+        ]
+    }
+
+    /// Parses a Direct Line Management functional descriptor
+    ///
+    /// `bytes` may contain more descriptors after this one; they are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let bytes = super::parse_functional(bytes, super::SUBTYPE_DIRECT_LINE, Self::SIZE)?;
+
+        Ok(Descriptor {
+            bmCapabilities: Capabilities::parse(bytes[3])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capabilities, Descriptor};
+
+    #[test]
+    fn round_trip() {
+        let desc = Descriptor {
+            bmCapabilities: Capabilities {
+                pulse: true,
+                aux: true,
+                ..Capabilities::default()
+            },
+        };
+
+        let bytes = desc.bytes();
+        assert_eq!(bytes, [4, 0x24, 0x03, 0b011]);
+        assert_eq!(Descriptor::parse(&bytes), Ok(desc));
+
+        // reserved bits
+        assert!(Descriptor::parse(&[4, 0x24, 0x03, 0b1000]).is_err());
+        // too short
+        assert!(Descriptor::parse(&[3, 0x24, 0x03]).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+//! Ethernet Control Model (ECM)
+//!
+//! The NETWORK_CONNECTION and CONNECTION_SPEED_CHANGE notifications used by ECM devices are in the
+//! `notification` module
+//!
+//! See (USBECM1.2)
+
+use core::{num::NonZeroU8, slice::ChunksExact};
+
+use crate::bmrequesttype::{bmRequestType, Direction, Recipient, Type};
+
+/// ECM request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Request {
+    /// Interface index
+    pub interface: u8,
+    /// Kind of request
+    pub kind: Kind,
+}
+
+/// ECM request kind
+///
+/// See section 6.2 of (USBECM1.2)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// SET_ETHERNET_MULTICAST_FILTERS -- the 6-byte MAC addresses follow in the data stage; see
+    /// `multicast_addresses`
+    SetEthernetMulticastFilters {
+        /// Number of filters
+        count: u16,
+    },
+    /// SET_ETHERNET_POWER_MANAGEMENT_PATTERN_FILTER -- the pattern follows in the data stage
+    SetEthernetPowerManagementPatternFilter {
+        /// Filter number
+        filter: u16,
+        /// Length of the pattern
+        length: u16,
+    },
+    /// GET_ETHERNET_POWER_MANAGEMENT_PATTERN_FILTER -- returns whether the filter is active (2
+    /// bytes)
+    GetEthernetPowerManagementPatternFilter {
+        /// Filter number
+        filter: u16,
+    },
+    /// SET_ETHERNET_PACKET_FILTER
+    SetEthernetPacketFilter(PacketFilter),
+    /// GET_ETHERNET_STATISTIC -- returns the statistic counter (4 bytes)
+    GetEthernetStatistic(Statistic),
+}
+
+const SET_ETHERNET_MULTICAST_FILTERS: u8 = 0x40;
+const SET_ETHERNET_POWER_MANAGEMENT_PATTERN_FILTER: u8 = 0x41;
+const GET_ETHERNET_POWER_MANAGEMENT_PATTERN_FILTER: u8 = 0x42;
+const SET_ETHERNET_PACKET_FILTER: u8 = 0x43;
+const GET_ETHERNET_STATISTIC: u8 = 0x44;
+
+/// Splits the data stage of a SET_ETHERNET_MULTICAST_FILTERS request into MAC addresses
+pub fn multicast_addresses(data: &[u8]) -> Result<ChunksExact<'_, u8>, ()> {
+    if data.len() % 6 != 0 {
+        return Err(());
+    }
+
+    Ok(data.chunks_exact(6))
+}
+
+/// Ethernet packet filter
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PacketFilter {
+    /// Forward all packets
+    pub promiscuous: bool,
+    /// Forward all multicast packets
+    pub all_multicast: bool,
+    /// Forward directed packets
+    pub directed: bool,
+    /// Forward broadcast packets
+    pub broadcast: bool,
+    /// Forward the multicast packets listed in the multicast filters
+    pub multicast: bool,
+}
+
+impl PacketFilter {
+    /// Returns the wValue representation of this filter
+    pub fn bits(&self) -> u16 {
+        let mut bits = 0;
+        if self.promiscuous {
+            bits |= 1 << 0;
+        }
+        if self.all_multicast {
+            bits |= 1 << 1;
+        }
+        if self.directed {
+            bits |= 1 << 2;
+        }
+        if self.broadcast {
+            bits |= 1 << 3;
+        }
+        if self.multicast {
+            bits |= 1 << 4;
+        }
+        bits
+    }
+
+    fn parse(bits: u16) -> Result<Self, ()> {
+        if bits & !0b1_1111 != 0 {
+            return Err(());
+        }
+
+        Ok(PacketFilter {
+            promiscuous: bits & (1 << 0) != 0,
+            all_multicast: bits & (1 << 1) != 0,
+            directed: bits & (1 << 2) != 0,
+            broadcast: bits & (1 << 3) != 0,
+            multicast: bits & (1 << 4) != 0,
+        })
+    }
+}
+
+repr!(u16,
+      /// Ethernet statistics feature selector
+      Statistic {
+    /// Frames transmitted without errors
+    XmitOk = 1,
+    /// Frames received without errors
+    RcvOk = 2,
+    /// Frames not transmitted, or transmitted with errors
+    XmitError = 3,
+    /// Frames received with errors that are not delivered to the USB host
+    RcvError = 4,
+    /// Frame missed, no buffers
+    RcvNoBuffer = 5,
+    /// Directed bytes transmitted without errors
+    DirectedBytesXmit = 6,
+    /// Directed frames transmitted without errors
+    DirectedFramesXmit = 7,
+    /// Multicast bytes transmitted without errors
+    MulticastBytesXmit = 8,
+    /// Multicast frames transmitted without errors
+    MulticastFramesXmit = 9,
+    /// Broadcast bytes transmitted without errors
+    BroadcastBytesXmit = 10,
+    /// Broadcast frames transmitted without errors
+    BroadcastFramesXmit = 11,
+    /// Directed bytes received without errors
+    DirectedBytesRcv = 12,
+    /// Directed frames received without errors
+    DirectedFramesRcv = 13,
+    /// Multicast bytes received without errors
+    MulticastBytesRcv = 14,
+    /// Multicast frames received without errors
+    MulticastFramesRcv = 15,
+    /// Broadcast bytes received without errors
+    BroadcastBytesRcv = 16,
+    /// Broadcast frames received without errors
+    BroadcastFramesRcv = 17,
+    /// Frames received with CRC or FCS errors
+    RcvCrcError = 18,
+    /// Length of transmit queue
+    TransmitQueueLength = 19,
+    /// Frames received with alignment errors
+    RcvErrorAlignment = 20,
+    /// Frames transmitted with one collision
+    XmitOneCollision = 21,
+    /// Frames transmitted with more than one collision
+    XmitMoreCollisions = 22,
+    /// Frames transmitted after deferral
+    XmitDeferred = 23,
+    /// Frames not transmitted due to collisions
+    XmitMaxCollisions = 24,
+    /// Frames not received due to overrun
+    RcvOverrun = 25,
+    /// Frames not transmitted due to underrun
+    XmitUnderrun = 26,
+    /// Frames transmitted with heartbeat failure
+    XmitHeartbeatFailure = 27,
+    /// Times carrier sense signal lost during transmission
+    XmitTimesCrsLost = 28,
+    /// Late collisions detected
+    XmitLateCollisions = 29,
+});
+
+impl Statistic {
+    /// Returns the bit that advertises this statistic in `bmEthernetStatistics`
+    pub fn mask(&self) -> u32 {
+        1 << (*self as u16 - 1)
+    }
+}
+
+impl Request {
+    /// Parses an ECM request
+    pub fn parse(
+        bmrequesttype: u8,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        let bmrequesttype = bmRequestType::parse(bmrequesttype)?;
+
+        if bmrequesttype.ty != Type::Class {
+            return Err(());
+        }
+
+        Self::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+    }
+
+    pub(crate) fn parse2(
+        bmRequestType {
+            direction,
+            recipient,
+            // ty must be `Class`
+            ..
+        }: bmRequestType,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        if recipient != Recipient::Interface {
+            return Err(());
+        }
+
+        let kind = match (brequest, direction) {
+            (SET_ETHERNET_MULTICAST_FILTERS, Direction::HostToDevice)
+                if u32::from(wlength) == 6 * u32::from(wvalue) =>
+            {
+                Kind::SetEthernetMulticastFilters { count: wvalue }
+            }
+
+            (SET_ETHERNET_POWER_MANAGEMENT_PATTERN_FILTER, Direction::HostToDevice) => {
+                Kind::SetEthernetPowerManagementPatternFilter {
+                    filter: wvalue,
+                    length: wlength,
+                }
+            }
+
+            (GET_ETHERNET_POWER_MANAGEMENT_PATTERN_FILTER, Direction::DeviceToHost)
+                if wlength == 2 =>
+            {
+                Kind::GetEthernetPowerManagementPatternFilter { filter: wvalue }
+            }
+
+            (SET_ETHERNET_PACKET_FILTER, Direction::HostToDevice) if wlength == 0 => {
+                Kind::SetEthernetPacketFilter(PacketFilter::parse(wvalue)?)
+            }
+
+            (GET_ETHERNET_STATISTIC, Direction::DeviceToHost) if wlength == 4 => {
+                Kind::GetEthernetStatistic(Statistic::_from(wvalue).ok_or(())?)
+            }
+
+            _ => return Err(()),
+        };
+
+        Ok(Request {
+            interface: crate::windex2interface(windex)?,
+            kind,
+        })
+    }
+}
+
+/// Ethernet Networking functional descriptor
+///
+/// See section 5.4 of (USBECM1.2)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct Descriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Index of the string descriptor that holds the MAC address (12 hexadecimal digits)
+    pub iMACAddress: NonZeroU8,
+    /// Statistics collected by the device; see `Statistic::mask`
+    pub bmEthernetStatistics: u32,
+    /// Maximum segment size; usually 1514 bytes
+    pub wMaxSegmentSize: u16,
+    /// Number of multicast filters; bit 15 set means the filtering is imperfect
+    pub wNumberMCFilters: u16,
+    /// Number of pattern filters available for causing wake-up of the host
+    pub bNumberPowerFilters: u8,
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 13;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            super::SUBTYPE_ETHERNET,
+            self.iMACAddress.get(),
+            self.bmEthernetStatistics as u8,
+            (self.bmEthernetStatistics >> 8) as u8,
+            (self.bmEthernetStatistics >> 16) as u8,
+            (self.bmEthernetStatistics >> 24) as u8,
+            self.wMaxSegmentSize as u8,
+            (self.wMaxSegmentSize >> 8) as u8,
+            self.wNumberMCFilters as u8,
+            (self.wNumberMCFilters >> 8) as u8,
+            self.bNumberPowerFilters,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Request;
+
+    use super::{Kind, PacketFilter, Statistic};
+
+    #[test]
+    fn packet_filter() {
+        assert_eq!(
+            Request::parse(0b0010_0001, 0x43, 0b0_1100, 0, 0),
+            Ok(Request::Ecm(super::Request {
+                interface: 0,
+                kind: Kind::SetEthernetPacketFilter(PacketFilter {
+                    directed: true,
+                    broadcast: true,
+                    ..PacketFilter::default()
+                }),
+            }))
+        );
+
+        // reserved bits
+        assert!(Request::parse(0b0010_0001, 0x43, 1 << 5, 0, 0).is_err());
+    }
+
+    #[test]
+    fn multicast_filters() {
+        assert_eq!(
+            super::Request::parse(0b0010_0001, 0x40, 2, 0, 12).map(|req| req.kind),
+            Ok(Kind::SetEthernetMulticastFilters { count: 2 })
+        );
+
+        // length doesn't match the number of filters
+        assert!(super::Request::parse(0b0010_0001, 0x40, 2, 0, 6).is_err());
+
+        let data = [1, 0, 0x5e, 0, 0, 1, 1, 0, 0x5e, 0, 0, 0xfb];
+        let mut addresses = super::multicast_addresses(&data).unwrap();
+        assert_eq!(addresses.next(), Some(&[1, 0, 0x5e, 0, 0, 1][..]));
+        assert_eq!(addresses.next(), Some(&[1, 0, 0x5e, 0, 0, 0xfb][..]));
+        assert_eq!(addresses.next(), None);
+    }
+
+    #[test]
+    fn statistic() {
+        assert_eq!(
+            super::Request::parse(0b1010_0001, 0x44, 2, 0, 4).map(|req| req.kind),
+            Ok(Kind::GetEthernetStatistic(Statistic::RcvOk))
+        );
+        assert_eq!(Statistic::XmitOk.mask(), 1);
+        assert_eq!(Statistic::XmitLateCollisions.mask(), 1 << 28);
+    }
+}
@@ -0,0 +1,375 @@
+//! Ethernet Emulation Model (EEM)
+//!
+//! EEM functions have no class-specific descriptors or requests; Ethernet frames and commands are
+//! exchanged as EEM packets over the bulk endpoints. A bulk transfer may hold several packets and
+//! a packet may span several transfers
+//!
+//! See (USBEEM1.0)
+
+use crate::crc::crc32;
+
+/// Maximum length of an Ethernet frame carried by a data packet, excluding its CRC
+pub const MAX_FRAME_SIZE: usize = 0x3FFF - CRC_SIZE;
+
+/// Maximum length of the payload of an Echo or Echo Response command
+pub const MAX_ECHO_SIZE: usize = 0x7FF;
+
+// sent in place of the CRC when bmCRC is not set
+const SENTINEL: [u8; 4] = [0xDE, 0xAD, 0xBE, 0xEF];
+
+const CRC_SIZE: usize = 4;
+const HEADER_SIZE: usize = 2;
+
+const BM_TYPE: u16 = 1 << 15;
+const BM_CRC: u16 = 1 << 14;
+
+const ECHO: u16 = 0;
+const ECHO_RESPONSE: u16 = 1;
+const SUSPEND_HINT: u16 = 2;
+const RESPONSE_HINT: u16 = 3;
+const RESPONSE_COMPLETE_HINT: u16 = 4;
+const TICKLE: u16 = 5;
+
+/// EEM packet
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Packet<'a> {
+    /// Ethernet frame, without its CRC
+    Data(&'a [u8]),
+    /// EEM command
+    Command(Command<'a>),
+}
+
+/// EEM command
+///
+/// See section 5.1.2 of (USBEEM1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command<'a> {
+    /// Echo -- the receiver must reply with an Echo Response carrying the same payload
+    Echo(&'a [u8]),
+    /// Echo Response
+    EchoResponse(&'a [u8]),
+    /// Suspend Hint -- the device has nothing else to send and the host may suspend the bus
+    SuspendHint,
+    /// Response Hint -- the device expects to send data soon
+    ResponseHint {
+        /// Time, in milliseconds, within which the device expects to send data (11 bits)
+        param: u16,
+    },
+    /// Response Complete Hint -- the device has nothing else to send
+    ResponseCompleteHint,
+    /// Tickle -- the device wants the host to keep polling
+    Tickle,
+}
+
+impl<'a> Packet<'a> {
+    /// Parses the packet at the start of `bytes`
+    ///
+    /// Returns the packet and its size on the wire, or `None` if `bytes` holds only part of the
+    /// packet. Zero-length EEM packets are returned as `None` with a size of 2
+    pub fn parse(bytes: &'a [u8]) -> Result<(Option<Self>, usize), ()> {
+        let header = match bytes.get(..HEADER_SIZE) {
+            Some(header) => u16::from_le_bytes([header[0], header[1]]),
+            None => return Ok((None, 0)),
+        };
+
+        if header == 0 {
+            // zero-length EEM
+            return Ok((None, HEADER_SIZE));
+        }
+
+        if header & BM_TYPE == 0 {
+            let length = usize::from(header & 0x3FFF);
+            if length < CRC_SIZE {
+                return Err(());
+            }
+
+            let size = HEADER_SIZE + length;
+            let packet = match bytes.get(HEADER_SIZE..size) {
+                Some(packet) => packet,
+                None => return Ok((None, 0)),
+            };
+
+            let (frame, crc) = packet.split_at(length - CRC_SIZE);
+            let ok = if header & BM_CRC != 0 {
+                crc == crc32(frame).to_le_bytes()
+            } else {
+                crc == SENTINEL
+            };
+
+            if ok {
+                Ok((Some(Packet::Data(frame)), size))
+            } else {
+                Err(())
+            }
+        } else {
+            if header & BM_CRC != 0 {
+                // reserved
+                return Err(());
+            }
+
+            let param = header & 0x7FF;
+            let command = match (header >> 11) & 0b111 {
+                ECHO | ECHO_RESPONSE => {
+                    let size = HEADER_SIZE + usize::from(param);
+                    let payload = match bytes.get(HEADER_SIZE..size) {
+                        Some(payload) => payload,
+                        None => return Ok((None, 0)),
+                    };
+
+                    let command = if (header >> 11) & 0b111 == ECHO {
+                        Command::Echo(payload)
+                    } else {
+                        Command::EchoResponse(payload)
+                    };
+                    return Ok((Some(Packet::Command(command)), size));
+                }
+                SUSPEND_HINT => Command::SuspendHint,
+                RESPONSE_HINT => Command::ResponseHint { param },
+                RESPONSE_COMPLETE_HINT => Command::ResponseCompleteHint,
+                TICKLE => Command::Tickle,
+                _ => return Err(()),
+            };
+
+            Ok((Some(Packet::Command(command)), HEADER_SIZE))
+        }
+    }
+
+    fn header(&self, crc: bool) -> Result<u16, ()> {
+        Ok(match self {
+            Packet::Data(frame) => {
+                if frame.len() > MAX_FRAME_SIZE {
+                    return Err(());
+                }
+
+                let length = (frame.len() + CRC_SIZE) as u16;
+                if crc {
+                    BM_CRC | length
+                } else {
+                    length
+                }
+            }
+            Packet::Command(command) => {
+                let (cmd, param) = match command {
+                    Command::Echo(payload) => (ECHO, payload.len()),
+                    Command::EchoResponse(payload) => (ECHO_RESPONSE, payload.len()),
+                    Command::SuspendHint => (SUSPEND_HINT, 0),
+                    Command::ResponseHint { param } => (RESPONSE_HINT, usize::from(*param)),
+                    Command::ResponseCompleteHint => (RESPONSE_COMPLETE_HINT, 0),
+                    Command::Tickle => (TICKLE, 0),
+                };
+
+                if param > 0x7FF {
+                    return Err(());
+                }
+
+                BM_TYPE | cmd << 11 | param as u16
+            }
+        })
+    }
+}
+
+/// Packs EEM packets into a bulk transfer
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    /// Starts a new transfer in `buf`
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Writer { buf, len: 0 }
+    }
+
+    /// Appends `packet` to the transfer
+    ///
+    /// Data packets carry the CRC of the frame if `crc` is set, and the 0xDEADBEEF sentinel
+    /// otherwise; `crc` is ignored for commands. Returns an error if the packet is invalid or
+    /// doesn't fit in the buffer
+    pub fn push(&mut self, packet: &Packet, crc: bool) -> Result<(), ()> {
+        let header = packet.header(crc)?;
+        let payload: &[u8] = match packet {
+            Packet::Data(frame) => frame,
+            Packet::Command(Command::Echo(payload))
+            | Packet::Command(Command::EchoResponse(payload)) => payload,
+            Packet::Command(_) => &[],
+        };
+
+        let trailer = if let Packet::Data(_) = packet {
+            CRC_SIZE
+        } else {
+            0
+        };
+        let size = HEADER_SIZE + payload.len() + trailer;
+        let bytes = self.buf.get_mut(self.len..self.len + size).ok_or(())?;
+
+        bytes[..HEADER_SIZE].copy_from_slice(&header.to_le_bytes());
+        bytes[HEADER_SIZE..HEADER_SIZE + payload.len()].copy_from_slice(payload);
+        if trailer != 0 {
+            let trailer = if crc {
+                crc32(payload).to_le_bytes()
+            } else {
+                SENTINEL
+            };
+            bytes[size - CRC_SIZE..].copy_from_slice(&trailer);
+        }
+        self.len += size;
+
+        Ok(())
+    }
+
+    /// Returns the size of the transfer
+    pub fn finish(self) -> usize {
+        self.len
+    }
+}
+
+/// Splits a stream of bulk transfers into EEM packets
+///
+/// Bytes that belong to an incomplete packet are kept in the buffer until the rest of the packet
+/// arrives
+pub struct Reader<'a> {
+    buf: &'a mut [u8],
+    start: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Creates a reader that uses `buf` to reassemble packets
+    ///
+    /// `buf` must be able to hold the largest expected packet plus one bulk transfer
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Reader {
+            buf,
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Appends a received bulk transfer
+    ///
+    /// Returns an error if the buffer can't hold the transfer
+    pub fn extend(&mut self, transfer: &[u8]) -> Result<(), ()> {
+        // move the pending bytes to the front of the buffer
+        self.buf.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+
+        let end = self.end + transfer.len();
+        self.buf
+            .get_mut(self.end..end)
+            .ok_or(())?
+            .copy_from_slice(transfer);
+        self.end = end;
+
+        Ok(())
+    }
+
+    /// Returns the next complete packet, or `None` if more data is needed
+    ///
+    /// A malformed header discards all the buffered data; a data packet with a bad CRC is
+    /// discarded on its own. Both cases are reported as an error
+    pub fn read(&mut self) -> Option<Result<Packet<'_>, ()>> {
+        loop {
+            let bytes = &self.buf[self.start..self.end];
+            match Packet::parse(bytes) {
+                Ok((_, 0)) => return None,
+                Ok((None, size)) => self.start += size,
+                Ok((Some(packet), size)) => {
+                    self.start += size;
+                    return Some(Ok(packet));
+                }
+                Err(()) => {
+                    if let Some(header) = bytes.get(..HEADER_SIZE) {
+                        let header = u16::from_le_bytes([header[0], header[1]]);
+                        let length = usize::from(header & 0x3FFF);
+                        if header & BM_TYPE == 0 && length >= CRC_SIZE {
+                            // bad CRC; skip the packet
+                            self.start += HEADER_SIZE + length;
+                            return Some(Err(()));
+                        }
+                    }
+
+                    // lost synchronization with the stream
+                    self.start = 0;
+                    self.end = 0;
+                    return Some(Err(()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, Packet, Reader, Writer};
+
+    #[test]
+    fn roundtrip() {
+        let frame = [0x5a; 60];
+        let packets = [
+            Packet::Data(&frame),
+            Packet::Command(Command::Echo(b"ping")),
+            Packet::Command(Command::ResponseHint { param: 100 }),
+            Packet::Data(&frame[..14]),
+        ];
+
+        let mut buf = [0; 256];
+        let mut writer = Writer::new(&mut buf);
+        for (i, packet) in packets.iter().enumerate() {
+            writer.push(packet, i == 0).unwrap();
+        }
+        let n = writer.finish();
+        assert_eq!(n, 2 + 64 + 2 + 4 + 2 + 2 + 18);
+
+        // sentinel instead of CRC
+        assert_eq!(&buf[n - 4..n], &[0xde, 0xad, 0xbe, 0xef]);
+
+        // deliver the packets in odd-sized transfers, with a zero-length EEM in between
+        let mut stream = [0; 258];
+        stream[..n].copy_from_slice(&buf[..n]);
+        let stream = &stream[..n + 2];
+
+        let mut rx = [0; 128];
+        let mut reader = Reader::new(&mut rx);
+        let mut i = 0;
+        for transfer in stream.chunks(7) {
+            reader.extend(transfer).unwrap();
+            while let Some(packet) = reader.read() {
+                assert_eq!(packet, Ok(packets[i]));
+                i += 1;
+            }
+        }
+        assert_eq!(i, packets.len());
+    }
+
+    #[test]
+    fn bad_crc() {
+        let mut buf = [0; 64];
+        let mut writer = Writer::new(&mut buf);
+        writer.push(&Packet::Data(&[1, 2, 3]), true).unwrap();
+        writer
+            .push(&Packet::Command(Command::Tickle), false)
+            .unwrap();
+        let n = writer.finish();
+        buf[2] ^= 0xff;
+
+        let mut rx = [0; 64];
+        let mut reader = Reader::new(&mut rx);
+        reader.extend(&buf[..n]).unwrap();
+        assert_eq!(reader.read(), Some(Err(())));
+        assert_eq!(reader.read(), Some(Ok(Packet::Command(Command::Tickle))));
+        assert_eq!(reader.read(), None);
+    }
+
+    #[test]
+    fn parse() {
+        // reserved command
+        assert!(Packet::parse(&[0x00, 0xb0]).is_err());
+        // incomplete echo
+        assert_eq!(Packet::parse(&[0x04, 0x80, 1, 2]), Ok((None, 0)));
+        assert_eq!(
+            Packet::parse(&[0x00, 0xa8]),
+            Ok((Some(Packet::Command(Command::Tickle)), 2))
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Telephone Call and Line State Reporting Capabilities functional descriptor
+
+/// Telephone Call and Line State Reporting Capabilities functional descriptor
+///
+/// See section 5.3.4 of (USBPTSN1.2)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Descriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Capabilities
+    pub bmCapabilities: Capabilities,
+}
+
+/// Call and line state reporting capabilities
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Capabilities {
+    /// Reports interrupted dialtone in addition to normal dialtone
+    pub interrupted_dialtone: bool,
+    /// Reports ringback, busy and fast busy states
+    pub ringback: bool,
+    /// Reports caller ID information
+    pub caller_id: bool,
+    /// Reports incoming distinctive ring patterns
+    pub distinctive_ring: bool,
+    /// Can report DTMF digits input remotely over the telephone line
+    pub dtmf: bool,
+    /// Supports the line state change notification
+    pub line_state_change: bool,
+}
+
+impl Capabilities {
+    fn word(&self) -> u32 {
+        let mut word = 0;
+        if self.interrupted_dialtone {
+            word |= 1 << 0;
+        }
+        if self.ringback {
+            word |= 1 << 1;
+        }
+        if self.caller_id {
+            word |= 1 << 2;
+        }
+        if self.distinctive_ring {
+            word |= 1 << 3;
+        }
+        if self.dtmf {
+            word |= 1 << 4;
+        }
+        if self.line_state_change {
+            word |= 1 << 5;
+        }
+        word
+    }
+
+    fn parse(word: u32) -> Result<Self, ()> {
+        if word & !0b11_1111 != 0 {
+            return Err(());
+        }
+
+        Ok(Capabilities {
+            interrupted_dialtone: word & (1 << 0) != 0,
+            ringback: word & (1 << 1) != 0,
+            caller_id: word & (1 << 2) != 0,
+            distinctive_ring: word & (1 << 3) != 0,
+            dtmf: word & (1 << 4) != 0,
+            line_state_change: word & (1 << 5) != 0,
+        })
+    }
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 7;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let word = self.bmCapabilities.word().to_le_bytes();
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            super::SUBTYPE_LINE_STATE,
+            word[0],
+            word[1],
+            word[2],
+            word[3],
+        ]
+    }
+
+    /// Parses a Telephone Call and Line State Reporting Capabilities functional descriptor
+    ///
+    /// `bytes` may contain more descriptors after this one; they are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let bytes = super::parse_functional(bytes, super::SUBTYPE_LINE_STATE, Self::SIZE)?;
+
+        Ok(Descriptor {
+            bmCapabilities: Capabilities::parse(u32::from_le_bytes([
+                bytes[3], bytes[4], bytes[5], bytes[6],
+            ]))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capabilities, Descriptor};
+
+    #[test]
+    fn round_trip() {
+        let desc = Descriptor {
+            bmCapabilities: Capabilities {
+                caller_id: true,
+                line_state_change: true,
+                ..Capabilities::default()
+            },
+        };
+
+        assert_eq!(desc.bytes(), [7, 0x24, 0x05, 0b10_0100, 0, 0, 0]);
+        assert_eq!(Descriptor::parse(&desc.bytes()), Ok(desc));
+
+        // reserved bit
+        assert!(Descriptor::parse(&[7, 0x24, 0x05, 0, 0, 0, 0x80]).is_err());
+        // wrong subtype
+        assert!(Descriptor::parse(&[7, 0x24, 0x04, 0, 0, 0, 0]).is_err());
+    }
+}
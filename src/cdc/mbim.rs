@@ -0,0 +1,803 @@
+//! Mobile Broadband Interface Model (MBIM)
+//!
+//! Control messages are sent by the host with SEND_ENCAPSULATED_COMMAND and read with
+//! GET_ENCAPSULATED_RESPONSE; those requests are parsed as `acm::Request`. The NTB requests are
+//! shared with NCM and parsed as `ncm::Request`
+//!
+//! See (MBIM1.0)
+
+use crate::bmrequesttype::{bmRequestType, Direction, Recipient, Type};
+
+/// MBIM request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Request {
+    /// Interface index
+    pub interface: u8,
+    /// Kind of request
+    pub kind: Kind,
+}
+
+/// MBIM request kind
+///
+/// See section 9.3 of (MBIM1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// RESET_FUNCTION
+    ResetFunction,
+}
+
+const RESET_FUNCTION: u8 = 0x05;
+
+impl Request {
+    /// Parses an MBIM request
+    pub fn parse(
+        bmrequesttype: u8,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        let bmrequesttype = bmRequestType::parse(bmrequesttype)?;
+
+        if bmrequesttype.ty != Type::Class {
+            return Err(());
+        }
+
+        Self::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+    }
+
+    pub(crate) fn parse2(
+        bmRequestType {
+            direction,
+            recipient,
+            // ty must be `Class`
+            ..
+        }: bmRequestType,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        if recipient != Recipient::Interface {
+            return Err(());
+        }
+
+        let kind = match (brequest, direction, wvalue, wlength) {
+            (RESET_FUNCTION, Direction::HostToDevice, 0, 0) => Kind::ResetFunction,
+            _ => return Err(()),
+        };
+
+        Ok(Request {
+            interface: crate::windex2interface(windex)?,
+            kind,
+        })
+    }
+}
+
+/// MBIM functional descriptor
+///
+/// See section 6.4 of (MBIM1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct Descriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Release number of the MBIM specification (Binary-coded Decimal)
+    pub bcdMBIMVersion: u16,
+    /// Maximum size of a control message (fragment) the function supports
+    pub wMaxControlMessage: u16,
+    /// Number of packet filters
+    pub bNumberFilters: u8,
+    /// Maximum size of a packet filter
+    pub bMaxFilterSize: u8,
+    /// Maximum segment size
+    pub wMaxSegmentSize: u16,
+    /// Optional requests supported by the function
+    pub bmNetworkCapabilities: NetworkCapabilities,
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 12;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            super::SUBTYPE_MBIM,
+            self.bcdMBIMVersion as u8,
+            (self.bcdMBIMVersion >> 8) as u8,
+            self.wMaxControlMessage as u8,
+            (self.wMaxControlMessage >> 8) as u8,
+            self.bNumberFilters,
+            self.bMaxFilterSize,
+            self.wMaxSegmentSize as u8,
+            (self.wMaxSegmentSize >> 8) as u8,
+            self.bmNetworkCapabilities.byte(),
+        ]
+    }
+}
+
+/// Optional MBIM requests supported by the function
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkCapabilities {
+    /// GET_MAX_DATAGRAM_SIZE and SET_MAX_DATAGRAM_SIZE
+    pub max_datagram_size: bool,
+    /// 8-byte GET_NTB_INPUT_SIZE and SET_NTB_INPUT_SIZE
+    pub ntb_input_size_8: bool,
+}
+
+impl NetworkCapabilities {
+    fn byte(&self) -> u8 {
+        let mut byte = 0;
+        if self.max_datagram_size {
+            byte |= 1 << 3;
+        }
+        if self.ntb_input_size_8 {
+            byte |= 1 << 5;
+        }
+        byte
+    }
+}
+
+/// MBIM extended functional descriptor
+///
+/// See section 6.5 of (MBIM1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct ExtendedDescriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Release number of the MBIM extended functional descriptor (Binary-coded Decimal)
+    pub bcdMBIMExtendedVersion: u16,
+    /// Maximum number of outstanding command messages the function can handle
+    pub bMaxOutstandingCommandMessages: u8,
+    /// Operator preferred MTU
+    pub wMTU: u16,
+}
+
+impl ExtendedDescriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 8;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            super::SUBTYPE_MBIM_EXTENDED,
+            self.bcdMBIMExtendedVersion as u8,
+            (self.bcdMBIMExtendedVersion >> 8) as u8,
+            self.bMaxOutstandingCommandMessages,
+            self.wMTU as u8,
+            (self.wMTU >> 8) as u8,
+        ]
+    }
+}
+
+/// Control message type
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MessageType {
+    /// MBIM_OPEN_MSG
+    Open,
+    /// MBIM_CLOSE_MSG
+    Close,
+    /// MBIM_COMMAND_MSG
+    Command,
+    /// MBIM_HOST_ERROR_MSG
+    HostError,
+    /// MBIM_OPEN_DONE
+    OpenDone,
+    /// MBIM_CLOSE_DONE
+    CloseDone,
+    /// MBIM_COMMAND_DONE
+    CommandDone,
+    /// MBIM_FUNCTION_ERROR_MSG
+    FunctionError,
+    /// MBIM_INDICATE_STATUS_MSG
+    IndicateStatus,
+}
+
+// set in the type of the messages sent by the function
+const FUNCTION_TO_HOST: u32 = 0x8000_0000;
+
+impl MessageType {
+    /// Returns the MessageType field of this type
+    pub fn value(&self) -> u32 {
+        match self {
+            MessageType::Open => 1,
+            MessageType::Close => 2,
+            MessageType::Command => 3,
+            MessageType::HostError => 4,
+            MessageType::OpenDone => FUNCTION_TO_HOST | 1,
+            MessageType::CloseDone => FUNCTION_TO_HOST | 2,
+            MessageType::CommandDone => FUNCTION_TO_HOST | 3,
+            MessageType::FunctionError => FUNCTION_TO_HOST | 4,
+            MessageType::IndicateStatus => FUNCTION_TO_HOST | 7,
+        }
+    }
+
+    fn _from(value: u32) -> Option<Self> {
+        Some(match value {
+            1 => MessageType::Open,
+            2 => MessageType::Close,
+            3 => MessageType::Command,
+            4 => MessageType::HostError,
+            0x8000_0001 => MessageType::OpenDone,
+            0x8000_0002 => MessageType::CloseDone,
+            0x8000_0003 => MessageType::CommandDone,
+            0x8000_0004 => MessageType::FunctionError,
+            0x8000_0007 => MessageType::IndicateStatus,
+            _ => return None,
+        })
+    }
+
+    // whether messages of this type carry a fragment header
+    fn is_fragmented(&self) -> bool {
+        matches!(
+            self,
+            MessageType::Command | MessageType::CommandDone | MessageType::IndicateStatus
+        )
+    }
+}
+
+repr!(u32,
+      /// Protocol error reported in HOST_ERROR and FUNCTION_ERROR messages
+      ErrorStatus {
+    /// The time between fragments exceeded the fragment timeout
+    TimeoutFragment = 1,
+    /// A fragment was received out of sequence
+    FragmentOutOfSequence = 2,
+    /// The length of a message doesn't match its contents
+    LengthMismatch = 3,
+    /// Two outstanding commands use the same transaction ID
+    DuplicatedTid = 4,
+    /// A message was received before the OPEN message
+    NotOpened = 5,
+    /// Unknown error
+    Unknown = 6,
+    /// The host cancels the transaction
+    Cancel = 7,
+    /// The message is larger than the maximum control transfer
+    MaxTransfer = 8,
+});
+
+repr!(u32,
+      /// Command type
+      CommandType {
+    /// Query the value of a CID
+    Query = 0,
+    /// Set the value of a CID
+    Set = 1,
+});
+
+/// Status code of a successful operation
+pub const STATUS_SUCCESS: u32 = 0;
+
+/// UUID of a device service, in wire (network) byte order
+pub type Uuid = [u8; 16];
+
+/// MBIM_COMMAND_MSG body
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Command<'a> {
+    /// Device service
+    pub service: Uuid,
+    /// Command identifier within the device service
+    pub cid: u32,
+    /// Command type
+    pub ty: CommandType,
+    /// Information buffer
+    pub information: &'a [u8],
+}
+
+/// MBIM_COMMAND_DONE body
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CommandDone<'a> {
+    /// Device service
+    pub service: Uuid,
+    /// Command identifier within the device service
+    pub cid: u32,
+    /// Status code; see `STATUS_SUCCESS`
+    pub status: u32,
+    /// Information buffer
+    pub information: &'a [u8],
+}
+
+/// MBIM_INDICATE_STATUS_MSG body
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IndicateStatus<'a> {
+    /// Device service
+    pub service: Uuid,
+    /// Command identifier within the device service
+    pub cid: u32,
+    /// Information buffer
+    pub information: &'a [u8],
+}
+
+/// MBIM control message
+///
+/// See section 9 of (MBIM1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Message<'a> {
+    /// MBIM_OPEN_MSG
+    Open {
+        /// Maximum size of the control transfers the host will use
+        max_control_transfer: u32,
+    },
+    /// MBIM_CLOSE_MSG
+    Close,
+    /// MBIM_COMMAND_MSG
+    Command(Command<'a>),
+    /// MBIM_HOST_ERROR_MSG
+    HostError(ErrorStatus),
+    /// MBIM_OPEN_DONE
+    OpenDone {
+        /// Status code; see `STATUS_SUCCESS`
+        status: u32,
+    },
+    /// MBIM_CLOSE_DONE
+    CloseDone {
+        /// Status code; see `STATUS_SUCCESS`
+        status: u32,
+    },
+    /// MBIM_COMMAND_DONE
+    CommandDone(CommandDone<'a>),
+    /// MBIM_FUNCTION_ERROR_MSG
+    FunctionError(ErrorStatus),
+    /// MBIM_INDICATE_STATUS_MSG
+    IndicateStatus(IndicateStatus<'a>),
+}
+
+// MessageType, MessageLength and TransactionId
+const HEADER_SIZE: usize = 12;
+// TotalFragments and CurrentFragment
+const FRAGMENT_HEADER_SIZE: usize = 8;
+// largest fixed part of a message body
+const MAX_FIXED_SIZE: usize = 28;
+
+impl<'a> Message<'a> {
+    /// Returns the type of this message
+    pub fn ty(&self) -> MessageType {
+        match self {
+            Message::Open { .. } => MessageType::Open,
+            Message::Close => MessageType::Close,
+            Message::Command(_) => MessageType::Command,
+            Message::HostError(_) => MessageType::HostError,
+            Message::OpenDone { .. } => MessageType::OpenDone,
+            Message::CloseDone { .. } => MessageType::CloseDone,
+            Message::CommandDone(_) => MessageType::CommandDone,
+            Message::FunctionError(_) => MessageType::FunctionError,
+            Message::IndicateStatus(_) => MessageType::IndicateStatus,
+        }
+    }
+
+    /// Parses a message that fits in a single fragment
+    ///
+    /// Returns the transaction ID and the message. Use `Reassembler` for messages that may be
+    /// split in several fragments
+    pub fn parse(bytes: &'a [u8]) -> Result<(u32, Self), ()> {
+        let (ty, transaction_id) = parse_header(bytes)?;
+
+        let body = if ty.is_fragmented() {
+            if read32(bytes, 12)? != 1 || read32(bytes, 16)? != 0 {
+                return Err(());
+            }
+
+            &bytes[HEADER_SIZE + FRAGMENT_HEADER_SIZE..]
+        } else {
+            &bytes[HEADER_SIZE..]
+        };
+
+        Ok((transaction_id, Self::parse_body(ty, body)?))
+    }
+
+    fn parse_body(ty: MessageType, body: &'a [u8]) -> Result<Self, ()> {
+        // information buffer that follows `fixed` bytes; its length is the last fixed field
+        let information = |fixed: usize| -> Result<&'a [u8], ()> {
+            let length = read32(body, fixed - 4)? as usize;
+            if body.len() != fixed + length {
+                return Err(());
+            }
+            Ok(&body[fixed..])
+        };
+        let service = || -> Result<Uuid, ()> {
+            let mut uuid = [0; 16];
+            uuid.copy_from_slice(body.get(..16).ok_or(())?);
+            Ok(uuid)
+        };
+        let word = |fixed_size: usize| -> Result<u32, ()> {
+            if body.len() != fixed_size {
+                return Err(());
+            }
+            read32(body, 0)
+        };
+
+        Ok(match ty {
+            MessageType::Open => Message::Open {
+                max_control_transfer: word(4)?,
+            },
+            MessageType::Close if body.is_empty() => Message::Close,
+            MessageType::Close => return Err(()),
+            MessageType::Command => Message::Command(Command {
+                service: service()?,
+                cid: read32(body, 16)?,
+                ty: CommandType::_from(read32(body, 20)?).ok_or(())?,
+                information: information(28)?,
+            }),
+            MessageType::HostError => Message::HostError(ErrorStatus::_from(word(4)?).ok_or(())?),
+            MessageType::OpenDone => Message::OpenDone { status: word(4)? },
+            MessageType::CloseDone => Message::CloseDone { status: word(4)? },
+            MessageType::CommandDone => Message::CommandDone(CommandDone {
+                service: service()?,
+                cid: read32(body, 16)?,
+                status: read32(body, 20)?,
+                information: information(28)?,
+            }),
+            MessageType::FunctionError => {
+                Message::FunctionError(ErrorStatus::_from(word(4)?).ok_or(())?)
+            }
+            MessageType::IndicateStatus => Message::IndicateStatus(IndicateStatus {
+                service: service()?,
+                cid: read32(body, 16)?,
+                information: information(24)?,
+            }),
+        })
+    }
+
+    // returns the fixed part of the body and the information buffer
+    fn body(&self) -> ([u8; MAX_FIXED_SIZE], usize, &'a [u8]) {
+        let mut fixed = [0; MAX_FIXED_SIZE];
+
+        let (size, information) = match *self {
+            Message::Open {
+                max_control_transfer: word,
+            }
+            | Message::OpenDone { status: word }
+            | Message::CloseDone { status: word } => {
+                fixed[..4].copy_from_slice(&word.to_le_bytes());
+                (4, &[][..])
+            }
+            Message::HostError(error) | Message::FunctionError(error) => {
+                fixed[..4].copy_from_slice(&(error as u32).to_le_bytes());
+                (4, &[][..])
+            }
+            Message::Close => (0, &[][..]),
+            Message::Command(Command {
+                service,
+                cid,
+                ty,
+                information,
+            }) => {
+                fixed[..16].copy_from_slice(&service);
+                fixed[16..20].copy_from_slice(&cid.to_le_bytes());
+                fixed[20..24].copy_from_slice(&(ty as u32).to_le_bytes());
+                fixed[24..28].copy_from_slice(&(information.len() as u32).to_le_bytes());
+                (28, information)
+            }
+            Message::CommandDone(CommandDone {
+                service,
+                cid,
+                status,
+                information,
+            }) => {
+                fixed[..16].copy_from_slice(&service);
+                fixed[16..20].copy_from_slice(&cid.to_le_bytes());
+                fixed[20..24].copy_from_slice(&status.to_le_bytes());
+                fixed[24..28].copy_from_slice(&(information.len() as u32).to_le_bytes());
+                (28, information)
+            }
+            Message::IndicateStatus(IndicateStatus {
+                service,
+                cid,
+                information,
+            }) => {
+                fixed[..16].copy_from_slice(&service);
+                fixed[16..20].copy_from_slice(&cid.to_le_bytes());
+                fixed[20..24].copy_from_slice(&(information.len() as u32).to_le_bytes());
+                (24, information)
+            }
+        };
+
+        (fixed, size, information)
+    }
+}
+
+/// Splits a message into fragments no larger than the maximum control transfer
+pub struct Fragments<'a> {
+    ty: MessageType,
+    transaction_id: u32,
+    fixed: [u8; MAX_FIXED_SIZE],
+    fixed_size: usize,
+    information: &'a [u8],
+    // body bytes per fragment
+    capacity: usize,
+    total: u32,
+    current: u32,
+}
+
+impl<'a> Fragments<'a> {
+    /// Prepares `message` for transmission
+    ///
+    /// Returns an error if `max_control_transfer` is too small for the message
+    pub fn new(
+        message: &Message<'a>,
+        transaction_id: u32,
+        max_control_transfer: usize,
+    ) -> Result<Self, ()> {
+        let ty = message.ty();
+        let (fixed, fixed_size, information) = message.body();
+        let body_size = fixed_size + information.len();
+
+        let overhead = if ty.is_fragmented() {
+            HEADER_SIZE + FRAGMENT_HEADER_SIZE
+        } else {
+            HEADER_SIZE
+        };
+        let capacity = max_control_transfer.checked_sub(overhead).ok_or(())?;
+
+        let total = if !ty.is_fragmented() {
+            if body_size > capacity {
+                return Err(());
+            }
+            1
+        } else {
+            let total = (body_size + capacity)
+                .saturating_sub(1)
+                .checked_div(capacity)
+                .ok_or(())?;
+            core::cmp::max(total, 1)
+        };
+
+        Ok(Fragments {
+            ty,
+            transaction_id,
+            fixed,
+            fixed_size,
+            information,
+            capacity,
+            total: total as u32,
+            current: 0,
+        })
+    }
+
+    /// Returns the number of fragments
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// Writes the next fragment into `buf`
+    ///
+    /// Returns the size of the fragment, `None` if all the fragments have been written, or an
+    /// error if `buf` is too small
+    pub fn write(&mut self, buf: &mut [u8]) -> Option<Result<usize, ()>> {
+        if self.current == self.total {
+            return None;
+        }
+
+        let body_size = self.fixed_size + self.information.len();
+        let start = self.current as usize * self.capacity;
+        let end = core::cmp::min(start + self.capacity, body_size);
+
+        let header_size = if self.ty.is_fragmented() {
+            HEADER_SIZE + FRAGMENT_HEADER_SIZE
+        } else {
+            HEADER_SIZE
+        };
+        let size = header_size + (end - start);
+        let buf = match buf.get_mut(..size) {
+            Some(buf) => buf,
+            None => return Some(Err(())),
+        };
+
+        buf[0..4].copy_from_slice(&self.ty.value().to_le_bytes());
+        buf[4..8].copy_from_slice(&(size as u32).to_le_bytes());
+        buf[8..12].copy_from_slice(&self.transaction_id.to_le_bytes());
+        if self.ty.is_fragmented() {
+            buf[12..16].copy_from_slice(&self.total.to_le_bytes());
+            buf[16..20].copy_from_slice(&self.current.to_le_bytes());
+        }
+
+        let body = &mut buf[header_size..];
+        for (i, byte) in (start..end).zip(body.iter_mut()) {
+            *byte = if i < self.fixed_size {
+                self.fixed[i]
+            } else {
+                self.information[i - self.fixed_size]
+            };
+        }
+
+        self.current += 1;
+        Some(Ok(size))
+    }
+}
+
+/// Reassembles fragmented messages
+pub struct Reassembler<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    // type, transaction ID, total and next fragment of the message being reassembled
+    pending: Option<(MessageType, u32, u32, u32)>,
+}
+
+impl<'a> Reassembler<'a> {
+    /// Creates a reassembler that uses `buf` to hold the body of fragmented messages
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Reassembler {
+            buf,
+            len: 0,
+            pending: None,
+        }
+    }
+
+    /// Processes a fragment
+    ///
+    /// Returns the transaction ID and the message once its last fragment has been received.
+    /// Returns an error, and drops the message being reassembled, if the fragment is malformed,
+    /// out of sequence or doesn't fit in the buffer
+    pub fn push<'b>(&'b mut self, fragment: &'b [u8]) -> Result<Option<(u32, Message<'b>)>, ()> {
+        let (ty, transaction_id) = parse_header(fragment)?;
+
+        if !ty.is_fragmented() {
+            return Message::parse_body(ty, &fragment[HEADER_SIZE..])
+                .map(|message| Some((transaction_id, message)));
+        }
+
+        let total = read32(fragment, 12)?;
+        let current = read32(fragment, 16)?;
+        let data = &fragment[HEADER_SIZE + FRAGMENT_HEADER_SIZE..];
+
+        if current == 0 {
+            self.pending = None;
+
+            if total == 1 {
+                return Message::parse_body(ty, data)
+                    .map(|message| Some((transaction_id, message)));
+            } else if total == 0 || data.len() > self.buf.len() {
+                return Err(());
+            }
+
+            self.buf[..data.len()].copy_from_slice(data);
+            self.len = data.len();
+            self.pending = Some((ty, transaction_id, total, 1));
+            return Ok(None);
+        }
+
+        match self.pending.take() {
+            Some((pty, ptid, ptotal, next))
+                if pty == ty && ptid == transaction_id && ptotal == total && next == current =>
+            {
+                let end = self.len + data.len();
+                self.buf
+                    .get_mut(self.len..end)
+                    .ok_or(())?
+                    .copy_from_slice(data);
+                self.len = end;
+
+                if current + 1 == total {
+                    Message::parse_body(ty, &self.buf[..self.len])
+                        .map(|message| Some((transaction_id, message)))
+                } else {
+                    self.pending = Some((ty, transaction_id, total, current + 1));
+                    Ok(None)
+                }
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+// validates the message header and returns the message type and transaction ID
+fn parse_header(bytes: &[u8]) -> Result<(MessageType, u32), ()> {
+    let ty = MessageType::_from(read32(bytes, 0)?).ok_or(())?;
+    if read32(bytes, 4)? as usize != bytes.len() {
+        return Err(());
+    }
+
+    let transaction_id = read32(bytes, 8)?;
+    if ty.is_fragmented() && bytes.len() < HEADER_SIZE + FRAGMENT_HEADER_SIZE {
+        return Err(());
+    }
+
+    Ok((ty, transaction_id))
+}
+
+fn read32(bytes: &[u8], offset: usize) -> Result<u32, ()> {
+    let bytes = bytes.get(offset..offset + 4).ok_or(())?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Request;
+
+    use super::{CommandDone, CommandType, Fragments, Kind, Message, Reassembler, STATUS_SUCCESS};
+
+    const BASIC_CONNECT: super::Uuid = [
+        0xa2, 0x89, 0xcc, 0x33, 0xbc, 0xbb, 0x8b, 0x4f, 0xb6, 0xb0, 0x13, 0x3e, 0xc2, 0xaa, 0xe6,
+        0xdf,
+    ];
+
+    #[test]
+    fn reset_function() {
+        assert_eq!(
+            Request::parse(0b0010_0001, 0x05, 0, 0, 0),
+            Ok(Request::Mbim(super::Request {
+                interface: 0,
+                kind: Kind::ResetFunction,
+            }))
+        );
+    }
+
+    #[test]
+    fn open() {
+        let message = Message::Open {
+            max_control_transfer: 4096,
+        };
+
+        let mut buf = [0; 64];
+        let mut fragments = Fragments::new(&message, 1, 64).unwrap();
+        let n = fragments.write(&mut buf).unwrap().unwrap();
+        assert!(fragments.write(&mut buf).is_none());
+
+        assert_eq!(
+            &buf[..n],
+            &[1, 0, 0, 0, 16, 0, 0, 0, 1, 0, 0, 0, 0, 0x10, 0, 0][..]
+        );
+        assert_eq!(Message::parse(&buf[..n]), Ok((1, message)));
+
+        let command = Message::Command(super::Command {
+            service: BASIC_CONNECT,
+            cid: 1,
+            ty: CommandType::Query,
+            information: &[],
+        });
+        let mut fragments = Fragments::new(&command, 2, 64).unwrap();
+        let n = fragments.write(&mut buf).unwrap().unwrap();
+        assert_eq!(n, 48);
+        assert_eq!(Message::parse(&buf[..n]), Ok((2, command)));
+    }
+
+    #[test]
+    fn fragments() {
+        let mut information = [0; 100];
+        for (i, byte) in information.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let message = Message::CommandDone(CommandDone {
+            service: BASIC_CONNECT,
+            cid: 2,
+            status: STATUS_SUCCESS,
+            information: &information,
+        });
+
+        // 128 body bytes, 44 per fragment
+        let mut fragments = Fragments::new(&message, 7, 64).unwrap();
+        assert_eq!(fragments.total(), 3);
+
+        let mut rx = [0; 256];
+        let mut reassembler = Reassembler::new(&mut rx);
+        let mut buf = [0; 64];
+        let mut done = false;
+        while let Some(n) = fragments.write(&mut buf) {
+            let n = n.unwrap();
+            assert!(n <= 64);
+            if let Some(received) = reassembler.push(&buf[..n]).unwrap() {
+                assert_eq!(received, (7, message));
+                done = true;
+            }
+        }
+        assert!(done);
+
+        // out of sequence
+        let mut fragments = Fragments::new(&message, 8, 64).unwrap();
+        let mut buf2 = [0; 64];
+        let n = fragments.write(&mut buf).unwrap().unwrap();
+        fragments.write(&mut buf2).unwrap().unwrap();
+        let m = fragments.write(&mut buf2).unwrap().unwrap();
+        assert_eq!(reassembler.push(&buf[..n]), Ok(None));
+        assert!(reassembler.push(&buf2[..m]).is_err());
+    }
+}
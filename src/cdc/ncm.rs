@@ -0,0 +1,428 @@
+//! Network Control Model (NCM)
+//!
+//! NCM functions also receive the ECM packet filter and statistics requests; those are parsed as
+//! `ecm::Request`. Datagrams are exchanged in NTBs; see the `ntb` module
+//!
+//! See (USBNCM1.0)
+
+use crate::bmrequesttype::{bmRequestType, Direction, Recipient, Type};
+
+use super::ntb::{CrcMode, Format};
+
+/// NCM request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Request {
+    /// Interface index
+    pub interface: u8,
+    /// Kind of request
+    pub kind: Kind,
+}
+
+/// NCM request kind
+///
+/// See section 6.2 of (USBNCM1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// GET_NTB_PARAMETERS -- returns `NtbParameters`
+    GetNtbParameters {
+        /// Maximum number of bytes to return
+        length: u16,
+    },
+    /// GET_NET_ADDRESS -- returns the current EUI-48 address (6 bytes)
+    GetNetAddress,
+    /// SET_NET_ADDRESS -- the new EUI-48 address (6 bytes) follows in the data stage
+    SetNetAddress,
+    /// GET_NTB_FORMAT -- returns the current NTB format (2 bytes)
+    GetNtbFormat,
+    /// SET_NTB_FORMAT
+    SetNtbFormat(Format),
+    /// GET_NTB_INPUT_SIZE -- returns `NtbInputSize`
+    GetNtbInputSize {
+        /// Size of the structure to return; 4 or 8 bytes
+        length: u16,
+    },
+    /// SET_NTB_INPUT_SIZE -- `NtbInputSize` follows in the data stage
+    SetNtbInputSize {
+        /// Size of the structure; 4 or 8 bytes
+        length: u16,
+    },
+    /// GET_MAX_DATAGRAM_SIZE -- returns the maximum datagram size (2 bytes)
+    GetMaxDatagramSize,
+    /// SET_MAX_DATAGRAM_SIZE -- the maximum datagram size (2 bytes) follows in the data stage
+    SetMaxDatagramSize,
+    /// GET_CRC_MODE -- returns the current CRC mode (2 bytes)
+    GetCrcMode,
+    /// SET_CRC_MODE
+    SetCrcMode(CrcMode),
+}
+
+const GET_NTB_PARAMETERS: u8 = 0x80;
+const GET_NET_ADDRESS: u8 = 0x81;
+const SET_NET_ADDRESS: u8 = 0x82;
+const GET_NTB_FORMAT: u8 = 0x83;
+const SET_NTB_FORMAT: u8 = 0x84;
+const GET_NTB_INPUT_SIZE: u8 = 0x85;
+const SET_NTB_INPUT_SIZE: u8 = 0x86;
+const GET_MAX_DATAGRAM_SIZE: u8 = 0x87;
+const SET_MAX_DATAGRAM_SIZE: u8 = 0x88;
+const GET_CRC_MODE: u8 = 0x89;
+const SET_CRC_MODE: u8 = 0x8A;
+
+impl Request {
+    /// Parses an NCM request
+    pub fn parse(
+        bmrequesttype: u8,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        let bmrequesttype = bmRequestType::parse(bmrequesttype)?;
+
+        if bmrequesttype.ty != Type::Class {
+            return Err(());
+        }
+
+        Self::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+    }
+
+    pub(crate) fn parse2(
+        bmRequestType {
+            direction,
+            recipient,
+            // ty must be `Class`
+            ..
+        }: bmRequestType,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        if recipient != Recipient::Interface {
+            return Err(());
+        }
+
+        let kind = match (brequest, direction, wvalue, wlength) {
+            (GET_NTB_PARAMETERS, Direction::DeviceToHost, 0, _) => {
+                Kind::GetNtbParameters { length: wlength }
+            }
+            (GET_NET_ADDRESS, Direction::DeviceToHost, 0, 6) => Kind::GetNetAddress,
+            (SET_NET_ADDRESS, Direction::HostToDevice, 0, 6) => Kind::SetNetAddress,
+            (GET_NTB_FORMAT, Direction::DeviceToHost, 0, 2) => Kind::GetNtbFormat,
+            (SET_NTB_FORMAT, Direction::HostToDevice, _, 0) => {
+                Kind::SetNtbFormat(Format::_from(wvalue).ok_or(())?)
+            }
+            (GET_NTB_INPUT_SIZE, Direction::DeviceToHost, 0, 4)
+            | (GET_NTB_INPUT_SIZE, Direction::DeviceToHost, 0, 8) => {
+                Kind::GetNtbInputSize { length: wlength }
+            }
+            (SET_NTB_INPUT_SIZE, Direction::HostToDevice, 0, 4)
+            | (SET_NTB_INPUT_SIZE, Direction::HostToDevice, 0, 8) => {
+                Kind::SetNtbInputSize { length: wlength }
+            }
+            (GET_MAX_DATAGRAM_SIZE, Direction::DeviceToHost, 0, 2) => Kind::GetMaxDatagramSize,
+            (SET_MAX_DATAGRAM_SIZE, Direction::HostToDevice, 0, 2) => Kind::SetMaxDatagramSize,
+            (GET_CRC_MODE, Direction::DeviceToHost, 0, 2) => Kind::GetCrcMode,
+            (SET_CRC_MODE, Direction::HostToDevice, _, 0) => {
+                Kind::SetCrcMode(CrcMode::_from(wvalue).ok_or(())?)
+            }
+            _ => return Err(()),
+        };
+
+        Ok(Request {
+            interface: crate::windex2interface(windex)?,
+            kind,
+        })
+    }
+}
+
+/// NCM functional descriptor
+///
+/// See section 5.2.1 of (USBNCM1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct Descriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Release number of the NCM specification (Binary-coded Decimal)
+    pub bcdNcmVersion: u16,
+    /// Optional requests supported by the function
+    pub bmNetworkCapabilities: NetworkCapabilities,
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 6;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            super::SUBTYPE_NCM,
+            self.bcdNcmVersion as u8,
+            (self.bcdNcmVersion >> 8) as u8,
+            self.bmNetworkCapabilities.byte(),
+        ]
+    }
+}
+
+/// Optional NCM requests supported by the function
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkCapabilities {
+    /// SET_ETHERNET_PACKET_FILTER
+    pub packet_filter: bool,
+    /// GET_NET_ADDRESS and SET_NET_ADDRESS
+    pub net_address: bool,
+    /// SEND_ENCAPSULATED_COMMAND and GET_ENCAPSULATED_RESPONSE
+    pub encapsulated_command: bool,
+    /// GET_MAX_DATAGRAM_SIZE and SET_MAX_DATAGRAM_SIZE
+    pub max_datagram_size: bool,
+    /// GET_CRC_MODE and SET_CRC_MODE
+    pub crc_mode: bool,
+    /// 8-byte GET_NTB_INPUT_SIZE and SET_NTB_INPUT_SIZE
+    pub ntb_input_size_8: bool,
+}
+
+impl NetworkCapabilities {
+    fn byte(&self) -> u8 {
+        let mut byte = 0;
+        if self.packet_filter {
+            byte |= 1 << 0;
+        }
+        if self.net_address {
+            byte |= 1 << 1;
+        }
+        if self.encapsulated_command {
+            byte |= 1 << 2;
+        }
+        if self.max_datagram_size {
+            byte |= 1 << 3;
+        }
+        if self.crc_mode {
+            byte |= 1 << 4;
+        }
+        if self.ntb_input_size_8 {
+            byte |= 1 << 5;
+        }
+        byte
+    }
+}
+
+/// NTB formats supported by the function
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NtbFormats {
+    /// NTB16; all functions must support it
+    pub ntb16: bool,
+    /// NTB32
+    pub ntb32: bool,
+}
+
+/// NTB parameter structure
+///
+/// See section 6.2.1 of (USBNCM1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NtbParameters {
+    // wLength: u16,
+    /// Supported NTB formats
+    pub bmNtbFormatsSupported: NtbFormats,
+    /// Maximum size of the NTBs sent by the function
+    pub dwNtbInMaxSize: u32,
+    /// Divisor used by the function to align datagrams
+    pub wNdpInDivisor: u16,
+    /// Remainder used by the function to align datagrams
+    pub wNdpInPayloadRemainder: u16,
+    /// Alignment of the NDPs sent by the function
+    pub wNdpInAlignment: u16,
+    // wReserved: u16,
+    /// Maximum size of the NTBs the function accepts
+    pub dwNtbOutMaxSize: u32,
+    /// Divisor the host must use to align datagrams
+    pub wNdpOutDivisor: u16,
+    /// Remainder the host must use to align datagrams
+    pub wNdpOutPayloadRemainder: u16,
+    /// Alignment of the NDPs the host sends
+    pub wNdpOutAlignment: u16,
+    /// Maximum number of datagrams per NTB the function accepts; `0` means no limit
+    pub wNtbOutMaxDatagrams: u16,
+}
+
+impl NtbParameters {
+    /// Size of this structure on the wire
+    pub const SIZE: u8 = 28;
+
+    /// Returns the wire representation of this structure
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        let formats = self.bmNtbFormatsSupported.ntb16 as u16
+            | (self.bmNtbFormatsSupported.ntb32 as u16) << 1;
+
+        bytes[0..2].copy_from_slice(&u16::from(Self::SIZE).to_le_bytes());
+        bytes[2..4].copy_from_slice(&formats.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.dwNtbInMaxSize.to_le_bytes());
+        bytes[8..10].copy_from_slice(&self.wNdpInDivisor.to_le_bytes());
+        bytes[10..12].copy_from_slice(&self.wNdpInPayloadRemainder.to_le_bytes());
+        bytes[12..14].copy_from_slice(&self.wNdpInAlignment.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.dwNtbOutMaxSize.to_le_bytes());
+        bytes[20..22].copy_from_slice(&self.wNdpOutDivisor.to_le_bytes());
+        bytes[22..24].copy_from_slice(&self.wNdpOutPayloadRemainder.to_le_bytes());
+        bytes[24..26].copy_from_slice(&self.wNdpOutAlignment.to_le_bytes());
+        bytes[26..28].copy_from_slice(&self.wNtbOutMaxDatagrams.to_le_bytes());
+        bytes
+    }
+
+    /// Parses the response to a GET_NTB_PARAMETERS request
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) || bytes[0..2] != [Self::SIZE, 0] {
+            return Err(());
+        }
+
+        let u16 = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let u32 =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+
+        let formats = u16(2);
+        if formats & !0b11 != 0 {
+            return Err(());
+        }
+
+        Ok(NtbParameters {
+            bmNtbFormatsSupported: NtbFormats {
+                ntb16: formats & (1 << 0) != 0,
+                ntb32: formats & (1 << 1) != 0,
+            },
+            dwNtbInMaxSize: u32(4),
+            wNdpInDivisor: u16(8),
+            wNdpInPayloadRemainder: u16(10),
+            wNdpInAlignment: u16(12),
+            dwNtbOutMaxSize: u32(16),
+            wNdpOutDivisor: u16(20),
+            wNdpOutPayloadRemainder: u16(22),
+            wNdpOutAlignment: u16(24),
+            wNtbOutMaxDatagrams: u16(26),
+        })
+    }
+}
+
+/// NTB input size structure
+///
+/// See section 6.2.7 of (USBNCM1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NtbInputSize {
+    /// Maximum size of the NTBs the function may send
+    pub dwNtbInMaxSize: u32,
+    /// Maximum number of datagrams per NTB; `0` means no limit. Only present in the 8-byte form
+    pub wNtbInMaxDatagrams: u16,
+}
+
+impl NtbInputSize {
+    /// Size of the 8-byte form of this structure on the wire
+    pub const SIZE: u8 = 8;
+
+    /// Returns the 8-byte wire representation of this structure; the 4-byte form is its prefix
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let size = self.dwNtbInMaxSize.to_le_bytes();
+        let datagrams = self.wNtbInMaxDatagrams.to_le_bytes();
+        [
+            size[0],
+            size[1],
+            size[2],
+            size[3],
+            datagrams[0],
+            datagrams[1],
+            0,
+            0,
+        ]
+    }
+
+    /// Parses the data stage of a SET_NTB_INPUT_SIZE request (4-byte or 8-byte form)
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let dwNtbInMaxSize = match bytes.len() {
+            4 | 8 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            _ => return Err(()),
+        };
+
+        let wNtbInMaxDatagrams = if bytes.len() == 8 {
+            if bytes[6..8] != [0, 0] {
+                return Err(());
+            }
+
+            u16::from_le_bytes([bytes[4], bytes[5]])
+        } else {
+            0
+        };
+
+        Ok(NtbInputSize {
+            dwNtbInMaxSize,
+            wNtbInMaxDatagrams,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{cdc::ntb::Format, Request};
+
+    use super::{Descriptor, Kind, NetworkCapabilities, NtbFormats, NtbInputSize, NtbParameters};
+
+    #[test]
+    fn requests() {
+        assert_eq!(
+            Request::parse(0b1010_0001, 0x80, 0, 1, 28),
+            Ok(Request::Ncm(super::Request {
+                interface: 1,
+                kind: Kind::GetNtbParameters { length: 28 },
+            }))
+        );
+        assert_eq!(
+            super::Request::parse(0b0010_0001, 0x84, 1, 0, 0).map(|req| req.kind),
+            Ok(Kind::SetNtbFormat(Format::Ntb32))
+        );
+        assert!(super::Request::parse(0b0010_0001, 0x84, 2, 0, 0).is_err());
+        assert!(super::Request::parse(0b0010_0001, 0x86, 0, 0, 6).is_err());
+    }
+
+    #[test]
+    fn descriptor() {
+        let desc = Descriptor {
+            bcdNcmVersion: 0x0100,
+            bmNetworkCapabilities: NetworkCapabilities {
+                packet_filter: true,
+                max_datagram_size: true,
+                ..NetworkCapabilities::default()
+            },
+        };
+        assert_eq!(desc.bytes(), [6, 0x24, 0x1A, 0x00, 0x01, 0b1001]);
+    }
+
+    #[test]
+    fn structures() {
+        let params = NtbParameters {
+            bmNtbFormatsSupported: NtbFormats {
+                ntb16: true,
+                ntb32: false,
+            },
+            dwNtbInMaxSize: 2048,
+            wNdpInDivisor: 4,
+            wNdpInPayloadRemainder: 0,
+            wNdpInAlignment: 4,
+            dwNtbOutMaxSize: 2048,
+            wNdpOutDivisor: 4,
+            wNdpOutPayloadRemainder: 2,
+            wNdpOutAlignment: 4,
+            wNtbOutMaxDatagrams: 1,
+        };
+        assert_eq!(NtbParameters::parse(&params.bytes()), Ok(params));
+
+        let size = NtbInputSize {
+            dwNtbInMaxSize: 4096,
+            wNtbInMaxDatagrams: 0,
+        };
+        assert_eq!(NtbInputSize::parse(&size.bytes()[..4]), Ok(size));
+        assert_eq!(NtbInputSize::parse(&size.bytes()), Ok(size));
+        assert!(NtbInputSize::parse(&size.bytes()[..6]).is_err());
+    }
+}
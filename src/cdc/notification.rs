@@ -0,0 +1,173 @@
+//! Communications Class notifications
+//!
+//! See section 6.3 of (USBCDC1.2)
+
+use super::{header, parse_header};
+
+pub(crate) const NETWORK_CONNECTION: u8 = 0x00;
+pub(crate) const RESPONSE_AVAILABLE: u8 = 0x01;
+pub(crate) const SERIAL_STATE: u8 = 0x20;
+pub(crate) const CONNECTION_SPEED_CHANGE: u8 = 0x2A;
+
+/// NETWORK_CONNECTION notification
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NetworkConnection {
+    /// Interface index
+    pub interface: u8,
+    /// Whether the network connection is up
+    pub connected: bool,
+}
+
+impl NetworkConnection {
+    /// Size of this notification on the wire in bytes
+    pub const SIZE: u8 = 8;
+
+    /// Returns the wire representation of this notification
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        header(NETWORK_CONNECTION, self.connected as u16, self.interface, 0)
+    }
+
+    /// Parses a NETWORK_CONNECTION notification
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let (wvalue, interface) = parse_header(bytes, NETWORK_CONNECTION, 0)?;
+
+        let connected = match wvalue {
+            0 => false,
+            1 => true,
+            _ => return Err(()),
+        };
+
+        Ok(NetworkConnection {
+            interface,
+            connected,
+        })
+    }
+}
+
+/// RESPONSE_AVAILABLE notification -- the response can be read with GET_ENCAPSULATED_RESPONSE
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResponseAvailable {
+    /// Interface index
+    pub interface: u8,
+}
+
+impl ResponseAvailable {
+    /// Size of this notification on the wire in bytes
+    pub const SIZE: u8 = 8;
+
+    /// Returns the wire representation of this notification
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        header(RESPONSE_AVAILABLE, 0, self.interface, 0)
+    }
+
+    /// Parses a RESPONSE_AVAILABLE notification
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let (wvalue, interface) = parse_header(bytes, RESPONSE_AVAILABLE, 0)?;
+
+        if wvalue != 0 {
+            return Err(());
+        }
+
+        Ok(ResponseAvailable { interface })
+    }
+}
+
+/// CONNECTION_SPEED_CHANGE notification
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConnectionSpeedChange {
+    /// Interface index
+    pub interface: u8,
+    /// Downlink (IN pipe) bit rate in bits per second
+    pub DLBitRate: u32,
+    /// Uplink (OUT pipe) bit rate in bits per second
+    pub ULBitRate: u32,
+}
+
+impl ConnectionSpeedChange {
+    /// Size of this notification on the wire in bytes
+    pub const SIZE: u8 = 16;
+
+    /// Returns the wire representation of this notification
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[..8].copy_from_slice(&header(CONNECTION_SPEED_CHANGE, 0, self.interface, 8));
+        bytes[8..12].copy_from_slice(&self.DLBitRate.to_le_bytes());
+        bytes[12..].copy_from_slice(&self.ULBitRate.to_le_bytes());
+        bytes
+    }
+
+    /// Parses a CONNECTION_SPEED_CHANGE notification
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let (wvalue, interface) = parse_header(bytes, CONNECTION_SPEED_CHANGE, 8)?;
+
+        if wvalue != 0 {
+            return Err(());
+        }
+
+        Ok(ConnectionSpeedChange {
+            interface,
+            DLBitRate: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+            ULBitRate: u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cdc::{acm::SerialState, Notification};
+
+    use super::{ConnectionSpeedChange, NetworkConnection, ResponseAvailable};
+
+    #[test]
+    fn round_trip() {
+        let state = SerialState {
+            interface: 2,
+            bOverRun: false,
+            bParity: true,
+            bFraming: false,
+            bRingSignal: false,
+            bBreak: true,
+            bTxCarrier: true,
+            bRxCarrier: true,
+        };
+        assert_eq!(
+            Notification::parse(&state.bytes()),
+            Ok(Notification::SerialState(state))
+        );
+
+        let connection = NetworkConnection {
+            interface: 0,
+            connected: true,
+        };
+        assert_eq!(
+            Notification::parse(&connection.bytes()),
+            Ok(Notification::NetworkConnection(connection))
+        );
+
+        let response = ResponseAvailable { interface: 1 };
+        assert_eq!(
+            Notification::parse(&response.bytes()),
+            Ok(Notification::ResponseAvailable(response))
+        );
+
+        let speed = ConnectionSpeedChange {
+            interface: 0,
+            DLBitRate: 480_000_000,
+            ULBitRate: 100_000_000,
+        };
+        assert_eq!(
+            Notification::parse(&speed.bytes()),
+            Ok(Notification::ConnectionSpeedChange(speed))
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        // wrong bmRequestType
+        assert!(Notification::parse(&[0x21, 0x01, 0, 0, 0, 0, 0, 0]).is_err());
+        // truncated
+        assert!(Notification::parse(&[0xA1, 0x20, 0, 0, 0, 0, 2, 0, 0]).is_err());
+        // unknown notification
+        assert!(Notification::parse(&[0xA1, 0x08, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,539 @@
+//! Network Transfer Blocks (NTB)
+//!
+//! An NTB packs several datagrams into a single bulk transfer. It starts with a transfer header
+//! (NTH) and contains the datagrams plus one or more datagram pointer tables (NDP) that locate
+//! them
+//!
+//! See section 3 of (USBNCM1.0)
+
+use crate::crc::crc32;
+
+/// Maximum number of datagrams `Writer` can pack into a single NTB
+pub const MAX_DATAGRAMS: usize = 32;
+
+repr!(u16,
+      /// NTB format
+      Format {
+    /// NTB with 16-bit offsets and lengths
+    Ntb16 = 0,
+    /// NTB with 32-bit offsets and lengths
+    Ntb32 = 1,
+});
+
+repr!(u16,
+      /// CRC mode
+      CrcMode {
+    /// Datagrams are not followed by a CRC
+    NoCrc = 0,
+    /// Every datagram is followed by its CRC-32 (Ethernet FCS)
+    Crc = 1,
+});
+
+const NTH16_SIGNATURE: [u8; 4] = *b"NCMH";
+const NTH32_SIGNATURE: [u8; 4] = *b"ncmh";
+const NDP16_SIGNATURE: [u8; 4] = *b"NCM0";
+const NDP16_CRC_SIGNATURE: [u8; 4] = *b"NCM1";
+const NDP32_SIGNATURE: [u8; 4] = *b"ncm0";
+const NDP32_CRC_SIGNATURE: [u8; 4] = *b"ncm1";
+
+const CRC_SIZE: usize = 4;
+
+impl Format {
+    fn header_length(&self) -> usize {
+        match self {
+            Format::Ntb16 => 12,
+            Format::Ntb32 => 16,
+        }
+    }
+
+    // size of the NDP fields that precede the datagram pointers
+    fn ndp_header_length(&self) -> usize {
+        match self {
+            Format::Ntb16 => 8,
+            Format::Ntb32 => 16,
+        }
+    }
+
+    fn pointer_size(&self) -> usize {
+        match self {
+            Format::Ntb16 => 4,
+            Format::Ntb32 => 8,
+        }
+    }
+
+    fn max_block_length(&self) -> usize {
+        match self {
+            Format::Ntb16 => usize::from(u16::MAX),
+            Format::Ntb32 => u32::MAX as usize,
+        }
+    }
+
+    fn ndp_signature(&self, crc: CrcMode) -> [u8; 4] {
+        match (self, crc) {
+            (Format::Ntb16, CrcMode::NoCrc) => NDP16_SIGNATURE,
+            (Format::Ntb16, CrcMode::Crc) => NDP16_CRC_SIGNATURE,
+            (Format::Ntb32, CrcMode::NoCrc) => NDP32_SIGNATURE,
+            (Format::Ntb32, CrcMode::Crc) => NDP32_CRC_SIGNATURE,
+        }
+    }
+
+    // size of an NDP that holds `datagrams` pointers plus the terminating null pointer
+    fn ndp_length(&self, datagrams: usize) -> usize {
+        self.ndp_header_length() + (datagrams + 1) * self.pointer_size()
+    }
+
+    // reads an offset or length field
+    fn read(&self, bytes: &[u8], offset: usize) -> Result<usize, ()> {
+        match self {
+            Format::Ntb16 => read16(bytes, offset).map(usize::from),
+            Format::Ntb32 => read32(bytes, offset).map(|x| x as usize),
+        }
+    }
+
+    // writes an offset or length field
+    fn write(&self, bytes: &mut [u8], offset: usize, value: usize) {
+        match self {
+            Format::Ntb16 => write16(bytes, offset, value as u16),
+            Format::Ntb32 => write32(bytes, offset, value as u32),
+        }
+    }
+}
+
+/// Layout of the NTBs produced by `Writer`
+///
+/// These values come from the `NtbParameters` of the function: the `In` fields when the device
+/// sends NTBs and the `Out` fields when the host does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Config {
+    /// NTB format
+    pub format: Format,
+    /// Whether datagrams are followed by a CRC
+    pub crc: CrcMode,
+    /// Datagrams start at an offset that is `remainder` modulo `divisor`
+    pub divisor: u16,
+    /// See `divisor`
+    pub remainder: u16,
+    /// Alignment of the NDP; a power of 2 no smaller than 4
+    pub ndp_alignment: u16,
+}
+
+/// NTB writer
+///
+/// Datagrams are placed right after the NTH, following the alignment rules of `Config`, and the
+/// NDP is placed after the last datagram
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    config: Config,
+    sequence: u16,
+    // end of the last datagram
+    offset: usize,
+    // (index, length) pairs
+    datagrams: [(usize, usize); MAX_DATAGRAMS],
+    count: usize,
+}
+
+impl<'a> Writer<'a> {
+    /// Starts a new NTB in `buf`
+    ///
+    /// Returns an error if `config` is invalid or `buf` can't hold the NTH
+    pub fn new(buf: &'a mut [u8], config: Config, sequence: u16) -> Result<Self, ()> {
+        if config.divisor == 0
+            || config.remainder >= config.divisor
+            || !config.ndp_alignment.is_power_of_two()
+            || config.ndp_alignment < 4
+        {
+            return Err(());
+        }
+
+        let offset = config.format.header_length();
+        if buf.len() < offset {
+            return Err(());
+        }
+
+        let max = config.format.max_block_length();
+        let buf = if buf.len() > max {
+            &mut buf[..max]
+        } else {
+            buf
+        };
+
+        Ok(Writer {
+            buf,
+            config,
+            sequence,
+            offset,
+            datagrams: [(0, 0); MAX_DATAGRAMS],
+            count: 0,
+        })
+    }
+
+    /// Appends a datagram to the NTB
+    ///
+    /// Returns an error if the datagram doesn't fit in the NTB; the NTB is left unchanged in that
+    /// case
+    pub fn push(&mut self, datagram: &[u8]) -> Result<(), ()> {
+        let format = self.config.format;
+        if self.count == MAX_DATAGRAMS {
+            return Err(());
+        }
+
+        let divisor = usize::from(self.config.divisor);
+        let remainder = usize::from(self.config.remainder);
+        let start = self.offset + (divisor + remainder - self.offset % divisor) % divisor;
+        let length = match self.config.crc {
+            CrcMode::NoCrc => datagram.len(),
+            CrcMode::Crc => datagram.len() + CRC_SIZE,
+        };
+        let end = start + length;
+
+        if self.ndp_index(end) + format.ndp_length(self.count + 1) > self.buf.len() {
+            return Err(());
+        }
+
+        for byte in &mut self.buf[self.offset..start] {
+            *byte = 0;
+        }
+        self.buf[start..start + datagram.len()].copy_from_slice(datagram);
+        if self.config.crc == CrcMode::Crc {
+            write32(self.buf, start + datagram.len(), crc32(datagram));
+        }
+
+        self.datagrams[self.count] = (start, length);
+        self.count += 1;
+        self.offset = end;
+
+        Ok(())
+    }
+
+    /// Writes the NTH and the NDP
+    ///
+    /// Returns the size of the NTB, or an error if no datagram was pushed
+    pub fn finish(self) -> Result<usize, ()> {
+        if self.count == 0 {
+            return Err(());
+        }
+
+        let format = self.config.format;
+        let ndp = self.ndp_index(self.offset);
+        let ndp_length = format.ndp_length(self.count);
+        let block_length = ndp + ndp_length;
+        let buf = &mut self.buf[..block_length];
+
+        for byte in &mut buf[self.offset..] {
+            *byte = 0;
+        }
+
+        // NTH
+        match format {
+            Format::Ntb16 => buf[..4].copy_from_slice(&NTH16_SIGNATURE),
+            Format::Ntb32 => buf[..4].copy_from_slice(&NTH32_SIGNATURE),
+        }
+        write16(buf, 4, format.header_length() as u16);
+        write16(buf, 6, self.sequence);
+        match format {
+            Format::Ntb16 => {
+                write16(buf, 8, block_length as u16);
+                write16(buf, 10, ndp as u16);
+            }
+            Format::Ntb32 => {
+                write32(buf, 8, block_length as u32);
+                write32(buf, 12, ndp as u32);
+            }
+        }
+
+        // NDP; the next NDP index and the terminating pointer were zeroed above
+        buf[ndp..ndp + 4].copy_from_slice(&format.ndp_signature(self.config.crc));
+        write16(buf, ndp + 4, ndp_length as u16);
+
+        let size = format.pointer_size();
+        let mut pointer = ndp + format.ndp_header_length();
+        for (index, length) in &self.datagrams[..self.count] {
+            format.write(buf, pointer, *index);
+            format.write(buf, pointer + size / 2, *length);
+            pointer += size;
+        }
+
+        Ok(block_length)
+    }
+
+    fn ndp_index(&self, offset: usize) -> usize {
+        let alignment = usize::from(self.config.ndp_alignment);
+        (offset + alignment - 1) & !(alignment - 1)
+    }
+}
+
+/// Iterator over the datagrams of an NTB
+///
+/// Datagrams are yielded in NDP order. The CRC of datagrams that carry one is checked and removed
+pub struct Datagrams<'a> {
+    ntb: &'a [u8],
+    format: Format,
+    sequence: u16,
+    // index of the current NDP; `0` when done
+    ndp: usize,
+    // index of the next datagram pointer in the current NDP
+    pointer: usize,
+    // number of NDPs left to visit; guards against NDP loops
+    hops: usize,
+}
+
+impl<'a> Datagrams<'a> {
+    /// Validates the NTH of `ntb`
+    pub fn new(ntb: &'a [u8]) -> Result<Self, ()> {
+        let format = match ntb.get(..4) {
+            Some(signature) if signature == NTH16_SIGNATURE => Format::Ntb16,
+            Some(signature) if signature == NTH32_SIGNATURE => Format::Ntb32,
+            _ => return Err(()),
+        };
+
+        let header_length = format.header_length();
+        if usize::from(read16(ntb, 4)?) != header_length {
+            return Err(());
+        }
+
+        let sequence = read16(ntb, 6)?;
+        let block_length = format.read(ntb, 8)?;
+        let ndp = format.read(ntb, 8 + format.pointer_size() / 2)?;
+        if block_length < header_length || block_length > ntb.len() || ndp < header_length {
+            return Err(());
+        }
+
+        let ntb = &ntb[..block_length];
+        Ok(Datagrams {
+            ntb,
+            format,
+            sequence,
+            ndp,
+            pointer: 0,
+            hops: ntb.len() / format.ndp_length(1),
+        })
+    }
+
+    /// Returns the format of the NTB
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the sequence number of the NTB
+    pub fn sequence(&self) -> u16 {
+        self.sequence
+    }
+
+    fn next_datagram(&mut self) -> Result<Option<&'a [u8]>, ()> {
+        let format = self.format;
+        let size = format.pointer_size();
+
+        loop {
+            if self.ndp == 0 {
+                return Ok(None);
+            }
+
+            let ndp = self.ndp;
+            let signature = self.ntb.get(ndp..ndp + 4).ok_or(())?;
+            let crc = if signature == format.ndp_signature(CrcMode::NoCrc) {
+                false
+            } else if signature == format.ndp_signature(CrcMode::Crc) {
+                true
+            } else {
+                return Err(());
+            };
+
+            let ndp_length = usize::from(read16(self.ntb, ndp + 4)?);
+            if ndp_length < format.ndp_length(1)
+                || ndp_length % size != 0
+                || ndp + ndp_length > self.ntb.len()
+            {
+                return Err(());
+            }
+
+            if self.pointer == 0 {
+                self.pointer = ndp + format.ndp_header_length();
+            }
+
+            let pointer = self.pointer;
+            let (index, length) = if pointer + size <= ndp + ndp_length {
+                (
+                    format.read(self.ntb, pointer)?,
+                    format.read(self.ntb, pointer + size / 2)?,
+                )
+            } else {
+                // missing null pointer
+                return Err(());
+            };
+
+            if index == 0 || length == 0 {
+                // move on to the next NDP
+                let next = match format {
+                    Format::Ntb16 => usize::from(read16(self.ntb, ndp + 6)?),
+                    Format::Ntb32 => read32(self.ntb, ndp + 8)? as usize,
+                };
+
+                if self.hops == 0 {
+                    return Err(());
+                }
+                self.hops -= 1;
+                self.ndp = next;
+                self.pointer = 0;
+                continue;
+            }
+
+            self.pointer += size;
+
+            let datagram = self
+                .ntb
+                .get(index..index.checked_add(length).ok_or(())?)
+                .ok_or(())?;
+            if index < format.header_length() {
+                return Err(());
+            }
+
+            return if crc {
+                if length < CRC_SIZE {
+                    return Err(());
+                }
+
+                let (datagram, fcs) = datagram.split_at(length - CRC_SIZE);
+                if read32(fcs, 0)? == crc32(datagram) {
+                    Ok(Some(datagram))
+                } else {
+                    Err(())
+                }
+            } else {
+                Ok(Some(datagram))
+            };
+        }
+    }
+}
+
+impl<'a> Iterator for Datagrams<'a> {
+    type Item = Result<&'a [u8], ()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_datagram() {
+            Ok(datagram) => datagram.map(Ok),
+            Err(()) => {
+                // stop after the first error
+                self.ndp = 0;
+                Some(Err(()))
+            }
+        }
+    }
+}
+
+fn read16(bytes: &[u8], offset: usize) -> Result<u16, ()> {
+    let bytes = bytes.get(offset..offset + 2).ok_or(())?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read32(bytes: &[u8], offset: usize) -> Result<u32, ()> {
+    let bytes = bytes.get(offset..offset + 4).ok_or(())?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn write16(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, CrcMode, Datagrams, Format, Writer};
+
+    const DATAGRAMS: [&[u8]; 3] = [&[1; 60], &[2; 61], &[3; 1514]];
+
+    fn roundtrip(config: Config) {
+        let mut buf = [0; 2048];
+        let mut writer = Writer::new(&mut buf, config, 7).unwrap();
+        for datagram in &DATAGRAMS {
+            writer.push(datagram).unwrap();
+        }
+        let n = writer.finish().unwrap();
+
+        let datagrams = Datagrams::new(&buf[..n]).unwrap();
+        assert_eq!(datagrams.format(), config.format);
+        assert_eq!(datagrams.sequence(), 7);
+        assert!(datagrams
+            .map(|datagram| datagram.unwrap())
+            .eq(DATAGRAMS.iter().cloned()));
+    }
+
+    #[test]
+    fn ntb16() {
+        let config = Config {
+            format: Format::Ntb16,
+            crc: CrcMode::NoCrc,
+            divisor: 4,
+            remainder: 2,
+            ndp_alignment: 4,
+        };
+        roundtrip(config);
+
+        let mut buf = [0; 128];
+        let mut writer = Writer::new(&mut buf, config, 0).unwrap();
+        writer.push(&[0xaa; 3]).unwrap();
+        writer.push(&[0xbb; 1]).unwrap();
+        // no room for a third datagram plus the NDP
+        assert!(writer.push(&[0xcc; 100]).is_err());
+        let n = writer.finish().unwrap();
+
+        #[rustfmt::skip]
+        assert_eq!(
+            &buf[..n],
+            &[
+                // NTH16
+                b'N', b'C', b'M', b'H', 12, 0, 0, 0, 40, 0, 20, 0,
+                // padding, datagram, padding, datagram
+                0, 0, 0xaa, 0xaa, 0xaa, 0, 0xbb, 0,
+                // NDP16
+                b'N', b'C', b'M', b'0', 20, 0, 0, 0,
+                14, 0, 3, 0,
+                18, 0, 1, 0,
+                0, 0, 0, 0,
+            ][..]
+        );
+    }
+
+    #[test]
+    fn ntb32_crc() {
+        let config = Config {
+            format: Format::Ntb32,
+            crc: CrcMode::Crc,
+            divisor: 8,
+            remainder: 0,
+            ndp_alignment: 8,
+        };
+        roundtrip(config);
+
+        let mut buf = [0; 128];
+        let mut writer = Writer::new(&mut buf, config, 0).unwrap();
+        writer.push(b"123456789").unwrap();
+        let n = writer.finish().unwrap();
+
+        // corrupt the CRC
+        assert_eq!(&buf[16 + 9..16 + 13], &0xcbf43926u32.to_le_bytes());
+        buf[16 + 9] ^= 1;
+        let mut datagrams = Datagrams::new(&buf[..n]).unwrap();
+        assert_eq!(datagrams.next(), Some(Err(())));
+        assert_eq!(datagrams.next(), None);
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(Datagrams::new(b"NCMH").is_err());
+
+        // NDP points to itself
+        #[rustfmt::skip]
+        let ntb = [
+            b'N', b'C', b'M', b'H', 12, 0, 0, 0, 28, 0, 12, 0,
+            b'N', b'C', b'M', b'0', 16, 0, 12, 0,
+            0, 0, 0, 0,
+            0, 0, 0, 0,
+        ];
+        assert!(Datagrams::new(&ntb)
+            .unwrap()
+            .any(|datagram| datagram.is_err()));
+    }
+}
@@ -0,0 +1,63 @@
+//! OBEX functional descriptor
+
+/// OBEX functional descriptor
+///
+/// See section 6.5.2.3 of (USBWMC1.1)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Descriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Version of the OBEX functional descriptor (Binary-coded Decimal); currently 0x0100
+    pub bcdVersion: u16,
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 5;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            super::SUBTYPE_OBEX,
+            self.bcdVersion as u8,
+            (self.bcdVersion >> 8) as u8,
+        ]
+    }
+
+    /// Parses an OBEX functional descriptor
+    ///
+    /// `bytes` may contain more descriptors after this one; they are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let bytes = super::parse_functional(bytes, super::SUBTYPE_OBEX, Self::SIZE)?;
+
+        Ok(Descriptor {
+            bcdVersion: u16::from_le_bytes([bytes[3], bytes[4]]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Descriptor;
+
+    #[test]
+    fn round_trip() {
+        let desc = Descriptor { bcdVersion: 0x0100 };
+
+        let bytes = desc.bytes();
+        assert_eq!(bytes, [5, 0x24, 0x15, 0x00, 0x01]);
+        assert_eq!(Descriptor::parse(&bytes), Ok(desc));
+
+        // more descriptors may follow
+        assert_eq!(
+            Descriptor::parse(&[5, 0x24, 0x15, 0x00, 0x01, 9, 4]),
+            Ok(desc)
+        );
+        // wrong subtype
+        assert!(Descriptor::parse(&[5, 0x24, 0x14, 0x00, 0x01]).is_err());
+    }
+}
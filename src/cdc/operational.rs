@@ -0,0 +1,102 @@
+//! Telephone Operational Modes functional descriptor
+
+/// Telephone Operational Modes functional descriptor
+///
+/// See section 5.3.3 of (USBPTSN1.2)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Descriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Supported operational modes
+    pub bmCapabilities: Modes,
+}
+
+/// Operational modes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Modes {
+    /// Simple mode
+    pub simple: bool,
+    /// Standalone mode
+    pub standalone: bool,
+    /// Computer Centric mode
+    pub computer_centric: bool,
+}
+
+impl Modes {
+    fn byte(&self) -> u8 {
+        let mut byte = 0;
+        if self.simple {
+            byte |= 1 << 0;
+        }
+        if self.standalone {
+            byte |= 1 << 1;
+        }
+        if self.computer_centric {
+            byte |= 1 << 2;
+        }
+        byte
+    }
+
+    fn parse(byte: u8) -> Result<Self, ()> {
+        if byte & !0b111 != 0 {
+            return Err(());
+        }
+
+        Ok(Modes {
+            simple: byte & (1 << 0) != 0,
+            standalone: byte & (1 << 1) != 0,
+            computer_centric: byte & (1 << 2) != 0,
+        })
+    }
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 4;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            super::SUBTYPE_OPERATIONAL_MODES,
+            self.bmCapabilities.byte(),
+        ]
+    }
+
+    /// Parses a Telephone Operational Modes functional descriptor
+    ///
+    /// `bytes` may contain more descriptors after this one; they are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let bytes = super::parse_functional(bytes, super::SUBTYPE_OPERATIONAL_MODES, Self::SIZE)?;
+
+        Ok(Descriptor {
+            bmCapabilities: Modes::parse(bytes[3])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Descriptor, Modes};
+
+    #[test]
+    fn round_trip() {
+        let desc = Descriptor {
+            bmCapabilities: Modes {
+                simple: true,
+                computer_centric: true,
+                ..Modes::default()
+            },
+        };
+
+        let bytes = desc.bytes();
+        assert_eq!(bytes, [4, 0x24, 0x08, 0b101]);
+        assert_eq!(Descriptor::parse(&bytes), Ok(desc));
+
+        // reserved bits
+        assert!(Descriptor::parse(&[4, 0x24, 0x08, 0b1000]).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! Telephone Ringer functional descriptor
+
+/// Telephone Ringer functional descriptor
+///
+/// See section 5.3.2 of (USBPTSN1.2)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Descriptor {
+    // bFunctionLength: u8,
+    // bDescriptorType: u8,
+    // bDescriptorSubtype: u8,
+    /// Number of discrete ringer volume steps; `0` means 256 steps
+    pub bRingerVolSteps: u8,
+    /// Number of ringer patterns supported; must be at least 1
+    pub bNumRingerPatterns: u8,
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 5;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            super::CS_INTERFACE,
+            super::SUBTYPE_RINGER,
+            self.bRingerVolSteps,
+            self.bNumRingerPatterns,
+        ]
+    }
+
+    /// Parses a Telephone Ringer functional descriptor
+    ///
+    /// `bytes` may contain more descriptors after this one; they are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let bytes = super::parse_functional(bytes, super::SUBTYPE_RINGER, Self::SIZE)?;
+
+        if bytes[4] == 0 {
+            return Err(());
+        }
+
+        Ok(Descriptor {
+            bRingerVolSteps: bytes[3],
+            bNumRingerPatterns: bytes[4],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Descriptor;
+
+    #[test]
+    fn round_trip() {
+        let desc = Descriptor {
+            bRingerVolSteps: 0,
+            bNumRingerPatterns: 3,
+        };
+
+        let bytes = desc.bytes();
+        assert_eq!(bytes, [5, 0x24, 0x04, 0, 3]);
+        assert_eq!(Descriptor::parse(&bytes), Ok(desc));
+
+        // at least one ringer pattern
+        assert!(Descriptor::parse(&[5, 0x24, 0x04, 0, 0]).is_err());
+        // wrong subtype
+        assert!(Descriptor::parse(&[5, 0x24, 0x03, 0, 3]).is_err());
+    }
+}
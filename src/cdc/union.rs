@@ -1,30 +1,87 @@
 //! Union Interface functional descriptor
 
 /// Union Interface functional descriptor
+///
+/// See section 5.2.3.2 of (USBCDC1.2)
 #[allow(non_snake_case)]
-#[derive(Clone, Copy)]
-pub struct Descriptor {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Descriptor<'a> {
     // bFunctionLength: u8,
     // bDescriptorType: u8,
     // bDescriptorSubtype: u8,
     /// Controlling interface
     pub bControlInterface: u8,
-    /// Subordinate interface
-    pub bSubordinateInterface0: u8,
+    /// Subordinate interfaces; there must be at least one
+    pub bSubordinateInterface: &'a [u8],
 }
 
-impl Descriptor {
+impl<'a> Descriptor<'a> {
     /// Size of this descriptor on the wire
-    pub const SIZE: u8 = 5;
-
-    /// Returns the wire representation of this device endpoint
-    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
-        [
-            Self::SIZE,
-            super::CS_INTERFACE,
-            super::SUBTYPE_UNION,
-            self.bControlInterface,
-            self.bSubordinateInterface0,
-        ]
+    pub const fn size(&self) -> usize {
+        4 + self.bSubordinateInterface.len()
+    }
+
+    /// Writes the wire representation of this descriptor into `buf`
+    ///
+    /// Returns the size of the descriptor, or an error if `buf` is too small or the number of
+    /// subordinate interfaces is out of range
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let size = self.size();
+        if self.bSubordinateInterface.is_empty() || size > usize::from(u8::MAX) {
+            return Err(());
+        }
+
+        let buf = buf.get_mut(..size).ok_or(())?;
+        buf[0] = size as u8;
+        buf[1] = super::CS_INTERFACE;
+        buf[2] = super::SUBTYPE_UNION;
+        buf[3] = self.bControlInterface;
+        buf[4..].copy_from_slice(self.bSubordinateInterface);
+
+        Ok(size)
+    }
+
+    /// Parses a Union Interface functional descriptor
+    ///
+    /// `bytes` may contain more descriptors after this one; they are ignored
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ()> {
+        let size = usize::from(*bytes.first().ok_or(())?);
+        let bytes = bytes.get(..size).ok_or(())?;
+
+        if size < 5 || bytes[1] != super::CS_INTERFACE || bytes[2] != super::SUBTYPE_UNION {
+            return Err(());
+        }
+
+        Ok(Descriptor {
+            bControlInterface: bytes[3],
+            bSubordinateInterface: &bytes[4..],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Descriptor;
+
+    #[test]
+    fn round_trip() {
+        let desc = Descriptor {
+            bControlInterface: 0,
+            bSubordinateInterface: &[1, 2, 3],
+        };
+
+        let mut buf = [0; 16];
+        let n = desc.bytes(&mut buf).unwrap();
+        assert_eq!(&buf[..n], &[7, 0x24, 0x06, 0, 1, 2, 3]);
+        assert_eq!(Descriptor::parse(&buf), Ok(desc));
+
+        // no subordinate interfaces
+        assert!(Descriptor::parse(&[4, 0x24, 0x06, 0]).is_err());
+        assert!(Descriptor {
+            bControlInterface: 0,
+            bSubordinateInterface: &[],
+        }
+        .bytes(&mut buf)
+        .is_err());
     }
 }
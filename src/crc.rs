@@ -0,0 +1,24 @@
+//! CRC-32 (IEEE 802.3) as used by the Ethernet frame check sequence and the DFU file suffix
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check() {
+        assert_eq!(super::crc32(b"123456789"), 0xCBF4_3926);
+    }
+}
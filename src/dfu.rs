@@ -0,0 +1,891 @@
+//! Device Firmware Upgrade (DFU)
+//!
+//! Some DFU requests are indistinguishable from requests of other classes (e.g. an UPLOAD of one
+//! byte of block `n < 256` looks like a HID GET_IDLE) so the crate level `Request::parse` doesn't
+//! match DFU requests; use `dfu::Request::parse` on requests addressed to the DFU interface
+//!
+//! For more details see (DFU1.1)
+
+use core::num::NonZeroU8;
+
+use crate::bmrequesttype::{bmRequestType, Direction, Recipient, Type};
+
+pub mod dfuse;
+
+const DFU_FUNCTIONAL: u8 = 0x21;
+
+/// DFU interface class
+#[derive(Clone, Copy)]
+pub struct Class {
+    /// Mode of operation
+    pub protocol: Protocol,
+}
+
+impl Class {
+    /// Class byte (Application Specific)
+    pub fn class(&self) -> NonZeroU8 {
+        unsafe { NonZeroU8::new_unchecked(0xFE) }
+    }
+
+    /// SubClass byte (Device Firmware Upgrade)
+    pub fn subclass(&self) -> u8 {
+        0x01
+    }
+
+    /// Protocol byte
+    pub fn protocol(&self) -> u8 {
+        self.protocol as u8
+    }
+}
+
+/// DFU Protocol codes
+///
+/// See section 4.2.1 of (DFU1.1)
+#[derive(Clone, Copy)]
+pub enum Protocol {
+    /// Run-time: DFU interface exposed next to the application interfaces
+    Runtime = 0x01,
+    /// DFU mode: the only interface of the device while upgrading
+    DfuMode = 0x02,
+}
+
+/// DFU functional descriptor
+///
+/// See section 4.1.3 of (DFU1.1)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Descriptor {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    /// DFU attributes
+    pub bmAttributes: Attributes,
+    /// Time, in milliseconds, the device waits for a USB reset after DETACH
+    pub wDetachTimeOut: u16,
+    /// Maximum number of bytes per DNLOAD or UPLOAD request
+    pub wTransferSize: u16,
+    /// Release number of the DFU specification (Binary-coded Decimal); `0x0110`
+    pub bcdDFUVersion: u16,
+}
+
+/// DFU attributes
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Attributes {
+    /// Download capable (bitCanDnload)
+    pub can_dnload: bool,
+    /// Upload capable (bitCanUpload)
+    pub can_upload: bool,
+    /// The device can communicate over USB after the manifestation phase
+    /// (bitManifestationTolerant)
+    pub manifestation_tolerant: bool,
+    /// The device detaches and re-attaches itself on DETACH (bitWillDetach)
+    pub will_detach: bool,
+}
+
+impl Attributes {
+    fn byte(&self) -> u8 {
+        let mut byte = 0;
+        if self.can_dnload {
+            byte |= 1 << 0;
+        }
+        if self.can_upload {
+            byte |= 1 << 1;
+        }
+        if self.manifestation_tolerant {
+            byte |= 1 << 2;
+        }
+        if self.will_detach {
+            byte |= 1 << 3;
+        }
+        byte
+    }
+
+    fn parse(byte: u8) -> Result<Self, ()> {
+        if byte & !0b1111 != 0 {
+            return Err(());
+        }
+
+        Ok(Attributes {
+            can_dnload: byte & (1 << 0) != 0,
+            can_upload: byte & (1 << 1) != 0,
+            manifestation_tolerant: byte & (1 << 2) != 0,
+            will_detach: byte & (1 << 3) != 0,
+        })
+    }
+}
+
+impl Descriptor {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 9;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            Self::SIZE,
+            DFU_FUNCTIONAL,
+            self.bmAttributes.byte(),
+            self.wDetachTimeOut as u8,
+            (self.wDetachTimeOut >> 8) as u8,
+            self.wTransferSize as u8,
+            (self.wTransferSize >> 8) as u8,
+            self.bcdDFUVersion as u8,
+            (self.bcdDFUVersion >> 8) as u8,
+        ]
+    }
+
+    /// Parses a DFU functional descriptor
+    ///
+    /// `bytes` may contain more descriptors after this one; they are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let bytes = bytes.get(..usize::from(Self::SIZE)).ok_or(())?;
+        if bytes[0] != Self::SIZE || bytes[1] != DFU_FUNCTIONAL {
+            return Err(());
+        }
+
+        Ok(Descriptor {
+            bmAttributes: Attributes::parse(bytes[2])?,
+            wDetachTimeOut: u16::from_le_bytes([bytes[3], bytes[4]]),
+            wTransferSize: u16::from_le_bytes([bytes[5], bytes[6]]),
+            bcdDFUVersion: u16::from_le_bytes([bytes[7], bytes[8]]),
+        })
+    }
+}
+
+/// DFU request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Request {
+    /// Interface index
+    pub interface: u8,
+    /// Kind of request
+    pub kind: Kind,
+}
+
+/// DFU request kind
+///
+/// See section 3 of (DFU1.1)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// DETACH -- switch to DFU mode at the next USB reset
+    Detach {
+        /// Time, in milliseconds, to wait for the USB reset
+        timeout: u16,
+    },
+    /// DNLOAD -- the block follows in the data stage; an empty block ends the download
+    Dnload {
+        /// Block number
+        block: u16,
+        /// Length of the block
+        length: u16,
+    },
+    /// UPLOAD -- returns up to `length` bytes; a shorter block ends the upload
+    Upload {
+        /// Block number
+        block: u16,
+        /// Maximum length of the block
+        length: u16,
+    },
+    /// GETSTATUS -- returns a `StatusResponse` (6 bytes)
+    GetStatus,
+    /// CLRSTATUS -- leaves the dfuERROR state
+    ClrStatus,
+    /// GETSTATE -- returns the state (1 byte)
+    GetState,
+    /// ABORT -- returns to the dfuIDLE state
+    Abort,
+}
+
+const DETACH: u8 = 0;
+const DNLOAD: u8 = 1;
+const UPLOAD: u8 = 2;
+const GETSTATUS: u8 = 3;
+const CLRSTATUS: u8 = 4;
+const GETSTATE: u8 = 5;
+const ABORT: u8 = 6;
+
+impl Request {
+    /// Parses a DFU request
+    pub fn parse(
+        bmrequesttype: u8,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        let bmRequestType {
+            direction,
+            recipient,
+            ty,
+        } = bmRequestType::parse(bmrequesttype)?;
+
+        if ty != Type::Class || recipient != Recipient::Interface {
+            return Err(());
+        }
+
+        let kind = match (brequest, direction, wvalue, wlength) {
+            (DETACH, Direction::HostToDevice, timeout, 0) => Kind::Detach { timeout },
+            (DNLOAD, Direction::HostToDevice, block, length) => Kind::Dnload { block, length },
+            (UPLOAD, Direction::DeviceToHost, block, length) => Kind::Upload { block, length },
+            (GETSTATUS, Direction::DeviceToHost, 0, 6) => Kind::GetStatus,
+            (CLRSTATUS, Direction::HostToDevice, 0, 0) => Kind::ClrStatus,
+            (GETSTATE, Direction::DeviceToHost, 0, 1) => Kind::GetState,
+            (ABORT, Direction::HostToDevice, 0, 0) => Kind::Abort,
+            _ => return Err(()),
+        };
+
+        Ok(Request {
+            interface: crate::windex2interface(windex)?,
+            kind,
+        })
+    }
+}
+
+repr!(u8,
+      /// DFU device status (bStatus)
+      Status {
+    /// No error condition is present
+    Ok = 0x00,
+    /// File is not targeted for use by this device (errTARGET)
+    Target = 0x01,
+    /// File is for this device but fails some vendor-specific verification test (errFILE)
+    File = 0x02,
+    /// Device is unable to write memory (errWRITE)
+    Write = 0x03,
+    /// Memory erase function failed (errERASE)
+    Erase = 0x04,
+    /// Memory erase check failed (errCHECK_ERASED)
+    CheckErased = 0x05,
+    /// Program memory function failed (errPROG)
+    Prog = 0x06,
+    /// Programmed memory failed verification (errVERIFY)
+    Verify = 0x07,
+    /// Received address is out of range (errADDRESS)
+    Address = 0x08,
+    /// Received DNLOAD with wLength = 0, but the firmware is incomplete (errNOTDONE)
+    NotDone = 0x09,
+    /// Device's firmware is corrupt; it cannot return to run-time operations (errFIRMWARE)
+    Firmware = 0x0A,
+    /// iString indicates a vendor-specific error (errVENDOR)
+    Vendor = 0x0B,
+    /// Device detected an unexpected USB reset (errUSBR)
+    Usbr = 0x0C,
+    /// Device detected an unexpected power on reset (errPOR)
+    Por = 0x0D,
+    /// Something went wrong, but the device does not know what (errUNKNOWN)
+    Unknown = 0x0E,
+    /// Device stalled an unexpected request (errSTALLEDPKT)
+    StalledPkt = 0x0F,
+});
+
+repr!(u8,
+      /// DFU device state (bState)
+      State {
+    /// appIDLE -- running the application
+    AppIdle = 0,
+    /// appDETACH -- waiting for a USB reset after DETACH
+    AppDetach = 1,
+    /// dfuIDLE -- DFU mode, waiting for requests
+    DfuIdle = 2,
+    /// dfuDNLOAD-SYNC -- a block was received; waiting for GETSTATUS
+    DfuDnloadSync = 3,
+    /// dfuDNBUSY -- programming a block
+    DfuDnbusy = 4,
+    /// dfuDNLOAD-IDLE -- waiting for the next block
+    DfuDnloadIdle = 5,
+    /// dfuMANIFEST-SYNC -- the download is complete; waiting for GETSTATUS
+    DfuManifestSync = 6,
+    /// dfuMANIFEST -- in the manifestation phase
+    DfuManifest = 7,
+    /// dfuMANIFEST-WAIT-RESET -- waiting for a USB reset after the manifestation phase
+    DfuManifestWaitReset = 8,
+    /// dfuUPLOAD-IDLE -- waiting for the next UPLOAD
+    DfuUploadIdle = 9,
+    /// dfuERROR -- an error occurred; waiting for CLRSTATUS
+    DfuError = 10,
+});
+
+/// Response to GETSTATUS
+///
+/// See section 6.1.2 of (DFU1.1)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatusResponse {
+    /// Status of the most recent request
+    pub bStatus: Status,
+    /// Time, in milliseconds, the host must wait before the next GETSTATUS (24 bits)
+    pub bwPollTimeout: u32,
+    /// State the device enters right after this response
+    pub bState: State,
+    /// String descriptor index of a vendor-specific error description
+    pub iString: Option<NonZeroU8>,
+}
+
+impl StatusResponse {
+    /// Size of this response on the wire
+    pub const SIZE: u8 = 6;
+
+    /// Returns the wire representation of this response
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            self.bStatus as u8,
+            self.bwPollTimeout as u8,
+            (self.bwPollTimeout >> 8) as u8,
+            (self.bwPollTimeout >> 16) as u8,
+            self.bState as u8,
+            self.iString.map(|nz| nz.get()).unwrap_or(0),
+        ]
+    }
+
+    /// Parses a GETSTATUS response
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) {
+            return Err(());
+        }
+
+        Ok(StatusResponse {
+            bStatus: Status::_from(bytes[0]).ok_or(())?,
+            bwPollTimeout: u32::from_le_bytes([bytes[1], bytes[2], bytes[3], 0]),
+            bState: State::_from(bytes[4]).ok_or(())?,
+            iString: NonZeroU8::new(bytes[5]),
+        })
+    }
+}
+
+/// What the firmware must do to complete an accepted DFU request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Nothing; complete the status stage
+    None,
+    /// Reply with this GETSTATUS response
+    Status([u8; StatusResponse::SIZE as usize]),
+    /// Reply with this GETSTATE response
+    State([u8; 1]),
+    /// Detach from the bus, if the device will detach itself, and reenumerate in DFU mode at the
+    /// next USB reset
+    Detach,
+    /// Program the block received in the data stage, then call `Device::done`
+    Download {
+        /// Block number
+        block: u16,
+        /// Length of the block
+        length: u16,
+    },
+    /// Reply with up to `length` bytes of the firmware; call `Device::upload_done` after sending
+    /// a shorter block
+    Upload {
+        /// Block number
+        block: u16,
+        /// Maximum length of the block
+        length: u16,
+    },
+}
+
+/// Sans-IO DFU state machine
+///
+/// Feed it the DFU requests addressed to the DFU interface, USB resets, the passage of time and
+/// the completion of programming and manifestation; it keeps the state and status reported to the
+/// host. See appendix A of (DFU1.1)
+pub struct Device {
+    interface: u8,
+    attributes: Attributes,
+    detach_timeout: u16,
+    transfer_size: u16,
+    state: State,
+    status: Status,
+    // time the host waits between GETSTATUS requests while the device is busy
+    poll_timeout: u32,
+    // remaining time of the appDETACH or dfuDNBUSY timers
+    timer: u32,
+    // programming in progress
+    busy: bool,
+    // manifestation finished (manifestation tolerant devices only)
+    manifested: bool,
+}
+
+impl Device {
+    /// Creates the state machine of a run-time DFU interface (appIDLE)
+    pub fn runtime(interface: u8, descriptor: &Descriptor) -> Self {
+        Self::new(interface, descriptor, State::AppIdle)
+    }
+
+    /// Creates the state machine of a DFU mode interface (dfuIDLE)
+    pub fn dfu_mode(interface: u8, descriptor: &Descriptor) -> Self {
+        Self::new(interface, descriptor, State::DfuIdle)
+    }
+
+    fn new(interface: u8, descriptor: &Descriptor, state: State) -> Self {
+        Device {
+            interface,
+            attributes: descriptor.bmAttributes,
+            detach_timeout: descriptor.wDetachTimeOut,
+            transfer_size: descriptor.wTransferSize,
+            state,
+            status: Status::Ok,
+            poll_timeout: 0,
+            timer: 0,
+            busy: false,
+            manifested: false,
+        }
+    }
+
+    /// Current state
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Current status
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Sets the `bwPollTimeout` reported while programming or manifesting
+    pub fn set_poll_timeout(&mut self, ms: u32) {
+        self.poll_timeout = ms & 0xFF_FFFF;
+    }
+
+    /// Updates the state machine with a request
+    ///
+    /// Returns what the firmware must do, or an error if the request must be stalled. Stalled
+    /// requests move the device to dfuERROR (errSTALLEDPKT) while in DFU mode, and back to
+    /// appIDLE while in appDETACH. DNLOAD and UPLOAD requests longer than `wTransferSize` are
+    /// stalled
+    pub fn handle(&mut self, request: &Request) -> Result<Action, ()> {
+        if request.interface != self.interface {
+            return Err(());
+        }
+
+        let action = self.transition(request.kind);
+        if action.is_err() {
+            match self.state {
+                State::AppIdle | State::DfuError | State::DfuManifestWaitReset => {}
+                State::AppDetach => self.state = State::AppIdle,
+                _ => self.error(Status::StalledPkt),
+            }
+        }
+        action
+    }
+
+    fn transition(&mut self, kind: Kind) -> Result<Action, ()> {
+        // GETSTATE is accepted everywhere but in the busy states
+        if kind == Kind::GetState
+            && self.state != State::DfuDnbusy
+            && self.state != State::DfuManifest
+            && self.state != State::DfuManifestWaitReset
+        {
+            return Ok(Action::State([self.state as u8]));
+        }
+
+        match (self.state, kind) {
+            (State::AppIdle, Kind::Detach { timeout }) => {
+                self.state = State::AppDetach;
+                self.timer = u32::from(timeout.min(self.detach_timeout));
+                Ok(Action::Detach)
+            }
+
+            (State::AppIdle, Kind::GetStatus)
+            | (State::AppDetach, Kind::GetStatus)
+            | (State::DfuIdle, Kind::GetStatus)
+            | (State::DfuDnloadIdle, Kind::GetStatus)
+            | (State::DfuUploadIdle, Kind::GetStatus)
+            | (State::DfuError, Kind::GetStatus) => Ok(self.status_response(0)),
+
+            (State::DfuIdle, Kind::Dnload { block, length })
+            | (State::DfuDnloadIdle, Kind::Dnload { block, length })
+                if length != 0 && length <= self.transfer_size && self.attributes.can_dnload =>
+            {
+                self.state = State::DfuDnloadSync;
+                self.busy = true;
+                Ok(Action::Download { block, length })
+            }
+
+            (State::DfuDnloadIdle, Kind::Dnload { length: 0, .. }) => {
+                self.state = State::DfuManifestSync;
+                self.manifested = false;
+                Ok(Action::None)
+            }
+
+            (State::DfuIdle, Kind::Upload { block, length })
+            | (State::DfuUploadIdle, Kind::Upload { block, length })
+                if length != 0 && length <= self.transfer_size && self.attributes.can_upload =>
+            {
+                self.state = State::DfuUploadIdle;
+                Ok(Action::Upload { block, length })
+            }
+
+            (State::DfuIdle, Kind::Abort)
+            | (State::DfuDnloadIdle, Kind::Abort)
+            | (State::DfuUploadIdle, Kind::Abort) => {
+                self.state = State::DfuIdle;
+                Ok(Action::None)
+            }
+
+            (State::DfuDnloadSync, Kind::GetStatus) => {
+                if self.busy {
+                    self.state = State::DfuDnbusy;
+                    self.timer = self.poll_timeout;
+                    Ok(self.status_response(self.poll_timeout))
+                } else {
+                    self.state = State::DfuDnloadIdle;
+                    Ok(self.status_response(0))
+                }
+            }
+
+            (State::DfuManifestSync, Kind::GetStatus) => {
+                if self.manifested {
+                    self.state = State::DfuIdle;
+                    Ok(self.status_response(0))
+                } else {
+                    self.state = State::DfuManifest;
+                    Ok(self.status_response(self.poll_timeout))
+                }
+            }
+
+            (State::DfuError, Kind::ClrStatus) => {
+                self.state = State::DfuIdle;
+                self.status = Status::Ok;
+                Ok(Action::None)
+            }
+
+            _ => Err(()),
+        }
+    }
+
+    /// Reports the completion of the programming of a block (dfuDNLOAD-SYNC, dfuDNBUSY) or of the
+    /// manifestation phase, which the firmware must start when the state becomes dfuMANIFEST
+    ///
+    /// An error moves the device to dfuERROR with the given status
+    pub fn done(&mut self, result: Result<(), Status>) {
+        match self.state {
+            State::DfuDnloadSync | State::DfuDnbusy | State::DfuManifest => {}
+            _ => return,
+        }
+
+        self.busy = false;
+        if let Err(status) = result {
+            self.error(status);
+            return;
+        }
+
+        if self.state == State::DfuManifest {
+            if self.attributes.manifestation_tolerant {
+                self.state = State::DfuManifestSync;
+                self.manifested = true;
+            } else {
+                self.state = State::DfuManifestWaitReset;
+            }
+        }
+    }
+
+    /// Reports that the last UPLOAD was answered with a short block, which ends the upload
+    pub fn upload_done(&mut self) {
+        if self.state == State::DfuUploadIdle {
+            self.state = State::DfuIdle;
+        }
+    }
+
+    /// Advances the appDETACH and dfuDNBUSY timers by `ms` milliseconds
+    pub fn advance(&mut self, ms: u32) {
+        match self.state {
+            State::AppDetach | State::DfuDnbusy => {}
+            _ => return,
+        }
+
+        self.timer = self.timer.saturating_sub(ms);
+        if self.timer == 0 {
+            self.state = if self.state == State::AppDetach {
+                // no USB reset in time
+                State::AppIdle
+            } else {
+                State::DfuDnloadSync
+            };
+        }
+    }
+
+    /// Updates the state machine with a USB reset
+    ///
+    /// `firmware_valid` tells whether the application firmware is intact. After the reset the
+    /// device must run the application if the state is appIDLE, and enumerate in DFU mode
+    /// otherwise
+    pub fn reset(&mut self, firmware_valid: bool) {
+        self.state = match self.state {
+            State::AppIdle => State::AppIdle,
+            State::AppDetach => State::DfuIdle,
+            _ if firmware_valid => State::AppIdle,
+            _ => {
+                self.status = Status::Firmware;
+                State::DfuError
+            }
+        };
+        self.busy = false;
+    }
+
+    fn error(&mut self, status: Status) {
+        self.state = State::DfuError;
+        self.status = status;
+    }
+
+    fn status_response(&self, poll_timeout: u32) -> Action {
+        Action::Status(
+            StatusResponse {
+                bStatus: self.status,
+                bwPollTimeout: poll_timeout,
+                bState: self.state,
+                iString: None,
+            }
+            .bytes(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Attributes, Descriptor, Device, Kind, Request, State, Status};
+
+    const DESCRIPTOR: Descriptor = Descriptor {
+        bmAttributes: Attributes {
+            can_dnload: true,
+            can_upload: false,
+            manifestation_tolerant: false,
+            will_detach: true,
+        },
+        wDetachTimeOut: 1000,
+        wTransferSize: 1024,
+        bcdDFUVersion: 0x0110,
+    };
+
+    fn request(kind: Kind) -> Request {
+        Request { interface: 0, kind }
+    }
+
+    #[test]
+    fn descriptor() {
+        let bytes = DESCRIPTOR.bytes();
+        assert_eq!(bytes, [9, 0x21, 0b1001, 0xE8, 0x03, 0, 4, 0x10, 0x01]);
+        assert_eq!(Descriptor::parse(&bytes), Ok(DESCRIPTOR));
+    }
+
+    #[test]
+    fn requests() {
+        assert_eq!(
+            Request::parse(0b0010_0001, 1, 3, 0, 64),
+            Ok(request(Kind::Dnload {
+                block: 3,
+                length: 64
+            }))
+        );
+        assert_eq!(
+            Request::parse(0b1010_0001, 3, 0, 0, 6),
+            Ok(request(Kind::GetStatus))
+        );
+        assert_eq!(
+            Request::parse(0b0010_0001, 6, 0, 0, 0),
+            Ok(request(Kind::Abort))
+        );
+        assert_eq!(
+            Request::parse(0b0010_0001, 0, 0, 0, 0),
+            Ok(request(Kind::Detach { timeout: 0 }))
+        );
+        assert!(Request::parse(0b1010_0001, 3, 0, 0, 5).is_err());
+
+        // same encoding as a HID GET_IDLE; not reported as a DFU request by the crate level parser
+        assert_eq!(
+            Request::parse(0b1010_0001, 2, 3, 0, 1),
+            Ok(request(Kind::Upload {
+                block: 3,
+                length: 1
+            }))
+        );
+        assert!(matches!(
+            crate::Request::parse(0b1010_0001, 2, 3, 0, 1),
+            Ok(crate::Request::Hid(..))
+        ));
+    }
+
+    #[test]
+    fn detach() {
+        let mut device = Device::runtime(0, &DESCRIPTOR);
+        assert_eq!(
+            device.handle(&request(Kind::Detach { timeout: 5000 })),
+            Ok(Action::Detach)
+        );
+        assert_eq!(device.state(), State::AppDetach);
+
+        // timer clamped to wDetachTimeOut
+        device.advance(1000);
+        assert_eq!(device.state(), State::AppIdle);
+
+        device
+            .handle(&request(Kind::Detach { timeout: 100 }))
+            .unwrap();
+        device.reset(true);
+        assert_eq!(device.state(), State::DfuIdle);
+    }
+
+    #[test]
+    fn download() {
+        let mut device = Device::dfu_mode(0, &DESCRIPTOR);
+        device.set_poll_timeout(10);
+
+        let dnload = request(Kind::Dnload {
+            block: 0,
+            length: 1024,
+        });
+        assert_eq!(
+            device.handle(&dnload),
+            Ok(Action::Download {
+                block: 0,
+                length: 1024
+            })
+        );
+        assert_eq!(device.state(), State::DfuDnloadSync);
+
+        // still programming
+        assert_eq!(
+            device.handle(&request(Kind::GetStatus)),
+            Ok(Action::Status([0, 10, 0, 0, 4, 0]))
+        );
+        // requests are not allowed while busy
+        assert!(device.handle(&request(Kind::GetState)).is_err());
+        assert_eq!(device.state(), State::DfuError);
+        assert_eq!(device.status(), Status::StalledPkt);
+        device.handle(&request(Kind::ClrStatus)).unwrap();
+        assert_eq!(device.state(), State::DfuIdle);
+
+        device.handle(&dnload).unwrap();
+        device.handle(&request(Kind::GetStatus)).unwrap();
+        device.done(Ok(()));
+        device.advance(10);
+        assert_eq!(device.state(), State::DfuDnloadSync);
+        assert_eq!(
+            device.handle(&request(Kind::GetStatus)),
+            Ok(Action::Status([0, 0, 0, 0, 5, 0]))
+        );
+
+        // end of download and manifestation
+        device
+            .handle(&request(Kind::Dnload {
+                block: 1,
+                length: 0,
+            }))
+            .unwrap();
+        assert_eq!(device.state(), State::DfuManifestSync);
+        device.handle(&request(Kind::GetStatus)).unwrap();
+        assert_eq!(device.state(), State::DfuManifest);
+        device.done(Ok(()));
+        assert_eq!(device.state(), State::DfuManifestWaitReset);
+        device.reset(true);
+        assert_eq!(device.state(), State::AppIdle);
+    }
+
+    #[test]
+    fn errors() {
+        let mut device = Device::dfu_mode(0, &DESCRIPTOR);
+
+        // uploads are not supported
+        assert!(device
+            .handle(&request(Kind::Upload {
+                block: 0,
+                length: 64
+            }))
+            .is_err());
+        assert_eq!(device.state(), State::DfuError);
+        assert_eq!(
+            device.handle(&request(Kind::GetStatus)),
+            Ok(Action::Status([0x0F, 0, 0, 0, 10, 0]))
+        );
+        device.handle(&request(Kind::ClrStatus)).unwrap();
+
+        // programming failure
+        device
+            .handle(&request(Kind::Dnload {
+                block: 0,
+                length: 8,
+            }))
+            .unwrap();
+        device.done(Err(Status::Verify));
+        assert_eq!(device.state(), State::DfuError);
+        assert_eq!(device.status(), Status::Verify);
+
+        // reset with a corrupt firmware
+        device.reset(false);
+        assert_eq!(device.state(), State::DfuError);
+        assert_eq!(device.status(), Status::Firmware);
+    }
+
+    #[test]
+    fn upload() {
+        let descriptor = Descriptor {
+            bmAttributes: Attributes {
+                can_upload: true,
+                ..DESCRIPTOR.bmAttributes
+            },
+            ..DESCRIPTOR
+        };
+        let mut device = Device::dfu_mode(0, &descriptor);
+
+        assert_eq!(
+            device.handle(&request(Kind::Upload {
+                block: 0,
+                length: 64
+            })),
+            Ok(Action::Upload {
+                block: 0,
+                length: 64
+            })
+        );
+        assert_eq!(device.state(), State::DfuUploadIdle);
+        device.upload_done();
+        assert_eq!(device.state(), State::DfuIdle);
+
+        // wLength must be greater than zero
+        assert!(device
+            .handle(&request(Kind::Upload {
+                block: 0,
+                length: 0
+            }))
+            .is_err());
+        assert_eq!(device.state(), State::DfuError);
+    }
+
+    #[test]
+    fn transfer_size() {
+        let descriptor = Descriptor {
+            bmAttributes: Attributes {
+                can_upload: true,
+                ..DESCRIPTOR.bmAttributes
+            },
+            ..DESCRIPTOR
+        };
+        let mut device = Device::dfu_mode(0, &descriptor);
+
+        // wLength must not exceed wTransferSize
+        assert!(device
+            .handle(&request(Kind::Dnload {
+                block: 0,
+                length: 1025,
+            }))
+            .is_err());
+        assert_eq!(device.state(), State::DfuError);
+        assert_eq!(device.status(), Status::StalledPkt);
+        device.handle(&request(Kind::ClrStatus)).unwrap();
+
+        assert!(device
+            .handle(&request(Kind::Upload {
+                block: 0,
+                length: 1025,
+            }))
+            .is_err());
+        assert_eq!(device.state(), State::DfuError);
+        assert_eq!(device.status(), Status::StalledPkt);
+        device.handle(&request(Kind::ClrStatus)).unwrap();
+
+        assert_eq!(
+            device.handle(&request(Kind::Upload {
+                block: 0,
+                length: 1024
+            })),
+            Ok(Action::Upload {
+                block: 0,
+                length: 1024
+            })
+        );
+    }
+}
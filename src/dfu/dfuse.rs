@@ -0,0 +1,638 @@
+//! DfuSe, the STMicroelectronics extension of DFU
+//!
+//! DfuSe commands are sent in DNLOAD requests with block number 0. Data blocks (block number 2
+//! onwards) are written at an address derived from the address pointer; see `address`. The
+//! memory of each alternate setting is described by its interface string (see `Layout`) and
+//! firmware images are distributed as DfuSe files (see `File`)
+//!
+//! See (UM0391) and (AN3156)
+
+use core::str::Split;
+
+/// Block number of DNLOAD and UPLOAD requests that carry DfuSe commands
+pub const COMMAND_BLOCK: u16 = 0;
+
+const SET_ADDRESS_POINTER: u8 = 0x21;
+const ERASE: u8 = 0x41;
+const READ_UNPROTECT: u8 = 0x92;
+
+/// DfuSe command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    /// Set Address Pointer
+    SetAddressPointer(u32),
+    /// Erase the page that contains this address
+    Erase(u32),
+    /// Erase the whole memory
+    MassErase,
+    /// Disable the read protection; the device erases its memory and resets
+    ReadUnprotect,
+}
+
+impl Command {
+    /// Maximum size of a command on the wire
+    pub const MAX_SIZE: u8 = 5;
+
+    /// Parses the data stage of a DNLOAD request with block number 0
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        let address = || -> Result<u32, ()> {
+            match bytes.get(1..) {
+                Some(&[a, b, c, d]) => Ok(u32::from_le_bytes([a, b, c, d])),
+                _ => Err(()),
+            }
+        };
+
+        Ok(match (*bytes.first().ok_or(())?, bytes.len()) {
+            (SET_ADDRESS_POINTER, _) => Command::SetAddressPointer(address()?),
+            (ERASE, 1) => Command::MassErase,
+            (ERASE, _) => Command::Erase(address()?),
+            (READ_UNPROTECT, 1) => Command::ReadUnprotect,
+            _ => return Err(()),
+        })
+    }
+
+    /// Writes the wire representation of this command into `buf`
+    ///
+    /// Returns the size of the command, or an error if `buf` is too small
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let (command, address) = match *self {
+            Command::SetAddressPointer(address) => (SET_ADDRESS_POINTER, Some(address)),
+            Command::Erase(address) => (ERASE, Some(address)),
+            Command::MassErase => (ERASE, None),
+            Command::ReadUnprotect => (READ_UNPROTECT, None),
+        };
+
+        if let Some(address) = address {
+            let buf = buf.get_mut(..5).ok_or(())?;
+            buf[0] = command;
+            buf[1..].copy_from_slice(&address.to_le_bytes());
+            Ok(5)
+        } else {
+            *buf.first_mut().ok_or(())? = command;
+            Ok(1)
+        }
+    }
+}
+
+/// Returns the address where the data of a DNLOAD or UPLOAD block goes
+///
+/// Returns `None` for the command blocks (0 and 1)
+pub fn address(pointer: u32, block: u16, transfer_size: u16) -> Option<u32> {
+    let index = block.checked_sub(2)?;
+    pointer.checked_add(u32::from(index) * u32::from(transfer_size))
+}
+
+/// Memory layout of an alternate setting, encoded in its interface string
+///
+/// e.g. `@Internal Flash  /0x08000000/04*016Kg,01*064Kg,07*128Kg`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout<'a> {
+    /// Name of the memory
+    pub name: &'a str,
+    segments: &'a str,
+}
+
+/// Group of contiguous sectors of the same size and type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sectors {
+    /// Address of the first sector
+    pub address: u32,
+    /// Number of sectors
+    pub count: u16,
+    /// Size of each sector in bytes
+    pub size: u32,
+    /// The sectors can be read
+    pub readable: bool,
+    /// The sectors can be erased
+    pub erasable: bool,
+    /// The sectors can be written
+    pub writable: bool,
+}
+
+impl Sectors {
+    /// Returns the address that follows the last sector
+    pub fn end(&self) -> u64 {
+        u64::from(self.address) + u64::from(self.count) * u64::from(self.size)
+    }
+}
+
+impl<'a> Layout<'a> {
+    /// Parses a memory layout string
+    pub fn parse(s: &'a str) -> Result<Self, ()> {
+        if !s.starts_with('@') {
+            return Err(());
+        }
+
+        let (name, segments) = s[1..].split_at(s.find('/').ok_or(())? - 1);
+        let layout = Layout {
+            name: name.trim(),
+            segments: &segments[1..],
+        };
+
+        // validate the whole string so that `sectors` can't fail
+        let mut iter = layout.iter();
+        let mut any = false;
+        while let Some(sectors) = iter.next_sectors()? {
+            if sectors.end() > 1 << 32 {
+                return Err(());
+            }
+            any = true;
+        }
+
+        if any {
+            Ok(layout)
+        } else {
+            Err(())
+        }
+    }
+
+    /// Returns an iterator over the sector groups
+    pub fn sectors(&self) -> impl Iterator<Item = Sectors> + 'a {
+        let mut iter = self.iter();
+        core::iter::from_fn(move || iter.next_sectors().ok().and_then(|sectors| sectors))
+    }
+
+    /// Returns the start address and size of the sector that contains `address`
+    pub fn sector(&self, address: u32) -> Option<(u32, u32)> {
+        self.sectors()
+            .find(|sectors| address >= sectors.address && u64::from(address) < sectors.end())
+            .map(|sectors| {
+                let index = (address - sectors.address) / sectors.size;
+                (sectors.address + index * sectors.size, sectors.size)
+            })
+    }
+
+    fn iter(&self) -> Iter<'a> {
+        Iter {
+            segments: self.segments.split('/'),
+            groups: None,
+            address: 0,
+        }
+    }
+}
+
+struct Iter<'a> {
+    segments: Split<'a, char>,
+    groups: Option<Split<'a, char>>,
+    address: u32,
+}
+
+impl<'a> Iter<'a> {
+    fn next_sectors(&mut self) -> Result<Option<Sectors>, ()> {
+        loop {
+            if let Some(group) = self.groups.as_mut().and_then(|groups| groups.next()) {
+                let sectors = parse_group(group.trim(), self.address)?;
+                self.address = sectors.end() as u32;
+                return Ok(Some(sectors));
+            }
+
+            let address = match self.segments.next() {
+                Some(address) if !address.trim().is_empty() => address.trim(),
+                // trailing slash
+                Some(_) if self.groups.is_some() => return Ok(None),
+                Some(_) => return Err(()),
+                None => return Ok(None),
+            };
+            let hex = address
+                .strip_prefix("0x")
+                .or_else(|| address.strip_prefix("0X"))
+                .ok_or(())?;
+            self.address = u32::from_str_radix(hex, 16).map_err(|_| ())?;
+            self.groups = Some(self.segments.next().ok_or(())?.split(','));
+        }
+    }
+}
+
+// e.g. `04*016Kg`
+fn parse_group(group: &str, address: u32) -> Result<Sectors, ()> {
+    let star = group.find('*').ok_or(())?;
+    let count = group[..star].parse::<u16>().map_err(|_| ())?;
+
+    let rest = &group[star + 1..];
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or(())?;
+    let size = rest[..digits].parse::<u32>().map_err(|_| ())?;
+
+    let (multiplier, ty) = match &rest.as_bytes()[digits..] {
+        [ty] => (1, *ty),
+        [b' ', ty] | [b'B', ty] => (1, *ty),
+        [b'K', ty] => (1024, *ty),
+        [b'M', ty] => (1024 * 1024, *ty),
+        _ => return Err(()),
+    };
+
+    if count == 0 || !(b'a'..=b'g').contains(&ty) {
+        return Err(());
+    }
+    let ty = ty - b'a' + 1;
+
+    Ok(Sectors {
+        address,
+        count,
+        size: size
+            .checked_mul(multiplier)
+            .filter(|size| *size != 0)
+            .ok_or(())?,
+        readable: ty & 1 != 0,
+        erasable: ty & 2 != 0,
+        writable: ty & 4 != 0,
+    })
+}
+
+const PREFIX_SIGNATURE: &[u8; 5] = b"DfuSe";
+const TARGET_SIGNATURE: &[u8; 6] = b"Target";
+const SUFFIX_SIGNATURE: &[u8; 3] = b"UFD";
+
+const PREFIX_SIZE: usize = 11;
+const TARGET_PREFIX_SIZE: usize = 274;
+const ELEMENT_HEADER_SIZE: usize = 8;
+const TARGET_NAME_SIZE: usize = 255;
+
+/// DFU file suffix
+///
+/// See appendix B of (DFU1.1)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Suffix {
+    /// Release number of the firmware; `0xFFFF` means any
+    pub bcdDevice: u16,
+    /// Product ID; `0xFFFF` means any
+    pub idProduct: u16,
+    /// Vendor ID; `0xFFFF` means any
+    pub idVendor: u16,
+    /// DFU specification number; `0x011A` for DfuSe files
+    pub bcdDFU: u16,
+    // ucDfuSignature: [u8; 3],
+    // bLength: u8,
+    // dwCRC: u32,
+}
+
+impl Suffix {
+    /// Size of the suffix on the wire
+    pub const SIZE: u8 = 16;
+}
+
+/// DfuSe file
+///
+/// See (UM0391)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct File<'a> {
+    /// DFU suffix
+    pub suffix: Suffix,
+    targets: u8,
+    images: &'a [u8],
+}
+
+/// Image of a DfuSe file, to be downloaded to one alternate setting
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target<'a> {
+    /// Alternate setting
+    pub alternate_setting: u8,
+    /// Name of the target, if any
+    pub name: Option<&'a [u8]>,
+    elements: u32,
+    data: &'a [u8],
+}
+
+/// Contiguous piece of an image
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Element<'a> {
+    /// Start address
+    pub address: u32,
+    /// Contents
+    pub data: &'a [u8],
+}
+
+impl<'a> File<'a> {
+    /// Parses a DfuSe file and verifies its CRC
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ()> {
+        let suffix_size = usize::from(Suffix::SIZE);
+        let body_size = bytes.len().checked_sub(suffix_size).ok_or(())?;
+        let (body, suffix) = bytes.split_at(body_size);
+
+        if &suffix[8..11] != SUFFIX_SIGNATURE
+            || usize::from(suffix[11]) != suffix_size
+            || read32(suffix, 12) != !crate::crc::crc32(&bytes[..bytes.len() - 4])
+        {
+            return Err(());
+        }
+
+        if body.len() < PREFIX_SIZE
+            || &body[..5] != PREFIX_SIGNATURE
+            || body[5] != 0x01
+            || read32(body, 6) as usize != body.len()
+        {
+            return Err(());
+        }
+
+        let file = File {
+            suffix: Suffix {
+                bcdDevice: read16(suffix, 0),
+                idProduct: read16(suffix, 2),
+                idVendor: read16(suffix, 4),
+                bcdDFU: read16(suffix, 6),
+            },
+            targets: body[10],
+            images: &body[PREFIX_SIZE..],
+        };
+
+        // validate the structure so that the iterators can't fail
+        let mut images = file.images;
+        for _ in 0..file.targets {
+            let (target, rest) = Target::parse(images)?;
+            let mut data = target.data;
+            for _ in 0..target.elements {
+                data = Element::parse(data)?.1;
+            }
+            if !data.is_empty() {
+                return Err(());
+            }
+            images = rest;
+        }
+        if !images.is_empty() {
+            return Err(());
+        }
+
+        Ok(file)
+    }
+
+    /// Returns an iterator over the images of the file
+    pub fn targets(&self) -> impl Iterator<Item = Target<'a>> + 'a {
+        let mut images = self.images;
+        (0..self.targets).filter_map(move |_| {
+            let (target, rest) = Target::parse(images).ok()?;
+            images = rest;
+            Some(target)
+        })
+    }
+}
+
+impl<'a> Target<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<(Self, &'a [u8]), ()> {
+        let prefix = bytes.get(..TARGET_PREFIX_SIZE).ok_or(())?;
+        if &prefix[..6] != TARGET_SIGNATURE {
+            return Err(());
+        }
+
+        let name = &prefix[11..11 + TARGET_NAME_SIZE];
+        let name = if read32(prefix, 7) != 0 {
+            let end = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+            Some(&name[..end])
+        } else {
+            None
+        };
+
+        let size = read32(prefix, 266) as usize;
+        let end = TARGET_PREFIX_SIZE.checked_add(size).ok_or(())?;
+        let data = bytes.get(TARGET_PREFIX_SIZE..end).ok_or(())?;
+
+        Ok((
+            Target {
+                alternate_setting: prefix[6],
+                name,
+                elements: read32(prefix, 270),
+                data,
+            },
+            &bytes[end..],
+        ))
+    }
+
+    /// Returns an iterator over the elements of this image
+    pub fn elements(&self) -> impl Iterator<Item = Element<'a>> + 'a {
+        let mut data = self.data;
+        (0..self.elements).filter_map(move |_| {
+            let (element, rest) = Element::parse(data).ok()?;
+            data = rest;
+            Some(element)
+        })
+    }
+}
+
+impl<'a> Element<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<(Self, &'a [u8]), ()> {
+        let header = bytes.get(..ELEMENT_HEADER_SIZE).ok_or(())?;
+        let size = read32(header, 4) as usize;
+        let end = ELEMENT_HEADER_SIZE.checked_add(size).ok_or(())?;
+
+        Ok((
+            Element {
+                address: read32(header, 0),
+                data: bytes.get(ELEMENT_HEADER_SIZE..end).ok_or(())?,
+            },
+            &bytes[end..],
+        ))
+    }
+}
+
+/// Builds a DfuSe file
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    targets: u8,
+    // position of the prefix of the current target
+    target: Option<usize>,
+}
+
+impl<'a> Writer<'a> {
+    /// Starts a new file in `buf`
+    pub fn new(buf: &'a mut [u8]) -> Result<Self, ()> {
+        let prefix = buf.get_mut(..PREFIX_SIZE).ok_or(())?;
+        prefix[..5].copy_from_slice(PREFIX_SIGNATURE);
+        prefix[5] = 0x01;
+
+        Ok(Writer {
+            buf,
+            len: PREFIX_SIZE,
+            targets: 0,
+            target: None,
+        })
+    }
+
+    /// Starts a new image for `alternate_setting`
+    ///
+    /// `name` must be shorter than 255 bytes
+    pub fn target(&mut self, alternate_setting: u8, name: Option<&[u8]>) -> Result<(), ()> {
+        let start = self.len;
+        let prefix = self
+            .buf
+            .get_mut(start..start + TARGET_PREFIX_SIZE)
+            .ok_or(())?;
+        let targets = self.targets.checked_add(1).ok_or(())?;
+
+        for byte in prefix.iter_mut() {
+            *byte = 0;
+        }
+        prefix[..6].copy_from_slice(TARGET_SIGNATURE);
+        prefix[6] = alternate_setting;
+        if let Some(name) = name {
+            if name.len() >= TARGET_NAME_SIZE {
+                return Err(());
+            }
+            prefix[7] = 1;
+            prefix[11..11 + name.len()].copy_from_slice(name);
+        }
+
+        self.targets = targets;
+        self.target = Some(start);
+        self.len += TARGET_PREFIX_SIZE;
+        Ok(())
+    }
+
+    /// Appends an element to the current image
+    pub fn element(&mut self, address: u32, data: &[u8]) -> Result<(), ()> {
+        let target = self.target.ok_or(())?;
+        let start = self.len;
+        let end = start + ELEMENT_HEADER_SIZE + data.len();
+        let bytes = self.buf.get_mut(start..end).ok_or(())?;
+
+        bytes[..4].copy_from_slice(&address.to_le_bytes());
+        bytes[4..8].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes[8..].copy_from_slice(data);
+        self.len = end;
+
+        let prefix = &mut self.buf[target..target + TARGET_PREFIX_SIZE];
+        let size = read32(prefix, 266) + (ELEMENT_HEADER_SIZE + data.len()) as u32;
+        let elements = read32(prefix, 270) + 1;
+        prefix[266..270].copy_from_slice(&size.to_le_bytes());
+        prefix[270..274].copy_from_slice(&elements.to_le_bytes());
+
+        Ok(())
+    }
+
+    /// Appends the suffix and returns the size of the file
+    pub fn finish(self, suffix: &Suffix) -> Result<usize, ()> {
+        let size = self.len + usize::from(Suffix::SIZE);
+        let buf = self.buf.get_mut(..size).ok_or(())?;
+
+        buf[6..10].copy_from_slice(&(self.len as u32).to_le_bytes());
+        buf[10] = self.targets;
+
+        let tail = &mut buf[self.len..];
+        tail[0..2].copy_from_slice(&suffix.bcdDevice.to_le_bytes());
+        tail[2..4].copy_from_slice(&suffix.idProduct.to_le_bytes());
+        tail[4..6].copy_from_slice(&suffix.idVendor.to_le_bytes());
+        tail[6..8].copy_from_slice(&suffix.bcdDFU.to_le_bytes());
+        tail[8..11].copy_from_slice(SUFFIX_SIGNATURE);
+        tail[11] = Suffix::SIZE;
+
+        let crc = !crate::crc::crc32(&buf[..size - 4]);
+        buf[size - 4..].copy_from_slice(&crc.to_le_bytes());
+
+        Ok(size)
+    }
+}
+
+fn read16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, File, Layout, Sectors, Suffix, Writer};
+
+    #[test]
+    fn commands() {
+        let mut buf = [0; 8];
+        let n = Command::SetAddressPointer(0x0800_4000)
+            .bytes(&mut buf)
+            .unwrap();
+        assert_eq!(&buf[..n], &[0x21, 0x00, 0x40, 0x00, 0x08]);
+        assert_eq!(
+            Command::parse(&buf[..n]),
+            Ok(Command::SetAddressPointer(0x0800_4000))
+        );
+
+        assert_eq!(Command::parse(&[0x41]), Ok(Command::MassErase));
+        assert_eq!(
+            Command::parse(&[0x41, 0, 0, 0, 0x08]),
+            Ok(Command::Erase(0x0800_0000))
+        );
+        assert_eq!(Command::parse(&[0x92]), Ok(Command::ReadUnprotect));
+        assert!(Command::parse(&[0x21, 0, 0]).is_err());
+
+        assert_eq!(super::address(0x0800_0000, 3, 1024), Some(0x0800_0400));
+        assert_eq!(super::address(0x0800_0000, 1, 1024), None);
+    }
+
+    #[test]
+    fn layout() {
+        let layout =
+            Layout::parse("@Internal Flash  /0x08000000/04*016Kg,01*064Kg,07*128Kg").unwrap();
+        assert_eq!(layout.name, "Internal Flash");
+
+        let mut sectors = layout.sectors();
+        assert_eq!(
+            sectors.next(),
+            Some(Sectors {
+                address: 0x0800_0000,
+                count: 4,
+                size: 16 * 1024,
+                readable: true,
+                erasable: true,
+                writable: true,
+            })
+        );
+        assert_eq!(sectors.next().map(|s| s.address), Some(0x0801_0000));
+        assert_eq!(sectors.next().map(|s| s.address), Some(0x0802_0000));
+        assert_eq!(sectors.next(), None);
+
+        assert_eq!(layout.sector(0x0801_2345), Some((0x0801_0000, 64 * 1024)));
+        assert_eq!(layout.sector(0x0810_0000), None);
+
+        let layout =
+            Layout::parse("@Option Bytes  /0x1FFFC000/01*016 e/0x1FFEC000/01*016 e").unwrap();
+        let mut sectors = layout.sectors();
+        let first = sectors.next().unwrap();
+        assert_eq!((first.size, first.erasable), (16, false));
+        assert_eq!(sectors.next().map(|s| s.address), Some(0x1FFE_C000));
+
+        assert!(Layout::parse("Internal Flash/0x08000000/04*016Kg").is_err());
+        assert!(Layout::parse("@Internal Flash/0x08000000/04*016Kz").is_err());
+        assert!(Layout::parse("@Internal Flash/08000000/04*016Kg").is_err());
+    }
+
+    #[test]
+    fn file() {
+        let suffix = Suffix {
+            bcdDevice: 0xFFFF,
+            idProduct: 0xDF11,
+            idVendor: 0x0483,
+            bcdDFU: 0x011A,
+        };
+
+        let mut buf = [0; 512];
+        let mut writer = Writer::new(&mut buf).unwrap();
+        writer.target(0, Some(b"ST...")).unwrap();
+        writer.element(0x0800_0000, &[1, 2, 3, 4]).unwrap();
+        writer.element(0x0800_4000, &[5, 6]).unwrap();
+        let n = writer.finish(&suffix).unwrap();
+        assert_eq!(n, 11 + 274 + 8 + 4 + 8 + 2 + 16);
+
+        {
+            let file = File::parse(&buf[..n]).unwrap();
+            assert_eq!(file.suffix, suffix);
+
+            let mut targets = file.targets();
+            let target = targets.next().unwrap();
+            assert_eq!(target.alternate_setting, 0);
+            assert_eq!(target.name, Some(&b"ST..."[..]));
+            let mut elements = target.elements();
+            assert_eq!(elements.next().map(|e| e.data), Some(&[1, 2, 3, 4][..]));
+            assert_eq!(elements.next().map(|e| e.address), Some(0x0800_4000));
+            assert_eq!(elements.next(), None);
+            assert_eq!(targets.next(), None);
+        }
+
+        // corrupt the image
+        buf[300] ^= 1;
+        assert!(File::parse(&buf[..n]).is_err());
+    }
+}
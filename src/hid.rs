@@ -4,7 +4,16 @@
 
 use core::num::NonZeroU8;
 
-use crate::bmrequesttype::{bmRequestType, Direction, Recipient};
+use crate::bmrequesttype::{bmRequestType, Direction, Recipient, Type};
+
+pub mod boot;
+pub mod digitizer;
+pub mod gamepad;
+pub mod idle;
+pub mod keyboard;
+pub mod report;
+pub mod sensor;
+pub mod usage;
 
 /// HID specific requests
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -25,6 +34,11 @@ pub enum Kind {
         /// ID of the report to silence; `None` means all reports
         report_id: Option<NonZeroU8>,
     },
+    /// GET_IDLE -- returns the idle rate (1 byte) of the specified report
+    GetIdle {
+        /// ID of the report; `None` means all reports
+        report_id: Option<NonZeroU8>,
+    },
     /// GET_DESCRIPTOR
     GetDescriptor {
         /// Length of the descriptor
@@ -32,8 +46,39 @@ pub enum Kind {
         /// The descriptor type
         descriptor: GetDescriptor,
     },
+    /// GET_REPORT
+    GetReport {
+        /// Report type
+        report_type: report::Type,
+        /// Report ID; `None` if the device doesn't use report IDs
+        report_id: Option<NonZeroU8>,
+        /// Length of the report
+        length: u16,
+    },
+    /// SET_REPORT -- the report follows in the data stage
+    SetReport {
+        /// Report type
+        report_type: report::Type,
+        /// Report ID; `None` if the device doesn't use report IDs
+        report_id: Option<NonZeroU8>,
+        /// Length of the report
+        length: u16,
+    },
+    /// GET_PROTOCOL -- returns the active protocol (1 byte)
+    GetProtocol,
+    /// SET_PROTOCOL
+    SetProtocol(ProtocolMode),
 }
 
+repr!(u8,
+      /// Protocol selected with SET_PROTOCOL
+      ProtocolMode {
+    /// Boot protocol
+    Boot = 0,
+    /// Report protocol (default)
+    Report = 1,
+});
+
 /// GET_DESCRIPTOR descriptor type
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum GetDescriptor {
@@ -52,8 +97,7 @@ impl Request {
         bmRequestType {
             direction,
             recipient,
-            // ty must be `Standard` or `Class`
-            ..
+            ty,
         }: bmRequestType,
         brequest: u8,
         wvalue: u16,
@@ -61,9 +105,70 @@ impl Request {
         wlength: u16,
     ) -> Result<Self, ()> {
         // bRequest
-        const SET_IDLE: u8 = 10;
+        const GET_REPORT: u8 = 0x01;
+        const GET_IDLE: u8 = 0x02;
+        const GET_PROTOCOL: u8 = 0x03;
+        const SET_REPORT: u8 = 0x09;
+        const SET_IDLE: u8 = 0x0A;
+        const SET_PROTOCOL: u8 = 0x0B;
         const GET_DESCRIPTOR: u8 = 6;
 
+        if ty == Type::Class && recipient == Recipient::Interface {
+            let interface = crate::windex2interface(windex)?;
+            let report = || -> Result<_, ()> {
+                let report_type = match wvalue >> 8 {
+                    1 => report::Type::Input,
+                    2 => report::Type::Output,
+                    3 => report::Type::Feature,
+                    _ => return Err(()),
+                };
+
+                Ok((report_type, NonZeroU8::new(wvalue as u8)))
+            };
+
+            let kind = match (brequest, direction) {
+                (GET_REPORT, Direction::DeviceToHost) => {
+                    let (report_type, report_id) = report()?;
+                    Some(Kind::GetReport {
+                        report_type,
+                        report_id,
+                        length: wlength,
+                    })
+                }
+
+                (SET_REPORT, Direction::HostToDevice) => {
+                    let (report_type, report_id) = report()?;
+                    Some(Kind::SetReport {
+                        report_type,
+                        report_id,
+                        length: wlength,
+                    })
+                }
+
+                (GET_IDLE, Direction::DeviceToHost) if wvalue >> 8 == 0 && wlength == 1 => {
+                    Some(Kind::GetIdle {
+                        report_id: NonZeroU8::new(wvalue as u8),
+                    })
+                }
+
+                (GET_PROTOCOL, Direction::DeviceToHost) if wvalue == 0 && wlength == 1 => {
+                    Some(Kind::GetProtocol)
+                }
+
+                (SET_PROTOCOL, Direction::HostToDevice) if wlength == 0 && wvalue >> 8 == 0 => {
+                    Some(Kind::SetProtocol(
+                        ProtocolMode::_from(wvalue as u8).ok_or(())?,
+                    ))
+                }
+
+                _ => None,
+            };
+
+            if let Some(kind) = kind {
+                return Ok(Request { interface, kind });
+            }
+        }
+
         if brequest == SET_IDLE
             && recipient == Recipient::Interface
             && direction == Direction::HostToDevice
@@ -107,7 +212,13 @@ impl Request {
 }
 
 /// Human Interface Device Class
-pub struct Class;
+#[derive(Clone, Copy)]
+pub enum Class {
+    /// No subclass
+    Generic,
+    /// Boot Interface subclass
+    Boot(Protocol),
+}
 
 impl Class {
     /// Class byte
@@ -117,15 +228,32 @@ impl Class {
 
     /// SubClass byte
     pub fn subclass(&self) -> u8 {
-        0
+        match self {
+            Class::Generic => 0,
+            Class::Boot(..) => 1,
+        }
     }
 
     /// Protocol byte
     pub fn protocol(&self) -> u8 {
-        0
+        match self {
+            Class::Generic => 0,
+            Class::Boot(protocol) => *protocol as u8,
+        }
     }
 }
 
+/// Boot Interface protocol codes
+///
+/// See section 4.3 of (HID1.11)
+#[derive(Clone, Copy)]
+pub enum Protocol {
+    /// Keyboard
+    Keyboard = 1,
+    /// Mouse
+    Mouse = 2,
+}
+
 /// HID descriptor -- single Report descriptor
 pub struct Descriptor {
     /// Country code of the localized hardware
@@ -252,4 +380,34 @@ mod tests {
             }))
         );
     }
+
+    #[test]
+    fn set_report() {
+        // SET_REPORT Output (keyboard LEDs)
+        assert_eq!(
+            Request::parse(0b0010_0001, 0x09, 0x02_00, 0, 1),
+            Ok(Request::Hid(super::Request {
+                interface: 0,
+                kind: super::Kind::SetReport {
+                    report_type: super::report::Type::Output,
+                    report_id: None,
+                    length: 1,
+                }
+            }))
+        );
+
+        // invalid report type
+        assert!(Request::parse(0b0010_0001, 0x09, 0x04_00, 0, 1).is_err());
+    }
+
+    #[test]
+    fn set_protocol() {
+        assert_eq!(
+            Request::parse(0b0010_0001, 0x0B, 0, 1, 0),
+            Ok(Request::Hid(super::Request {
+                interface: 1,
+                kind: super::Kind::SetProtocol(super::ProtocolMode::Boot),
+            }))
+        );
+    }
 }
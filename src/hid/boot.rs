@@ -0,0 +1,434 @@
+//! Boot Interface keyboard and mouse
+//!
+//! See appendix B of (HID1.11)
+
+/// Report descriptor of a boot keyboard
+///
+/// See appendix E.6 of (HID1.11)
+pub const KEYBOARD_REPORT_DESCRIPTOR: [u8; 63] = [
+    0x05, 0x01, // USAGE_PAGE (Generic Desktop)
+    0x09, 0x06, // USAGE (Keyboard)
+    0xa1, 0x01, // COLLECTION (Application)
+    0x05, 0x07, //   USAGE_PAGE (Keyboard)
+    0x19, 0xe0, //   USAGE_MINIMUM (Keyboard LeftControl)
+    0x29, 0xe7, //   USAGE_MAXIMUM (Keyboard Right GUI)
+    0x15, 0x00, //   LOGICAL_MINIMUM (0)
+    0x25, 0x01, //   LOGICAL_MAXIMUM (1)
+    0x75, 0x01, //   REPORT_SIZE (1)
+    0x95, 0x08, //   REPORT_COUNT (8)
+    0x81, 0x02, //   INPUT (Data,Var,Abs)
+    0x95, 0x01, //   REPORT_COUNT (1)
+    0x75, 0x08, //   REPORT_SIZE (8)
+    0x81, 0x01, //   INPUT (Cnst)
+    0x95, 0x05, //   REPORT_COUNT (5)
+    0x75, 0x01, //   REPORT_SIZE (1)
+    0x05, 0x08, //   USAGE_PAGE (LEDs)
+    0x19, 0x01, //   USAGE_MINIMUM (Num Lock)
+    0x29, 0x05, //   USAGE_MAXIMUM (Kana)
+    0x91, 0x02, //   OUTPUT (Data,Var,Abs)
+    0x95, 0x01, //   REPORT_COUNT (1)
+    0x75, 0x03, //   REPORT_SIZE (3)
+    0x91, 0x01, //   OUTPUT (Cnst)
+    0x95, 0x06, //   REPORT_COUNT (6)
+    0x75, 0x08, //   REPORT_SIZE (8)
+    0x15, 0x00, //   LOGICAL_MINIMUM (0)
+    0x25, 0x65, //   LOGICAL_MAXIMUM (101)
+    0x05, 0x07, //   USAGE_PAGE (Keyboard)
+    0x19, 0x00, //   USAGE_MINIMUM (Reserved)
+    0x29, 0x65, //   USAGE_MAXIMUM (Keyboard Application)
+    0x81, 0x00, //   INPUT (Data,Ary,Abs)
+    0xc0, // END_COLLECTION
+];
+
+/// Report descriptor of a boot mouse
+///
+/// See appendix E.10 of (HID1.11)
+pub const MOUSE_REPORT_DESCRIPTOR: [u8; 50] = [
+    0x05, 0x01, // USAGE_PAGE (Generic Desktop)
+    0x09, 0x02, // USAGE (Mouse)
+    0xa1, 0x01, // COLLECTION (Application)
+    0x09, 0x01, //   USAGE (Pointer)
+    0xa1, 0x00, //   COLLECTION (Physical)
+    0x05, 0x09, //     USAGE_PAGE (Button)
+    0x19, 0x01, //     USAGE_MINIMUM (Button 1)
+    0x29, 0x03, //     USAGE_MAXIMUM (Button 3)
+    0x15, 0x00, //     LOGICAL_MINIMUM (0)
+    0x25, 0x01, //     LOGICAL_MAXIMUM (1)
+    0x95, 0x03, //     REPORT_COUNT (3)
+    0x75, 0x01, //     REPORT_SIZE (1)
+    0x81, 0x02, //     INPUT (Data,Var,Abs)
+    0x95, 0x01, //     REPORT_COUNT (1)
+    0x75, 0x05, //     REPORT_SIZE (5)
+    0x81, 0x01, //     INPUT (Cnst)
+    0x05, 0x01, //     USAGE_PAGE (Generic Desktop)
+    0x09, 0x30, //     USAGE (X)
+    0x09, 0x31, //     USAGE (Y)
+    0x15, 0x81, //     LOGICAL_MINIMUM (-127)
+    0x25, 0x7f, //     LOGICAL_MAXIMUM (127)
+    0x75, 0x08, //     REPORT_SIZE (8)
+    0x95, 0x02, //     REPORT_COUNT (2)
+    0x81, 0x06, //     INPUT (Data,Var,Rel)
+    0xc0, //   END_COLLECTION
+    0xc0, // END_COLLECTION
+];
+
+/// Keyboard input report
+///
+/// See appendix B.1 of (HID1.11)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BootKeyboardReport {
+    /// Modifier keys
+    pub modifiers: Modifiers,
+    /// Keycodes of the pressed keys; unused slots are set to `0`
+    pub keycodes: [u8; 6],
+    /// Keys pressed while all the `keycodes` slots were in use; unused slots are set to `0`
+    pub overflow: [u8; 10],
+}
+
+/// Keycode reported in all slots when more than 6 keys are pressed
+pub const ERROR_ROLL_OVER: u8 = 0x01;
+
+impl BootKeyboardReport {
+    /// The size of this report on the wire
+    pub const SIZE: u8 = 8;
+
+    /// Adds `keycode` to the list of pressed keys
+    ///
+    /// If all the `keycodes` slots are in use the key is stored in `overflow`, the report switches
+    /// to the "phantom state" (`ERROR_ROLL_OVER`) and an error is returned. The report leaves the
+    /// phantom state once enough keys are released
+    pub fn press(&mut self, keycode: u8) -> Result<(), ()> {
+        if keycode == 0 || self.keycodes.contains(&keycode) || self.overflow.contains(&keycode) {
+            return Ok(());
+        }
+
+        if let Some(slot) = self.keycodes.iter_mut().find(|slot| **slot == 0) {
+            *slot = keycode;
+            return Ok(());
+        }
+
+        // if `overflow` is full the key is not tracked; the report is in the phantom state anyway
+        if let Some(slot) = self.overflow.iter_mut().find(|slot| **slot == 0) {
+            *slot = keycode;
+        }
+        Err(())
+    }
+
+    /// Removes `keycode` from the list of pressed keys
+    pub fn release(&mut self, keycode: u8) {
+        if keycode == 0 {
+            return;
+        }
+
+        remove(&mut self.keycodes, keycode);
+        remove(&mut self.overflow, keycode);
+
+        // move overflowed keys into the free slots
+        for slot in self.keycodes.iter_mut().filter(|slot| **slot == 0) {
+            if self.overflow[0] == 0 {
+                break;
+            }
+
+            *slot = self.overflow[0];
+            remove(&mut self.overflow, *slot);
+        }
+    }
+
+    /// Returns the wire representation of this report
+    ///
+    /// All the keycodes are reported as `ERROR_ROLL_OVER` while more than 6 keys are pressed
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let k = if self.overflow[0] != 0 {
+            &[ERROR_ROLL_OVER; 6]
+        } else {
+            &self.keycodes
+        };
+        [self.modifiers.byte(), 0, k[0], k[1], k[2], k[3], k[4], k[5]]
+    }
+
+    /// Parses a keyboard report
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) {
+            return Err(());
+        }
+
+        let mut keycodes = [0; 6];
+        keycodes.copy_from_slice(&bytes[2..]);
+
+        Ok(BootKeyboardReport {
+            modifiers: Modifiers::from(bytes[0]),
+            keycodes,
+            overflow: [0; 10],
+        })
+    }
+}
+
+// Removes `keycode` from `slots`, keeping the other keycodes at the front
+fn remove(slots: &mut [u8], keycode: u8) {
+    if let Some(pos) = slots.iter().position(|slot| *slot == keycode) {
+        slots[pos..].rotate_left(1);
+        *slots.last_mut().unwrap() = 0;
+    }
+}
+
+/// Modifier keys
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Modifiers {
+    /// Left Control
+    pub left_ctrl: bool,
+    /// Left Shift
+    pub left_shift: bool,
+    /// Left Alt
+    pub left_alt: bool,
+    /// Left GUI
+    pub left_gui: bool,
+    /// Right Control
+    pub right_ctrl: bool,
+    /// Right Shift
+    pub right_shift: bool,
+    /// Right Alt
+    pub right_alt: bool,
+    /// Right GUI
+    pub right_gui: bool,
+}
+
+impl Modifiers {
+    pub(crate) fn byte(&self) -> u8 {
+        let mut byte = 0;
+        if self.left_ctrl {
+            byte |= 1 << 0;
+        }
+        if self.left_shift {
+            byte |= 1 << 1;
+        }
+        if self.left_alt {
+            byte |= 1 << 2;
+        }
+        if self.left_gui {
+            byte |= 1 << 3;
+        }
+        if self.right_ctrl {
+            byte |= 1 << 4;
+        }
+        if self.right_shift {
+            byte |= 1 << 5;
+        }
+        if self.right_alt {
+            byte |= 1 << 6;
+        }
+        if self.right_gui {
+            byte |= 1 << 7;
+        }
+        byte
+    }
+}
+
+impl From<u8> for Modifiers {
+    fn from(byte: u8) -> Self {
+        Modifiers {
+            left_ctrl: byte & (1 << 0) != 0,
+            left_shift: byte & (1 << 1) != 0,
+            left_alt: byte & (1 << 2) != 0,
+            left_gui: byte & (1 << 3) != 0,
+            right_ctrl: byte & (1 << 4) != 0,
+            right_shift: byte & (1 << 5) != 0,
+            right_alt: byte & (1 << 6) != 0,
+            right_gui: byte & (1 << 7) != 0,
+        }
+    }
+}
+
+/// Keyboard LED output report
+///
+/// Sent by the host using SET_REPORT or the interrupt OUT endpoint
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Leds {
+    /// Num Lock
+    pub num_lock: bool,
+    /// Caps Lock
+    pub caps_lock: bool,
+    /// Scroll Lock
+    pub scroll_lock: bool,
+    /// Compose
+    pub compose: bool,
+    /// Kana
+    pub kana: bool,
+}
+
+impl Leds {
+    /// The size of this report on the wire
+    pub const SIZE: u8 = 1;
+
+    /// Returns the wire representation of this report
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut byte = 0;
+        if self.num_lock {
+            byte |= 1 << 0;
+        }
+        if self.caps_lock {
+            byte |= 1 << 1;
+        }
+        if self.scroll_lock {
+            byte |= 1 << 2;
+        }
+        if self.compose {
+            byte |= 1 << 3;
+        }
+        if self.kana {
+            byte |= 1 << 4;
+        }
+        [byte]
+    }
+
+    /// Parses a LED report; the padding bits are ignored
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) {
+            return Err(());
+        }
+
+        let byte = bytes[0];
+        Ok(Leds {
+            num_lock: byte & (1 << 0) != 0,
+            caps_lock: byte & (1 << 1) != 0,
+            scroll_lock: byte & (1 << 2) != 0,
+            compose: byte & (1 << 3) != 0,
+            kana: byte & (1 << 4) != 0,
+        })
+    }
+}
+
+/// Mouse input report
+///
+/// See appendix B.2 of (HID1.11)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BootMouseReport {
+    /// Button 1 (left)
+    pub button1: bool,
+    /// Button 2 (right)
+    pub button2: bool,
+    /// Button 3 (middle)
+    pub button3: bool,
+    /// Relative X displacement
+    pub x: i8,
+    /// Relative Y displacement
+    pub y: i8,
+}
+
+impl BootMouseReport {
+    /// The size of this report on the wire
+    pub const SIZE: u8 = 3;
+
+    /// Returns the wire representation of this report
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut buttons = 0;
+        if self.button1 {
+            buttons |= 1 << 0;
+        }
+        if self.button2 {
+            buttons |= 1 << 1;
+        }
+        if self.button3 {
+            buttons |= 1 << 2;
+        }
+        [buttons, self.x as u8, self.y as u8]
+    }
+
+    /// Parses a mouse report
+    ///
+    /// Extra trailing bytes (e.g. a wheel) are ignored, as the boot protocol requires
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() < usize::from(Self::SIZE) {
+            return Err(());
+        }
+
+        Ok(BootMouseReport {
+            button1: bytes[0] & (1 << 0) != 0,
+            button2: bytes[0] & (1 << 1) != 0,
+            button3: bytes[0] & (1 << 2) != 0,
+            x: bytes[1] as i8,
+            y: bytes[2] as i8,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hid::report::{self, Type};
+
+    use super::{BootKeyboardReport, BootMouseReport, Leds, Modifiers};
+
+    #[test]
+    fn descriptors() {
+        let keyboard = &super::KEYBOARD_REPORT_DESCRIPTOR[..];
+        assert_eq!(
+            report::length(keyboard, Type::Input, None),
+            Ok(usize::from(BootKeyboardReport::SIZE))
+        );
+        assert_eq!(
+            report::length(keyboard, Type::Output, None),
+            Ok(usize::from(Leds::SIZE))
+        );
+
+        let mouse = &super::MOUSE_REPORT_DESCRIPTOR[..];
+        assert_eq!(
+            report::length(mouse, Type::Input, None),
+            Ok(usize::from(BootMouseReport::SIZE))
+        );
+    }
+
+    #[test]
+    fn keyboard() {
+        let mut report = BootKeyboardReport {
+            modifiers: Modifiers {
+                left_shift: true,
+                ..Modifiers::default()
+            },
+            ..BootKeyboardReport::default()
+        };
+
+        report.press(0x04).unwrap();
+        report.press(0x05).unwrap();
+        report.release(0x04);
+        assert_eq!(report.bytes(), [0b10, 0, 0x05, 0, 0, 0, 0, 0]);
+        assert_eq!(BootKeyboardReport::parse(&report.bytes()), Ok(report));
+
+        for keycode in 0x06..0x0b {
+            report.press(keycode).unwrap();
+        }
+        assert!(report.press(0x0b).is_err());
+        assert_eq!(report.bytes()[2..], [super::ERROR_ROLL_OVER; 6]);
+    }
+
+    #[test]
+    fn roll_over() {
+        let mut report = BootKeyboardReport::default();
+
+        for keycode in 0x04..0x0b {
+            let _ = report.press(keycode);
+        }
+        assert_eq!(report.bytes(), [0, 0, 1, 1, 1, 1, 1, 1]);
+
+        // back to six keys
+        report.release(0x05);
+        assert_eq!(report.bytes(), [0, 0, 0x04, 0x06, 0x07, 0x08, 0x09, 0x0a]);
+
+        for keycode in 0x04..0x0b {
+            report.release(keycode);
+        }
+        assert_eq!(report.bytes(), [0; 8]);
+        assert_eq!(report, BootKeyboardReport::default());
+    }
+
+    #[test]
+    fn mouse() {
+        let report = BootMouseReport {
+            button2: true,
+            x: -1,
+            y: 5,
+            ..BootMouseReport::default()
+        };
+
+        assert_eq!(report.bytes(), [0b10, 0xff, 5]);
+        assert_eq!(BootMouseReport::parse(&[0b10, 0xff, 5, 0]), Ok(report));
+    }
+}
@@ -0,0 +1,410 @@
+//! Digitizers (multi-touch touch screen and pen)
+//!
+//! Each touch screen input report carries up to `contacts` fingers followed by the number of
+//! valid contacts. The maximum number of simultaneous contacts is exposed as a feature report
+//!
+//! A pen input report carries the state of a single stylus
+
+use core::num::NonZeroU8;
+
+use super::{
+    report::{self, Collection, Writer, CONSTANT, VARIABLE},
+    usage::{Digitizer, GenericDesktop, Page},
+};
+
+/// Maximum number of contacts per input report
+pub const MAX_CONTACTS: usize = 10;
+
+// tip switch + padding, contact identifier, X, Y
+const CONTACT_SIZE: usize = 6;
+
+// report ID, tip switch + barrel switch + in range + padding, X, Y
+const PEN_REPORT_SIZE: usize = 6;
+
+/// Touch screen description
+#[derive(Clone, Copy)]
+pub struct TouchScreen {
+    /// ID of the input report
+    pub report_id: NonZeroU8,
+    /// ID of the Contact Count Maximum feature report
+    pub feature_report_id: NonZeroU8,
+    /// Number of contacts per input report (`1..=MAX_CONTACTS`)
+    pub contacts: u8,
+    /// Maximum number of simultaneous contacts the device supports
+    pub max_contacts: u8,
+    /// Logical maximum of the X coordinate
+    pub x_max: u16,
+    /// Logical maximum of the Y coordinate
+    pub y_max: u16,
+}
+
+impl TouchScreen {
+    /// Size, in bytes, of the input report including the report ID
+    pub fn report_size(&self) -> usize {
+        1 + usize::from(self.contacts) * CONTACT_SIZE + 1
+    }
+
+    /// Writes the report descriptor into `buf`
+    ///
+    /// Returns the size of the report descriptor, or an error if the configuration is invalid or
+    /// `buf` is too small
+    pub fn report_descriptor(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        if self.contacts == 0 || usize::from(self.contacts) > MAX_CONTACTS {
+            return Err(());
+        }
+
+        let mut w = Writer::new(buf);
+        w.usage_page(Page::Digitizer)
+            .usage(Digitizer::TouchScreen as u16)
+            .collection(Collection::Application)
+            .report_id(self.report_id);
+
+        for _ in 0..self.contacts {
+            w.usage_page(Page::Digitizer)
+                .usage(Digitizer::Finger as u16)
+                .collection(Collection::Logical)
+                .logical_minimum(0)
+                .logical_maximum(1)
+                .usage(Digitizer::TipSwitch as u16)
+                .report_size(1)
+                .report_count(1)
+                .input(VARIABLE)
+                .report_size(7)
+                .input(CONSTANT)
+                .logical_maximum(i32::from(u8::MAX))
+                .usage(Digitizer::ContactIdentifier as u16)
+                .report_size(8)
+                .input(VARIABLE)
+                .usage_page(Page::GenericDesktop)
+                .logical_maximum(i32::from(self.x_max))
+                .usage(GenericDesktop::X as u16)
+                .report_size(16)
+                .input(VARIABLE)
+                .logical_maximum(i32::from(self.y_max))
+                .usage(GenericDesktop::Y as u16)
+                .input(VARIABLE)
+                .end_collection();
+        }
+
+        w.usage_page(Page::Digitizer)
+            .logical_maximum(i32::from(u8::MAX))
+            .usage(Digitizer::ContactCount as u16)
+            .report_size(8)
+            .report_count(1)
+            .input(VARIABLE)
+            .report_id(self.feature_report_id)
+            .logical_maximum(i32::from(self.max_contacts))
+            .usage(Digitizer::ContactCountMaximum as u16)
+            .feature(VARIABLE)
+            .end_collection()
+            .finish()
+    }
+
+    /// Returns the Contact Count Maximum feature report
+    pub fn feature_report(&self) -> [u8; 2] {
+        [self.feature_report_id.get(), self.max_contacts]
+    }
+}
+
+/// Pen description
+#[derive(Clone, Copy)]
+pub struct Pen {
+    /// ID of the input report
+    pub report_id: NonZeroU8,
+    /// Logical maximum of the X coordinate
+    pub x_max: u16,
+    /// Logical maximum of the Y coordinate
+    pub y_max: u16,
+}
+
+impl Pen {
+    /// Size, in bytes, of the input report including the report ID
+    pub fn report_size(&self) -> usize {
+        PEN_REPORT_SIZE
+    }
+
+    /// Writes the report descriptor into `buf`
+    ///
+    /// Returns the size of the report descriptor, or an error if `buf` is too small
+    pub fn report_descriptor(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        Writer::new(buf)
+            .usage_page(Page::Digitizer)
+            .usage(Digitizer::Pen as u16)
+            .collection(Collection::Application)
+            .report_id(self.report_id)
+            .usage(Digitizer::Stylus as u16)
+            .collection(Collection::Physical)
+            .logical_minimum(0)
+            .logical_maximum(1)
+            .usage(Digitizer::TipSwitch as u16)
+            .usage(Digitizer::BarrelSwitch as u16)
+            .usage(Digitizer::InRange as u16)
+            .report_size(1)
+            .report_count(3)
+            .input(VARIABLE)
+            .report_size(5)
+            .report_count(1)
+            .input(CONSTANT)
+            .usage_page(Page::GenericDesktop)
+            .logical_maximum(i32::from(self.x_max))
+            .usage(GenericDesktop::X as u16)
+            .report_size(16)
+            .input(VARIABLE)
+            .logical_maximum(i32::from(self.y_max))
+            .usage(GenericDesktop::Y as u16)
+            .input(VARIABLE)
+            .end_collection()
+            .end_collection()
+            .finish()
+    }
+}
+
+/// A single touch contact
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Contact {
+    /// Whether the finger is touching the surface
+    pub tip_switch: bool,
+    /// Identifier of the contact; must be stable while the finger stays on the surface
+    pub id: u8,
+    /// X coordinate
+    pub x: u16,
+    /// Y coordinate
+    pub y: u16,
+}
+
+/// Touch screen input report
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Report {
+    /// Contacts; only the first `count` are valid
+    pub contacts: [Contact; MAX_CONTACTS],
+    /// Number of valid contacts
+    pub count: u8,
+}
+
+impl Report {
+    /// Writes the wire representation of this report into `buf`
+    ///
+    /// Returns the size of the report, or an error if `buf` is too small or the report doesn't
+    /// match the `screen` configuration
+    pub fn bytes(&self, screen: &TouchScreen, buf: &mut [u8]) -> Result<usize, ()> {
+        if screen.contacts == 0 || usize::from(screen.contacts) > MAX_CONTACTS {
+            return Err(());
+        }
+
+        let size = screen.report_size();
+        let buf = buf.get_mut(..size).ok_or(())?;
+        if self.count > screen.contacts {
+            return Err(());
+        }
+
+        buf[0] = screen.report_id.get();
+        for (i, bytes) in buf[1..size - 1].chunks_mut(CONTACT_SIZE).enumerate() {
+            let contact = if i < usize::from(self.count) {
+                self.contacts[i]
+            } else {
+                Contact::default()
+            };
+
+            if contact.x > screen.x_max || contact.y > screen.y_max {
+                return Err(());
+            }
+
+            bytes[0] = contact.tip_switch as u8;
+            bytes[1] = contact.id;
+            report::write_bits(&mut bytes[2..], 0, 16, u32::from(contact.x))?;
+            report::write_bits(&mut bytes[2..], 16, 16, u32::from(contact.y))?;
+        }
+        buf[size - 1] = self.count;
+
+        Ok(size)
+    }
+}
+
+/// Pen input report
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PenReport {
+    /// Whether the pen tip is touching the surface
+    pub tip_switch: bool,
+    /// Whether the barrel button is pressed
+    pub barrel_switch: bool,
+    /// Whether the pen is within detection range of the surface
+    pub in_range: bool,
+    /// X coordinate
+    pub x: u16,
+    /// Y coordinate
+    pub y: u16,
+}
+
+impl PenReport {
+    /// Writes the wire representation of this report into `buf`
+    ///
+    /// Returns the size of the report, or an error if `buf` is too small or the coordinates exceed
+    /// the `pen` configuration
+    pub fn bytes(&self, pen: &Pen, buf: &mut [u8]) -> Result<usize, ()> {
+        let size = pen.report_size();
+        let buf = buf.get_mut(..size).ok_or(())?;
+        if self.x > pen.x_max || self.y > pen.y_max {
+            return Err(());
+        }
+
+        let mut byte = 0;
+        if self.tip_switch {
+            byte |= 1;
+        }
+        if self.barrel_switch {
+            byte |= 1 << 1;
+        }
+        if self.in_range {
+            byte |= 1 << 2;
+        }
+
+        buf[0] = pen.report_id.get();
+        buf[1] = byte;
+        report::write_bits(&mut buf[2..], 0, 16, u32::from(self.x))?;
+        report::write_bits(&mut buf[2..], 16, 16, u32::from(self.y))?;
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU8;
+
+    use crate::hid::{
+        report::{self, Fields, Type},
+        usage::{Digitizer, GenericDesktop, Usage},
+    };
+
+    use super::{Contact, Pen, PenReport, Report, TouchScreen, MAX_CONTACTS};
+
+    fn screen() -> TouchScreen {
+        TouchScreen {
+            report_id: NonZeroU8::new(1).unwrap(),
+            feature_report_id: NonZeroU8::new(2).unwrap(),
+            contacts: 2,
+            max_contacts: 5,
+            x_max: 4095,
+            y_max: 4095,
+        }
+    }
+
+    #[test]
+    fn touch_screen() {
+        let screen = screen();
+        let mut desc = [0; 256];
+        let n = screen.report_descriptor(&mut desc).unwrap();
+        let desc = &desc[..n];
+
+        assert_eq!(
+            report::length(desc, Type::Input, Some(screen.report_id)),
+            Ok(screen.report_size())
+        );
+        assert_eq!(
+            report::length(desc, Type::Feature, Some(screen.feature_report_id)),
+            Ok(screen.feature_report().len())
+        );
+
+        let mut report = Report {
+            count: 1,
+            ..Report::default()
+        };
+        report.contacts[0] = Contact {
+            tip_switch: true,
+            id: 7,
+            x: 100,
+            y: 4095,
+        };
+
+        let mut buf = [0; 32];
+        let n = report.bytes(&screen, &mut buf).unwrap();
+        let bytes = &buf[..n];
+
+        let mut fields = Fields::new(desc)
+            .map(|field| field.unwrap())
+            .filter(|field| field.ty == Type::Input && !field.is_constant());
+        let mut next = |usage| {
+            let field = fields.next().unwrap();
+            assert_eq!(Usage::from_extended(field.usage_minimum), Ok(usage));
+            report::extract(bytes, &field).unwrap()
+        };
+
+        assert_eq!(next(Usage::Digitizer(Digitizer::TipSwitch)), 1);
+        assert_eq!(next(Usage::Digitizer(Digitizer::ContactIdentifier)), 7);
+        assert_eq!(next(Usage::GenericDesktop(GenericDesktop::X)), 100);
+        assert_eq!(next(Usage::GenericDesktop(GenericDesktop::Y)), 4095);
+        assert_eq!(next(Usage::Digitizer(Digitizer::TipSwitch)), 0);
+        next(Usage::Digitizer(Digitizer::ContactIdentifier));
+        next(Usage::GenericDesktop(GenericDesktop::X));
+        next(Usage::GenericDesktop(GenericDesktop::Y));
+        assert_eq!(next(Usage::Digitizer(Digitizer::ContactCount)), 1);
+    }
+
+    #[test]
+    fn invalid_contacts() {
+        let report = Report::default();
+        let mut buf = [0; 128];
+
+        let none = TouchScreen {
+            contacts: 0,
+            ..screen()
+        };
+        assert!(report.bytes(&none, &mut buf).is_err());
+
+        let too_many = TouchScreen {
+            contacts: MAX_CONTACTS as u8 + 1,
+            ..screen()
+        };
+        assert!(report.bytes(&too_many, &mut buf).is_err());
+    }
+
+    #[test]
+    fn pen() {
+        let pen = Pen {
+            report_id: NonZeroU8::new(3).unwrap(),
+            x_max: 10_000,
+            y_max: 8_000,
+        };
+        let mut desc = [0; 128];
+        let n = pen.report_descriptor(&mut desc).unwrap();
+        let desc = &desc[..n];
+
+        assert_eq!(
+            report::length(desc, Type::Input, Some(pen.report_id)),
+            Ok(pen.report_size())
+        );
+
+        let report = PenReport {
+            tip_switch: false,
+            barrel_switch: true,
+            in_range: true,
+            x: 10_000,
+            y: 1234,
+        };
+
+        let mut buf = [0; 8];
+        let n = report.bytes(&pen, &mut buf).unwrap();
+        let bytes = &buf[..n];
+
+        let mut fields = Fields::new(desc)
+            .map(|field| field.unwrap())
+            .filter(|field| field.ty == Type::Input && !field.is_constant());
+        let mut next = |usage| {
+            let field = fields.next().unwrap();
+            assert_eq!(Usage::from_extended(field.usage_minimum), Ok(usage));
+            report::extract(bytes, &field).unwrap()
+        };
+
+        assert_eq!(next(Usage::Digitizer(Digitizer::TipSwitch)), 0);
+        assert_eq!(next(Usage::Digitizer(Digitizer::BarrelSwitch)), 1);
+        assert_eq!(next(Usage::Digitizer(Digitizer::InRange)), 1);
+        assert_eq!(next(Usage::GenericDesktop(GenericDesktop::X)), 10_000);
+        assert_eq!(next(Usage::GenericDesktop(GenericDesktop::Y)), 1234);
+
+        let out_of_range = PenReport {
+            x: 10_001,
+            ..report
+        };
+        assert!(out_of_range.bytes(&pen, &mut buf).is_err());
+    }
+}
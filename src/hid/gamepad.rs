@@ -0,0 +1,364 @@
+//! Configurable gamepad / joystick
+//!
+//! `Gamepad` describes the controls of the device and produces both its report descriptor and
+//! the wire representation of its input reports. The report layout is:
+//!
+//! - report ID, if any
+//! - one bit per button, padded to a byte boundary
+//! - the hat switch, if any, as a nibble padded to a byte
+//! - the axes, `axis_bits` each, padded to a byte boundary
+
+use core::num::NonZeroU8;
+
+use super::{
+    report::{self, Collection, Writer, CONSTANT, NULL_STATE, VARIABLE},
+    usage::{GenericDesktop, Page},
+};
+
+/// Maximum number of buttons
+pub const MAX_BUTTONS: u8 = 32;
+
+/// Usages of the axes, in report order
+pub const AXES: [GenericDesktop; 8] = [
+    GenericDesktop::X,
+    GenericDesktop::Y,
+    GenericDesktop::Z,
+    GenericDesktop::Rx,
+    GenericDesktop::Ry,
+    GenericDesktop::Rz,
+    GenericDesktop::Slider,
+    GenericDesktop::Dial,
+];
+
+/// Gamepad / joystick description
+#[derive(Clone, Copy)]
+pub struct Gamepad {
+    /// Application usage; usually `GenericDesktop::GamePad` or `GenericDesktop::Joystick`
+    pub usage: GenericDesktop,
+    /// Report ID; `None` if this is the only report of the interface
+    pub report_id: Option<NonZeroU8>,
+    /// Number of buttons (`0..=MAX_BUTTONS`)
+    pub buttons: u8,
+    /// Number of axes (`0..=AXES.len()`); the first `axes` usages of `AXES` are used
+    pub axes: u8,
+    /// Resolution of each axis in bits (`2..=16`); axes are signed and centered at `0`
+    pub axis_bits: u8,
+    /// Whether the device has a (8-way) hat switch
+    pub hat_switch: bool,
+}
+
+// Engineering Rotation: Degrees
+const UNIT_DEGREES: u32 = 0x14;
+
+impl Gamepad {
+    /// Returns the size, in bytes, of the input report including the report ID
+    pub fn report_size(&self) -> usize {
+        let prefix = if self.report_id.is_some() { 1 } else { 0 };
+        let hat = if self.hat_switch { 1 } else { 0 };
+
+        prefix + self.button_bytes() + hat + self.axes_bytes()
+    }
+
+    /// Logical range of the axes
+    ///
+    /// Returns `None` if the gamepad has no axes or `axis_bits` is out of range
+    pub fn axis_range(&self) -> Option<(i16, i16)> {
+        if self.axes == 0 || self.axis_bits < 2 || self.axis_bits > 16 {
+            return None;
+        }
+
+        let max = ((1u32 << (self.axis_bits - 1)) - 1) as i16;
+        Some((-max, max))
+    }
+
+    fn check(&self) -> Result<(), ()> {
+        if self.buttons > MAX_BUTTONS
+            || usize::from(self.axes) > AXES.len()
+            || (self.axes != 0 && self.axis_range().is_none())
+        {
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes the report descriptor into `buf`
+    ///
+    /// Returns the size of the report descriptor, or an error if the configuration is invalid or
+    /// `buf` is too small
+    pub fn report_descriptor(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        self.check()?;
+
+        let mut w = Writer::new(buf);
+        w.usage_page(Page::GenericDesktop)
+            .usage(self.usage as u16)
+            .collection(Collection::Application);
+
+        if let Some(id) = self.report_id {
+            w.report_id(id);
+        }
+
+        if self.buttons != 0 {
+            w.usage_page(Page::Button)
+                .usage_minimum(1)
+                .usage_maximum(u16::from(self.buttons))
+                .logical_minimum(0)
+                .logical_maximum(1)
+                .report_size(1)
+                .report_count(u16::from(self.buttons))
+                .input(VARIABLE);
+
+            let padding = self.button_bytes() as u8 * 8 - self.buttons;
+            if padding != 0 {
+                w.report_size(padding).report_count(1).input(CONSTANT);
+            }
+        }
+
+        if self.hat_switch {
+            w.usage_page(Page::GenericDesktop)
+                .usage(GenericDesktop::HatSwitch as u16)
+                .logical_minimum(0)
+                .logical_maximum(7)
+                .physical_minimum(0)
+                .physical_maximum(315)
+                .unit(UNIT_DEGREES)
+                .report_size(4)
+                .report_count(1)
+                .input(VARIABLE | NULL_STATE)
+                // restore the defaults for the items that follow
+                .physical_maximum(0)
+                .unit(0)
+                .input(CONSTANT);
+        }
+
+        if let Some((min, max)) = self.axis_range() {
+            w.usage_page(Page::GenericDesktop);
+            for axis in &AXES[..usize::from(self.axes)] {
+                w.usage(*axis as u16);
+            }
+            w.logical_minimum(i32::from(min))
+                .logical_maximum(i32::from(max))
+                .report_size(self.axis_bits)
+                .report_count(u16::from(self.axes))
+                .input(VARIABLE);
+
+            let bits = u16::from(self.axes) * u16::from(self.axis_bits);
+            let padding = (self.axes_bytes() * 8) as u16 - bits;
+            if padding != 0 {
+                w.report_size(padding as u8).report_count(1).input(CONSTANT);
+            }
+        }
+
+        w.end_collection().finish()
+    }
+
+    fn button_bytes(&self) -> usize {
+        (usize::from(self.buttons) + 7) / 8
+    }
+
+    fn axes_bytes(&self) -> usize {
+        (usize::from(self.axes) * usize::from(self.axis_bits) + 7) / 8
+    }
+}
+
+repr!(u8,
+      /// Hat switch direction
+      Hat {
+    /// North
+    Up = 0,
+    /// North-east
+    UpRight = 1,
+    /// East
+    Right = 2,
+    /// South-east
+    DownRight = 3,
+    /// South
+    Down = 4,
+    /// South-west
+    DownLeft = 5,
+    /// West
+    Left = 6,
+    /// North-west
+    UpLeft = 7,
+});
+
+// null state of the hat switch
+const HAT_CENTERED: u32 = 8;
+
+/// Gamepad input report
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Report {
+    /// Button state; bit `n` is button `n + 1`
+    pub buttons: u32,
+    /// Hat switch direction; `None` means centered
+    pub hat: Option<Hat>,
+    /// Axis values, in `AXES` order
+    pub axes: [i16; 8],
+}
+
+impl Report {
+    /// Writes the wire representation of this report into `buf`
+    ///
+    /// Returns the size of the report, or an error if the `gamepad` configuration is invalid,
+    /// `buf` is too small or a value is outside the range declared by `gamepad`
+    pub fn bytes(&self, gamepad: &Gamepad, buf: &mut [u8]) -> Result<usize, ()> {
+        gamepad.check()?;
+
+        let size = gamepad.report_size();
+        let buf = buf.get_mut(..size).ok_or(())?;
+        for byte in buf.iter_mut() {
+            *byte = 0;
+        }
+
+        let data = if let Some(id) = gamepad.report_id {
+            buf[0] = id.get();
+            &mut buf[1..]
+        } else {
+            buf
+        };
+
+        if gamepad.buttons < MAX_BUTTONS && self.buttons >> gamepad.buttons != 0 {
+            return Err(());
+        }
+        report::write_bits(data, 0, gamepad.buttons, self.buttons)?;
+
+        let mut offset = gamepad.button_bytes() as u16 * 8;
+        if gamepad.hat_switch {
+            let hat = self.hat.map(|hat| hat as u32).unwrap_or(HAT_CENTERED);
+            report::write_bits(data, offset, 4, hat)?;
+            offset += 8;
+        }
+
+        if let Some((min, max)) = gamepad.axis_range() {
+            for value in &self.axes[..usize::from(gamepad.axes)] {
+                if *value < min || *value > max {
+                    return Err(());
+                }
+
+                report::write_bits(data, offset, gamepad.axis_bits, *value as u32)?;
+                offset += u16::from(gamepad.axis_bits);
+            }
+        }
+
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU8;
+
+    use crate::hid::{
+        report::{self, Fields, Type},
+        usage::{GenericDesktop, Usage},
+    };
+
+    use super::{Gamepad, Hat, Report};
+
+    const GAMEPAD: Gamepad = Gamepad {
+        usage: GenericDesktop::GamePad,
+        report_id: NonZeroU8::new(3),
+        buttons: 10,
+        axes: 3,
+        axis_bits: 12,
+        hat_switch: true,
+    };
+
+    #[test]
+    fn descriptor() {
+        let mut desc = [0; 128];
+        let n = GAMEPAD.report_descriptor(&mut desc).unwrap();
+        let desc = &desc[..n];
+
+        // 1 (ID) + 2 (buttons) + 1 (hat) + 5 (3 x 12-bit axes, padded)
+        assert_eq!(GAMEPAD.report_size(), 9);
+        assert_eq!(
+            report::length(desc, Type::Input, GAMEPAD.report_id),
+            Ok(GAMEPAD.report_size())
+        );
+
+        assert!(GAMEPAD.report_descriptor(&mut [0; 16]).is_err());
+    }
+
+    #[test]
+    fn report() {
+        let mut desc = [0; 128];
+        let n = GAMEPAD.report_descriptor(&mut desc).unwrap();
+        let desc = &desc[..n];
+
+        let report = Report {
+            buttons: 0b10_0000_0001,
+            hat: Some(Hat::Left),
+            axes: [-2047, 0, 1000, 0, 0, 0, 0, 0],
+        };
+
+        let mut buf = [0; 16];
+        let n = report.bytes(&GAMEPAD, &mut buf).unwrap();
+        let bytes = &buf[..n];
+
+        let mut axes = 0;
+        for field in Fields::new(desc) {
+            let field = field.unwrap();
+            if field.is_constant() {
+                continue;
+            }
+
+            let value = report::extract(bytes, &field).unwrap();
+            match Usage::from_extended(field.usage_minimum).unwrap() {
+                Usage::Button(n) => {
+                    assert_eq!(value == 1, n == 1 || n == 10);
+                }
+                Usage::GenericDesktop(GenericDesktop::HatSwitch) => {
+                    assert_eq!(value, Hat::Left as i32)
+                }
+                _ => {
+                    assert_eq!(value, i32::from(report.axes[axes]));
+                    axes += 1;
+                }
+            }
+        }
+        assert_eq!(axes, 3);
+
+        // out of range
+        let report = Report {
+            axes: [-2048, 0, 0, 0, 0, 0, 0, 0],
+            ..Report::default()
+        };
+        assert!(report.bytes(&GAMEPAD, &mut buf).is_err());
+
+        // invalid configuration
+        let gamepad = Gamepad {
+            buttons: 33,
+            ..GAMEPAD
+        };
+        assert!(Report::default().bytes(&gamepad, &mut buf).is_err());
+    }
+
+    #[test]
+    fn buttons_only() {
+        let gamepad = Gamepad {
+            usage: GenericDesktop::GamePad,
+            report_id: None,
+            buttons: 4,
+            axes: 0,
+            axis_bits: 0,
+            hat_switch: false,
+        };
+        assert_eq!(gamepad.axis_range(), None);
+
+        let mut desc = [0; 64];
+        let n = gamepad.report_descriptor(&mut desc).unwrap();
+        assert_eq!(
+            report::length(&desc[..n], Type::Input, None),
+            Ok(gamepad.report_size())
+        );
+
+        let report = Report {
+            buttons: 0b1001,
+            ..Report::default()
+        };
+        let mut buf = [0; 4];
+        assert_eq!(report.bytes(&gamepad, &mut buf), Ok(1));
+        assert_eq!(buf[0], 0b1001);
+    }
+}
@@ -0,0 +1,159 @@
+//! Idle rate tracking
+//!
+//! See section 7.2.4 of (HID1.11)
+
+use core::num::NonZeroU8;
+
+use super::{Kind, Request};
+
+/// Maximum number of report IDs with their own idle rate
+pub const MAX_REPORTS: usize = 8;
+
+/// Duration of one idle rate unit in milliseconds
+pub const MS_PER_UNIT: u32 = 4;
+
+/// Idle rate of a boot keyboard after reset (500 ms)
+pub const KEYBOARD_DEFAULT: Option<NonZeroU8> = NonZeroU8::new(125);
+
+/// Per report ID idle rate tracker
+///
+/// An idle rate of `None` means "indefinite": the report is only sent when its data changes
+pub struct Tracker {
+    // idle rate of the reports not listed in `rates`
+    default: Option<NonZeroU8>,
+    rates: [(u8, Option<NonZeroU8>); MAX_REPORTS],
+    nrates: usize,
+}
+
+impl Tracker {
+    /// Creates a tracker where all reports use the `default` idle rate
+    pub const fn new(default: Option<NonZeroU8>) -> Self {
+        Tracker {
+            default,
+            rates: [(0, None); MAX_REPORTS],
+            nrates: 0,
+        }
+    }
+
+    /// Records a SET_IDLE request
+    ///
+    /// A `report_id` of `None` changes the idle rate of all reports. Returns an error if there's
+    /// no space left to track another report ID
+    pub fn set(
+        &mut self,
+        report_id: Option<NonZeroU8>,
+        duration: Option<NonZeroU8>,
+    ) -> Result<(), ()> {
+        let id = match report_id {
+            Some(id) => id.get(),
+            None => {
+                self.default = duration;
+                self.nrates = 0;
+                return Ok(());
+            }
+        };
+
+        if let Some(rate) = self.rates[..self.nrates]
+            .iter_mut()
+            .find(|(rid, _)| *rid == id)
+        {
+            rate.1 = duration;
+        } else if self.nrates < MAX_REPORTS {
+            self.rates[self.nrates] = (id, duration);
+            self.nrates += 1;
+        } else {
+            return Err(());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the idle rate of the specified report; this is the answer to GET_IDLE
+    pub fn get(&self, report_id: Option<NonZeroU8>) -> Option<NonZeroU8> {
+        report_id
+            .and_then(|id| {
+                self.rates[..self.nrates]
+                    .iter()
+                    .find(|(rid, _)| *rid == id.get())
+            })
+            .map(|(_, duration)| *duration)
+            .unwrap_or(self.default)
+    }
+
+    /// Updates the tracker with a SET_IDLE request and returns the reply to a GET_IDLE request
+    ///
+    /// Other requests are ignored and return `Ok(None)`
+    pub fn handle(&mut self, request: &Request) -> Result<Option<[u8; 1]>, ()> {
+        match request.kind {
+            Kind::SetIdle {
+                duration,
+                report_id,
+            } => self.set(report_id, duration).map(|_| None),
+
+            Kind::GetIdle { report_id } => {
+                Ok(Some([self.get(report_id).map(|nz| nz.get()).unwrap_or(0)]))
+            }
+
+            _ => Ok(None),
+        }
+    }
+
+    /// Decides whether the specified report must be sent now
+    ///
+    /// `elapsed` is the number of milliseconds since the report was last sent and `changed`
+    /// indicates whether its data changed since then
+    pub fn must_send(&self, report_id: Option<NonZeroU8>, elapsed: u32, changed: bool) -> bool {
+        if changed {
+            return true;
+        }
+
+        match self.get(report_id) {
+            Some(duration) => elapsed >= u32::from(duration.get()) * MS_PER_UNIT,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU8;
+
+    use crate::{hid, Request};
+
+    use super::Tracker;
+
+    #[test]
+    fn idle() {
+        let mut tracker = Tracker::new(super::KEYBOARD_DEFAULT);
+        assert!(!tracker.must_send(None, 499, false));
+        assert!(tracker.must_send(None, 500, false));
+        assert!(tracker.must_send(None, 1, true));
+
+        // SET_IDLE(report 2, indefinite)
+        let set_idle = match Request::parse(0b0010_0001, 0x0A, 0x00_02, 0, 0) {
+            Ok(Request::Hid(request)) => request,
+            _ => panic!(),
+        };
+        assert_eq!(tracker.handle(&set_idle), Ok(None));
+        assert!(!tracker.must_send(NonZeroU8::new(2), 10_000, false));
+        assert!(tracker.must_send(NonZeroU8::new(1), 500, false));
+
+        // GET_IDLE(report 1)
+        let get_idle = match Request::parse(0b1010_0001, 0x02, 0x00_01, 0, 1) {
+            Ok(Request::Hid(request)) => request,
+            _ => panic!(),
+        };
+        assert_eq!(
+            get_idle.kind,
+            hid::Kind::GetIdle {
+                report_id: NonZeroU8::new(1)
+            }
+        );
+        assert_eq!(tracker.handle(&get_idle), Ok(Some([125])));
+
+        // SET_IDLE(all reports, 8 ms) overrides the per-report rates
+        tracker.set(None, NonZeroU8::new(2)).unwrap();
+        assert_eq!(tracker.get(NonZeroU8::new(2)), NonZeroU8::new(2));
+        assert!(tracker.must_send(NonZeroU8::new(2), 8, false));
+    }
+}
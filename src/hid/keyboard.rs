@@ -0,0 +1,299 @@
+//! N-key rollover keyboard with Consumer Control (media) keys
+//!
+//! Both reports share a single interface and are told apart by their report ID
+
+use super::{
+    boot::Modifiers,
+    usage::{Consumer, Keyboard},
+};
+
+/// Report ID of the keyboard report (and of the LED output report)
+pub const KEYBOARD_REPORT_ID: u8 = 1;
+
+/// Report ID of the Consumer Control report
+pub const CONSUMER_REPORT_ID: u8 = 2;
+
+/// Number of bytes in the key bitmap; covers keyboard usages `0x00..=0x9F`
+pub const BITMAP_SIZE: usize = 20;
+
+/// Report descriptor that declares `NkroReport`, `ConsumerReport` and the keyboard LED output
+/// report (`boot::Leds` prefixed with `KEYBOARD_REPORT_ID`)
+#[rustfmt::skip]
+pub const REPORT_DESCRIPTOR: [u8; 74] = [
+    0x05, 0x01,                 // USAGE_PAGE (Generic Desktop)
+    0x09, 0x06,                 // USAGE (Keyboard)
+    0xa1, 0x01,                 // COLLECTION (Application)
+    0x85, KEYBOARD_REPORT_ID,   //   REPORT_ID (1)
+    0x05, 0x07,                 //   USAGE_PAGE (Keyboard)
+    0x19, 0xe0,                 //   USAGE_MINIMUM (Keyboard LeftControl)
+    0x29, 0xe7,                 //   USAGE_MAXIMUM (Keyboard Right GUI)
+    0x15, 0x00,                 //   LOGICAL_MINIMUM (0)
+    0x25, 0x01,                 //   LOGICAL_MAXIMUM (1)
+    0x75, 0x01,                 //   REPORT_SIZE (1)
+    0x95, 0x08,                 //   REPORT_COUNT (8)
+    0x81, 0x02,                 //   INPUT (Data,Var,Abs)
+    0x19, 0x00,                 //   USAGE_MINIMUM (0)
+    0x29, 0x9f,                 //   USAGE_MAXIMUM (159)
+    0x95, 0xa0,                 //   REPORT_COUNT (160)
+    0x81, 0x02,                 //   INPUT (Data,Var,Abs)
+    0x05, 0x08,                 //   USAGE_PAGE (LEDs)
+    0x19, 0x01,                 //   USAGE_MINIMUM (Num Lock)
+    0x29, 0x05,                 //   USAGE_MAXIMUM (Kana)
+    0x95, 0x05,                 //   REPORT_COUNT (5)
+    0x91, 0x02,                 //   OUTPUT (Data,Var,Abs)
+    0x95, 0x01,                 //   REPORT_COUNT (1)
+    0x75, 0x03,                 //   REPORT_SIZE (3)
+    0x91, 0x01,                 //   OUTPUT (Cnst)
+    0xc0,                       // END_COLLECTION
+    0x05, 0x0c,                 // USAGE_PAGE (Consumer)
+    0x09, 0x01,                 // USAGE (Consumer Control)
+    0xa1, 0x01,                 // COLLECTION (Application)
+    0x85, CONSUMER_REPORT_ID,   //   REPORT_ID (2)
+    0x15, 0x00,                 //   LOGICAL_MINIMUM (0)
+    0x26, 0xff, 0x03,           //   LOGICAL_MAXIMUM (1023)
+    0x19, 0x00,                 //   USAGE_MINIMUM (Unassigned)
+    0x2a, 0xff, 0x03,           //   USAGE_MAXIMUM (1023)
+    0x75, 0x10,                 //   REPORT_SIZE (16)
+    0x95, 0x01,                 //   REPORT_COUNT (1)
+    0x81, 0x00,                 //   INPUT (Data,Ary,Abs)
+    0xc0,                       // END_COLLECTION
+];
+
+/// N-key rollover keyboard input report
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NkroReport {
+    /// Modifier keys
+    pub modifiers: Modifiers,
+    /// One bit per key; bit `n` of byte `m` is the keyboard usage `8 * m + n`
+    pub keys: [u8; BITMAP_SIZE],
+}
+
+impl NkroReport {
+    /// The size of this report on the wire, including the report ID
+    pub const SIZE: u8 = 2 + BITMAP_SIZE as u8;
+
+    /// Marks `key` as pressed
+    ///
+    /// Returns an error if `key` is not covered by the key bitmap
+    pub fn press(&mut self, key: Keyboard) -> Result<(), ()> {
+        self.set(key, true)
+    }
+
+    /// Marks `key` as released
+    ///
+    /// Returns an error if `key` is not covered by the key bitmap
+    pub fn release(&mut self, key: Keyboard) -> Result<(), ()> {
+        self.set(key, false)
+    }
+
+    /// Returns `true` if `key` is pressed
+    pub fn is_pressed(&self, key: Keyboard) -> bool {
+        let mut modifiers = self.modifiers;
+        if let Some(modifier) = modifier(&mut modifiers, key) {
+            return *modifier;
+        }
+
+        let (byte, bit) = position(key);
+        self.keys
+            .get(byte)
+            .map(|byte| byte & (1 << bit) != 0)
+            .unwrap_or(false)
+    }
+
+    fn set(&mut self, key: Keyboard, pressed: bool) -> Result<(), ()> {
+        if let Some(modifier) = modifier(&mut self.modifiers, key) {
+            *modifier = pressed;
+            return Ok(());
+        }
+
+        let (byte, bit) = position(key);
+        let byte = self.keys.get_mut(byte).ok_or(())?;
+        if pressed {
+            *byte |= 1 << bit;
+        } else {
+            *byte &= !(1 << bit);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the wire representation of this report
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[0] = KEYBOARD_REPORT_ID;
+        bytes[1] = self.modifiers.byte();
+        bytes[2..].copy_from_slice(&self.keys);
+        bytes
+    }
+
+    /// Parses a keyboard report
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) || bytes[0] != KEYBOARD_REPORT_ID {
+            return Err(());
+        }
+
+        let mut keys = [0; BITMAP_SIZE];
+        keys.copy_from_slice(&bytes[2..]);
+
+        Ok(NkroReport {
+            modifiers: Modifiers::from(bytes[1]),
+            keys,
+        })
+    }
+}
+
+fn position(key: Keyboard) -> (usize, u8) {
+    let usage = key as usize;
+    (usage / 8, (usage % 8) as u8)
+}
+
+fn modifier(modifiers: &mut Modifiers, key: Keyboard) -> Option<&mut bool> {
+    Some(match key {
+        Keyboard::LeftControl => &mut modifiers.left_ctrl,
+        Keyboard::LeftShift => &mut modifiers.left_shift,
+        Keyboard::LeftAlt => &mut modifiers.left_alt,
+        Keyboard::LeftGui => &mut modifiers.left_gui,
+        Keyboard::RightControl => &mut modifiers.right_ctrl,
+        Keyboard::RightShift => &mut modifiers.right_shift,
+        Keyboard::RightAlt => &mut modifiers.right_alt,
+        Keyboard::RightGui => &mut modifiers.right_gui,
+        _ => return None,
+    })
+}
+
+/// Consumer Control input report
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConsumerReport {
+    /// Consumer usage ID of the control being pressed (`0..=CONSUMER_USAGE_MAXIMUM`); `0`
+    /// (Unassigned) means none
+    pub usage: u16,
+}
+
+/// Largest Consumer usage ID the report descriptor declares
+pub const CONSUMER_USAGE_MAXIMUM: u16 = 0x3FF;
+
+impl ConsumerReport {
+    /// The size of this report on the wire, including the report ID
+    pub const SIZE: u8 = 3;
+
+    /// Report with `usage` pressed
+    pub fn new(usage: Consumer) -> Self {
+        ConsumerReport {
+            usage: usage as u16,
+        }
+    }
+
+    /// Returns the pressed control, if it's one of the known `Consumer` usages
+    pub fn consumer(&self) -> Option<Consumer> {
+        Consumer::_from(self.usage)
+    }
+
+    /// Returns the wire representation of this report
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [
+            CONSUMER_REPORT_ID,
+            self.usage as u8,
+            (self.usage >> 8) as u8,
+        ]
+    }
+
+    /// Parses a Consumer Control report
+    ///
+    /// Any usage ID in the logical range is accepted, even if it has no `Consumer` variant
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) || bytes[0] != CONSUMER_REPORT_ID {
+            return Err(());
+        }
+
+        let usage = u16::from(bytes[1]) | u16::from(bytes[2]) << 8;
+        if usage > CONSUMER_USAGE_MAXIMUM {
+            return Err(());
+        }
+
+        Ok(ConsumerReport { usage })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU8;
+
+    use crate::hid::{
+        report::{self, Fields, Type},
+        usage::{Consumer, Keyboard, Usage},
+    };
+
+    use super::{ConsumerReport, NkroReport, CONSUMER_REPORT_ID, KEYBOARD_REPORT_ID};
+
+    #[test]
+    fn descriptor() {
+        let desc = &super::REPORT_DESCRIPTOR[..];
+
+        assert_eq!(
+            report::length(desc, Type::Input, NonZeroU8::new(KEYBOARD_REPORT_ID)),
+            Ok(usize::from(NkroReport::SIZE))
+        );
+        assert_eq!(
+            report::length(desc, Type::Output, NonZeroU8::new(KEYBOARD_REPORT_ID)),
+            Ok(2)
+        );
+        assert_eq!(
+            report::length(desc, Type::Input, NonZeroU8::new(CONSUMER_REPORT_ID)),
+            Ok(usize::from(ConsumerReport::SIZE))
+        );
+    }
+
+    #[test]
+    fn nkro() {
+        let mut report = NkroReport::default();
+        report.press(Keyboard::LeftShift).unwrap();
+        report.press(Keyboard::A).unwrap();
+        report.press(Keyboard::F24).unwrap();
+        report.press(Keyboard::Enter).unwrap();
+        report.release(Keyboard::Enter).unwrap();
+
+        assert!(report.is_pressed(Keyboard::A));
+        assert!(!report.is_pressed(Keyboard::Enter));
+        assert!(report.press(Keyboard::RightGui).is_ok());
+
+        let bytes = report.bytes();
+        assert_eq!(NkroReport::parse(&bytes), Ok(report));
+
+        // cross-check the report against the descriptor
+        for field in Fields::new(&super::REPORT_DESCRIPTOR) {
+            let field = field.unwrap();
+            if field.ty != Type::Input || field.report_id != NonZeroU8::new(KEYBOARD_REPORT_ID) {
+                continue;
+            }
+
+            let usage = Usage::from_extended(field.usage_minimum);
+            let pressed = report::extract(&bytes, &field) == Ok(1);
+            let expected = [
+                Keyboard::LeftShift,
+                Keyboard::RightGui,
+                Keyboard::A,
+                Keyboard::F24,
+            ]
+            .iter()
+            .any(|key| usage == Ok(Usage::Keyboard(*key)));
+            assert_eq!(pressed, expected);
+        }
+    }
+
+    #[test]
+    fn consumer() {
+        let report = ConsumerReport::new(Consumer::AcSearch);
+
+        assert_eq!(report.bytes(), [CONSUMER_REPORT_ID, 0x21, 0x02]);
+        assert_eq!(ConsumerReport::parse(&report.bytes()), Ok(report));
+        assert_eq!(report.consumer(), Some(Consumer::AcSearch));
+
+        // AL Consumer Control Configuration (0x183) has no `Consumer` variant but is valid
+        let config = ConsumerReport::parse(&[CONSUMER_REPORT_ID, 0x83, 0x01]).unwrap();
+        assert_eq!(config.usage, 0x183);
+        assert_eq!(config.consumer(), None);
+
+        // outside the logical range
+        assert!(ConsumerReport::parse(&[CONSUMER_REPORT_ID, 0x00, 0x04]).is_err());
+    }
+}
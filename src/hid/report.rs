@@ -0,0 +1,975 @@
+//! Report descriptor parser and report field access
+//!
+//! See section 6.2.2 of (HID1.11)
+
+use core::num::NonZeroU8;
+
+use super::usage::Page;
+
+/// Report type
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Type {
+    /// Input report (device to host)
+    Input = 1,
+    /// Output report (host to device)
+    Output = 2,
+    /// Feature report
+    Feature = 3,
+}
+
+impl Type {
+    fn index(self) -> usize {
+        self as usize - 1
+    }
+}
+
+/// A single data field of a report
+///
+/// A Main item with a Report Count of N produces N fields
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Field {
+    /// ID of the report this field belongs to; `None` if the descriptor uses no report IDs
+    pub report_id: Option<NonZeroU8>,
+    /// Type of the report this field belongs to
+    pub ty: Type,
+    /// Data bits of the Main item that declared this field
+    pub flags: u16,
+    /// Offset, in bits, from the start of the report data (the report ID prefix is not included)
+    pub offset: u16,
+    /// Size in bits (`1..=32`)
+    pub size: u8,
+    /// Logical Minimum
+    pub logical_minimum: i32,
+    /// Logical Maximum
+    pub logical_maximum: i32,
+    /// First usage (Usage Page in the high half-word) associated to this field
+    pub usage_minimum: u32,
+    /// Last usage associated to this field; equal to `usage_minimum` for variable fields
+    pub usage_maximum: u32,
+    /// Usages of an array field declared with a list of Usage items (rather than a Usage
+    /// Minimum / Usage Maximum range); empty otherwise
+    pub usages: Usages,
+}
+
+/// List of usages (Usage Page in the high half-word) selectable by an array field
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Usages {
+    list: [u32; MAX_USAGES],
+    len: u8,
+}
+
+impl Usages {
+    /// Maximum number of usages in the list
+    pub const CAPACITY: usize = MAX_USAGES;
+
+    /// Creates an empty list
+    pub const fn empty() -> Self {
+        Usages {
+            list: [0; MAX_USAGES],
+            len: 0,
+        }
+    }
+
+    /// Creates a list from `usages`
+    ///
+    /// Returns an error if there are more than `CAPACITY` usages
+    pub fn new(usages: &[u32]) -> Result<Self, ()> {
+        let mut list = [0; MAX_USAGES];
+        list.get_mut(..usages.len())
+            .ok_or(())?
+            .copy_from_slice(usages);
+
+        Ok(Usages {
+            list,
+            len: usages.len() as u8,
+        })
+    }
+
+    /// Returns the usages as a slice
+    pub fn as_slice(&self) -> &[u32] {
+        &self.list[..usize::from(self.len)]
+    }
+}
+
+impl Default for Usages {
+    fn default() -> Self {
+        Usages::empty()
+    }
+}
+
+/// Main item flag: Constant (as opposed to Data)
+pub const CONSTANT: u16 = 1 << 0;
+/// Main item flag: Variable (as opposed to Array)
+pub const VARIABLE: u16 = 1 << 1;
+/// Main item flag: Relative (as opposed to Absolute)
+pub const RELATIVE: u16 = 1 << 2;
+/// Main item flag: Null State -- out of range values mean "no data"
+pub const NULL_STATE: u16 = 1 << 6;
+
+impl Field {
+    /// Constant field -- usually padding
+    pub fn is_constant(&self) -> bool {
+        self.flags & CONSTANT != 0
+    }
+
+    /// Array fields contain usage selectors; variable fields contain the value of a single usage
+    pub fn is_array(&self) -> bool {
+        self.flags & VARIABLE == 0
+    }
+
+    /// Relative (as opposed to absolute) data
+    pub fn is_relative(&self) -> bool {
+        self.flags & RELATIVE != 0
+    }
+
+    /// Returns the usage that `value` (as returned by `extract`) refers to
+    ///
+    /// For variable fields this is always the field usage. For array fields `None` is returned
+    /// when `value` is out of range, i.e. no usage is selected
+    pub fn usage(&self, value: i32) -> Option<u32> {
+        if !self.is_array() {
+            return Some(self.usage_minimum);
+        }
+
+        if value < self.logical_minimum || value > self.logical_maximum {
+            return None;
+        }
+
+        // can't overflow: `value` is not below `logical_minimum`
+        let index = (i64::from(value) - i64::from(self.logical_minimum)) as u32;
+        let usages = self.usages.as_slice();
+        if !usages.is_empty() {
+            return usages.get(index as usize).cloned();
+        }
+
+        let usage = self.usage_minimum.checked_add(index)?;
+        if usage <= self.usage_maximum {
+            Some(usage)
+        } else {
+            None
+        }
+    }
+
+    fn check(&self) -> Result<(), ()> {
+        if self.size == 0 || self.size > 32 {
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn signed(&self) -> bool {
+        self.logical_minimum < 0
+    }
+}
+
+/// Reads the value of `field` from `report`
+///
+/// `report` must start with the report ID byte if the field has one. Values are sign extended
+/// when Logical Minimum is negative. Returns an error if the field size is not in `1..=32`
+pub fn extract(report: &[u8], field: &Field) -> Result<i32, ()> {
+    field.check()?;
+    let data = data(report, field)?;
+    let mut value = read_bits(data, field.offset, field.size)?;
+
+    if field.signed() && field.size < 32 && value & (1 << (field.size - 1)) != 0 {
+        value |= !0 << field.size;
+    }
+
+    Ok(value as i32)
+}
+
+/// Writes `value` into the `field` of the report `buf`
+///
+/// The report ID prefix is written as well. Returns an error if `buf` is too small, the field size
+/// is not in `1..=32` or `value` doesn't fit in the field
+pub fn pack(buf: &mut [u8], field: &Field, value: i32) -> Result<(), ()> {
+    field.check()?;
+
+    if field.size < 32 {
+        let fits = if field.signed() {
+            let limit = 1i32 << (field.size - 1);
+            value >= -limit && value < limit
+        } else {
+            value >= 0 && (value as u32) < 1 << field.size
+        };
+
+        if !fits {
+            return Err(());
+        }
+    }
+
+    if let Some(id) = field.report_id {
+        *buf.first_mut().ok_or(())? = id.get();
+    }
+
+    let data = if field.report_id.is_some() {
+        &mut buf[1..]
+    } else {
+        buf
+    };
+
+    write_bits(data, field.offset, field.size, value as u32)
+}
+
+// Reads `size` bits, starting at bit `offset`, as a little endian value
+pub(crate) fn read_bits(data: &[u8], offset: u16, size: u8) -> Result<u32, ()> {
+    if size > 32 {
+        return Err(());
+    }
+
+    let mut value = 0u32;
+    for i in 0..usize::from(size) {
+        let bit = usize::from(offset) + i;
+        let byte = data.get(bit / 8).ok_or(())?;
+        if byte & (1 << (bit % 8)) != 0 {
+            value |= 1 << i;
+        }
+    }
+
+    Ok(value)
+}
+
+// Writes the `size` lower bits of `value` starting at bit `offset`
+pub(crate) fn write_bits(data: &mut [u8], offset: u16, size: u8, value: u32) -> Result<(), ()> {
+    if size > 32 {
+        return Err(());
+    }
+
+    for i in 0..usize::from(size) {
+        let bit = usize::from(offset) + i;
+        let byte = data.get_mut(bit / 8).ok_or(())?;
+        if value & (1 << i) != 0 {
+            *byte |= 1 << (bit % 8);
+        } else {
+            *byte &= !(1 << (bit % 8));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the size, in bytes, of the specified report including its report ID prefix
+pub fn length(descriptor: &[u8], ty: Type, report_id: Option<NonZeroU8>) -> Result<usize, ()> {
+    let mut bits = 0;
+    for field in Fields::new(descriptor) {
+        let field = field?;
+        if field.ty == ty && field.report_id == report_id {
+            bits = bits.max(usize::from(field.offset) + usize::from(field.size));
+        }
+    }
+
+    let prefix = if report_id.is_some() { 1 } else { 0 };
+    Ok(prefix + (bits + 7) / 8)
+}
+
+fn data<'a>(report: &'a [u8], field: &Field) -> Result<&'a [u8], ()> {
+    if let Some(id) = field.report_id {
+        if report.first() != Some(&id.get()) {
+            return Err(());
+        }
+
+        Ok(&report[1..])
+    } else {
+        Ok(report)
+    }
+}
+
+repr!(u8,
+      /// Collection type
+      Collection {
+    /// Physical (group of axes)
+    Physical = 0x00,
+    /// Application (mouse, keyboard)
+    Application = 0x01,
+    /// Logical (interrelated data)
+    Logical = 0x02,
+    /// Report
+    Report = 0x03,
+    /// Named Array
+    NamedArray = 0x04,
+    /// Usage Switch
+    UsageSwitch = 0x05,
+    /// Usage Modifier
+    UsageModifier = 0x06,
+});
+
+/// Report descriptor writer
+///
+/// Items are encoded using the smallest possible data size. Running out of space is reported by
+/// `finish`
+pub struct Writer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+    overflow: bool,
+}
+
+impl<'a> Writer<'a> {
+    /// Starts writing a report descriptor into `buf`
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Writer {
+            buf,
+            pos: 0,
+            overflow: false,
+        }
+    }
+
+    /// Usage Page item
+    pub fn usage_page(&mut self, page: Page) -> &mut Self {
+        self.unsigned(GLOBAL, USAGE_PAGE, u32::from(page as u16))
+    }
+
+    /// Usage item; the usage ID is relative to the current Usage Page
+    pub fn usage(&mut self, id: u16) -> &mut Self {
+        self.unsigned(LOCAL, USAGE, u32::from(id))
+    }
+
+    /// Usage Minimum item
+    pub fn usage_minimum(&mut self, id: u16) -> &mut Self {
+        self.unsigned(LOCAL, USAGE_MINIMUM, u32::from(id))
+    }
+
+    /// Usage Maximum item
+    pub fn usage_maximum(&mut self, id: u16) -> &mut Self {
+        self.unsigned(LOCAL, USAGE_MAXIMUM, u32::from(id))
+    }
+
+    /// Logical Minimum item
+    pub fn logical_minimum(&mut self, value: i32) -> &mut Self {
+        self.signed(GLOBAL, LOGICAL_MINIMUM, value)
+    }
+
+    /// Logical Maximum item
+    pub fn logical_maximum(&mut self, value: i32) -> &mut Self {
+        self.signed(GLOBAL, LOGICAL_MAXIMUM, value)
+    }
+
+    /// Physical Minimum item
+    pub fn physical_minimum(&mut self, value: i32) -> &mut Self {
+        self.signed(GLOBAL, PHYSICAL_MINIMUM, value)
+    }
+
+    /// Physical Maximum item
+    pub fn physical_maximum(&mut self, value: i32) -> &mut Self {
+        self.signed(GLOBAL, PHYSICAL_MAXIMUM, value)
+    }
+
+    /// Unit Exponent item
+    pub fn unit_exponent(&mut self, exponent: i8) -> &mut Self {
+        // 4-bit two's complement
+        self.unsigned(GLOBAL, UNIT_EXPONENT, u32::from(exponent as u8 & 0xF))
+    }
+
+    /// Unit item
+    pub fn unit(&mut self, unit: u32) -> &mut Self {
+        self.unsigned(GLOBAL, UNIT, unit)
+    }
+
+    /// Report Size item (in bits)
+    pub fn report_size(&mut self, bits: u8) -> &mut Self {
+        self.unsigned(GLOBAL, REPORT_SIZE, u32::from(bits))
+    }
+
+    /// Report ID item
+    pub fn report_id(&mut self, id: NonZeroU8) -> &mut Self {
+        self.unsigned(GLOBAL, REPORT_ID, u32::from(id.get()))
+    }
+
+    /// Report Count item
+    pub fn report_count(&mut self, count: u16) -> &mut Self {
+        self.unsigned(GLOBAL, REPORT_COUNT, u32::from(count))
+    }
+
+    /// Collection item
+    pub fn collection(&mut self, collection: Collection) -> &mut Self {
+        self.unsigned(MAIN, COLLECTION, collection as u32)
+    }
+
+    /// End Collection item
+    pub fn end_collection(&mut self) -> &mut Self {
+        self.item(MAIN, END_COLLECTION, &[])
+    }
+
+    /// Input item
+    pub fn input(&mut self, flags: u16) -> &mut Self {
+        self.unsigned(MAIN, INPUT, u32::from(flags))
+    }
+
+    /// Output item
+    pub fn output(&mut self, flags: u16) -> &mut Self {
+        self.unsigned(MAIN, OUTPUT, u32::from(flags))
+    }
+
+    /// Feature item
+    pub fn feature(&mut self, flags: u16) -> &mut Self {
+        self.unsigned(MAIN, FEATURE, u32::from(flags))
+    }
+
+    /// Returns the size of the report descriptor
+    ///
+    /// Returns an error if the buffer was too small to hold the descriptor
+    pub fn finish(&self) -> Result<usize, ()> {
+        if self.overflow {
+            Err(())
+        } else {
+            Ok(self.pos)
+        }
+    }
+
+    fn unsigned(&mut self, ty: u8, tag: u8, value: u32) -> &mut Self {
+        let bytes = value.to_le_bytes();
+        if value <= u32::from(u8::MAX) {
+            self.item(ty, tag, &bytes[..1])
+        } else if value <= u32::from(u16::MAX) {
+            self.item(ty, tag, &bytes[..2])
+        } else {
+            self.item(ty, tag, &bytes)
+        }
+    }
+
+    fn signed(&mut self, ty: u8, tag: u8, value: i32) -> &mut Self {
+        let bytes = value.to_le_bytes();
+        if i32::from(value as i8) == value {
+            self.item(ty, tag, &bytes[..1])
+        } else if i32::from(value as i16) == value {
+            self.item(ty, tag, &bytes[..2])
+        } else {
+            self.item(ty, tag, &bytes)
+        }
+    }
+
+    fn item(&mut self, ty: u8, tag: u8, data: &[u8]) -> &mut Self {
+        let size = if data.len() == 4 { 3 } else { data.len() as u8 };
+
+        match self.buf.get_mut(self.pos..self.pos + 1 + data.len()) {
+            Some(item) => {
+                item[0] = tag << 4 | ty << 2 | size;
+                item[1..].copy_from_slice(data);
+                self.pos += 1 + data.len();
+            }
+            None => self.overflow = true,
+        }
+
+        self
+    }
+}
+
+// Item types
+const MAIN: u8 = 0;
+const GLOBAL: u8 = 1;
+const LOCAL: u8 = 2;
+
+// Main item tags
+const INPUT: u8 = 0x8;
+const OUTPUT: u8 = 0x9;
+const COLLECTION: u8 = 0xA;
+const FEATURE: u8 = 0xB;
+const END_COLLECTION: u8 = 0xC;
+
+// Global item tags
+const USAGE_PAGE: u8 = 0x0;
+const LOGICAL_MINIMUM: u8 = 0x1;
+const LOGICAL_MAXIMUM: u8 = 0x2;
+const PHYSICAL_MINIMUM: u8 = 0x3;
+const PHYSICAL_MAXIMUM: u8 = 0x4;
+const UNIT_EXPONENT: u8 = 0x5;
+const UNIT: u8 = 0x6;
+const REPORT_SIZE: u8 = 0x7;
+const REPORT_ID: u8 = 0x8;
+const REPORT_COUNT: u8 = 0x9;
+const PUSH: u8 = 0xA;
+const POP: u8 = 0xB;
+
+// Local item tags
+const USAGE: u8 = 0x0;
+const USAGE_MINIMUM: u8 = 0x1;
+const USAGE_MAXIMUM: u8 = 0x2;
+
+const LONG_ITEM: u8 = 0xFE;
+
+// Maximum depth of the PUSH / POP stack
+const STACK_SIZE: usize = 4;
+// Maximum number of Usage items before a Main item
+const MAX_USAGES: usize = 32;
+// Maximum number of distinct report IDs
+const MAX_REPORTS: usize = 16;
+
+#[derive(Clone, Copy, Default)]
+struct Global {
+    usage_page: u16,
+    logical_minimum: i32,
+    logical_maximum: i32,
+    // Logical Maximum without sign extension
+    logical_maximum_unsigned: u32,
+    report_size: u32,
+    report_id: u8,
+    report_count: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Local {
+    usages: [u32; MAX_USAGES],
+    nusages: usize,
+    usage_minimum: Option<u32>,
+    usage_maximum: Option<u32>,
+}
+
+impl Local {
+    const fn new() -> Self {
+        Local {
+            usages: [0; MAX_USAGES],
+            nusages: 0,
+            usage_minimum: None,
+            usage_maximum: None,
+        }
+    }
+}
+
+// A Main item being expanded into fields
+#[derive(Clone, Copy)]
+struct Pending {
+    field: Field,
+    usages: [u32; MAX_USAGES],
+    nusages: usize,
+    count: u32,
+    index: u32,
+}
+
+/// Iterator over the data fields declared in a report descriptor
+///
+/// Returns an error (and then stops) if the descriptor is malformed or exceeds the limits of this
+/// parser (e.g. fields larger than 32 bits)
+pub struct Fields<'a> {
+    bytes: &'a [u8],
+    global: Global,
+    stack: [Global; STACK_SIZE],
+    depth: usize,
+    local: Local,
+    // (report ID, bit offset for each report type)
+    offsets: [(u8, [u16; 3]); MAX_REPORTS],
+    nreports: usize,
+    pending: Option<Pending>,
+    done: bool,
+}
+
+impl<'a> Fields<'a> {
+    /// Starts parsing the given report descriptor
+    pub fn new(descriptor: &'a [u8]) -> Self {
+        Fields {
+            bytes: descriptor,
+            global: Global::default(),
+            stack: [Global::default(); STACK_SIZE],
+            depth: 0,
+            local: Local::new(),
+            offsets: [(0, [0; 3]); MAX_REPORTS],
+            nreports: 0,
+            pending: None,
+            done: false,
+        }
+    }
+
+    fn offset(&mut self, id: u8, ty: Type) -> Result<&mut u16, ()> {
+        let pos = match self.offsets[..self.nreports]
+            .iter()
+            .position(|(rid, _)| *rid == id)
+        {
+            Some(pos) => pos,
+            None => {
+                if self.nreports == MAX_REPORTS {
+                    return Err(());
+                }
+
+                self.offsets[self.nreports] = (id, [0; 3]);
+                self.nreports += 1;
+                self.nreports - 1
+            }
+        };
+
+        Ok(&mut self.offsets[pos].1[ty.index()])
+    }
+
+    fn usage(&self, data: u32, size: usize) -> u32 {
+        // 4-byte usages include the usage page
+        if size == 4 {
+            data
+        } else {
+            u32::from(self.global.usage_page) << 16 | data
+        }
+    }
+
+    fn main(&mut self, ty: Type, flags: u16) -> Result<Option<Pending>, ()> {
+        let global = self.global;
+        let local = self.local;
+        self.local = Local::new();
+
+        let size = global.report_size;
+        let count = global.report_count;
+        if size > 32 || count > u32::from(u16::MAX) {
+            return Err(());
+        }
+
+        let offset = self.offset(global.report_id, ty)?;
+        let start = *offset;
+        let total = u32::from(start) + size * count;
+        if total > u32::from(u16::MAX) {
+            return Err(());
+        }
+        *offset = total as u16;
+
+        if size == 0 || count == 0 {
+            return Ok(None);
+        }
+
+        let mut logical_maximum = global.logical_maximum;
+        let mut usages = Usages::empty();
+        let (usage_minimum, usage_maximum) = match (local.usage_minimum, local.usage_maximum) {
+            (Some(min), Some(max)) => (min, max),
+            _ => {
+                if flags & VARIABLE == 0 {
+                    usages = Usages::new(&local.usages[..local.nusages])?;
+                }
+
+                let first = if local.nusages == 0 {
+                    0
+                } else {
+                    local.usages[0]
+                };
+                (
+                    first,
+                    local.usages[..local.nusages]
+                        .iter()
+                        .cloned()
+                        .max()
+                        .unwrap_or(0),
+                )
+            }
+        };
+
+        // a negative Logical Maximum with a non-negative Logical Minimum is a unsigned value that
+        // was encoded in too few bytes
+        if global.logical_minimum >= 0 && logical_maximum < 0 {
+            logical_maximum = global.logical_maximum_unsigned as i32;
+        }
+
+        Ok(Some(Pending {
+            field: Field {
+                report_id: NonZeroU8::new(global.report_id),
+                ty,
+                flags,
+                offset: start,
+                size: size as u8,
+                logical_minimum: global.logical_minimum,
+                logical_maximum,
+                usage_minimum,
+                usage_maximum,
+                usages,
+            },
+            usages: local.usages,
+            nusages: local.nusages,
+            count,
+            index: 0,
+        }))
+    }
+
+    fn next_item(&mut self) -> Result<Option<Pending>, ()> {
+        let prefix = *self.bytes.first().ok_or(())?;
+
+        if prefix == LONG_ITEM {
+            let size = usize::from(*self.bytes.get(1).ok_or(())?);
+            self.bytes = self.bytes.get(3 + size..).ok_or(())?;
+            return Ok(None);
+        }
+
+        let size = match prefix & 0b11 {
+            3 => 4,
+            n => usize::from(n),
+        };
+        let ty = (prefix >> 2) & 0b11;
+        let tag = prefix >> 4;
+
+        let data = self.bytes.get(1..1 + size).ok_or(())?;
+        self.bytes = &self.bytes[1 + size..];
+
+        let unsigned = data
+            .iter()
+            .rev()
+            .fold(0u32, |acc, byte| acc << 8 | u32::from(*byte));
+        let signed = if size == 0 || size == 4 {
+            unsigned as i32
+        } else {
+            let shift = 32 - 8 * size as u32;
+            ((unsigned << shift) as i32) >> shift
+        };
+
+        match (ty, tag) {
+            (MAIN, INPUT) => self.main(Type::Input, unsigned as u16),
+            (MAIN, OUTPUT) => self.main(Type::Output, unsigned as u16),
+            (MAIN, FEATURE) => self.main(Type::Feature, unsigned as u16),
+            (MAIN, COLLECTION) | (MAIN, END_COLLECTION) => {
+                self.local = Local::new();
+                Ok(None)
+            }
+
+            (GLOBAL, USAGE_PAGE) => {
+                self.global.usage_page = unsigned as u16;
+                Ok(None)
+            }
+            (GLOBAL, LOGICAL_MINIMUM) => {
+                self.global.logical_minimum = signed;
+                Ok(None)
+            }
+            (GLOBAL, LOGICAL_MAXIMUM) => {
+                self.global.logical_maximum = signed;
+                self.global.logical_maximum_unsigned = unsigned;
+                Ok(None)
+            }
+            (GLOBAL, REPORT_SIZE) => {
+                self.global.report_size = unsigned;
+                Ok(None)
+            }
+            (GLOBAL, REPORT_ID) => {
+                if unsigned == 0 || unsigned > 0xFF {
+                    return Err(());
+                }
+
+                self.global.report_id = unsigned as u8;
+                Ok(None)
+            }
+            (GLOBAL, REPORT_COUNT) => {
+                self.global.report_count = unsigned;
+                Ok(None)
+            }
+            (GLOBAL, PUSH) => {
+                if self.depth == STACK_SIZE {
+                    return Err(());
+                }
+
+                self.stack[self.depth] = self.global;
+                self.depth += 1;
+                Ok(None)
+            }
+            (GLOBAL, POP) => {
+                if self.depth == 0 {
+                    return Err(());
+                }
+
+                self.depth -= 1;
+                self.global = self.stack[self.depth];
+                Ok(None)
+            }
+
+            (LOCAL, USAGE) => {
+                if self.local.nusages == MAX_USAGES {
+                    return Err(());
+                }
+
+                self.local.usages[self.local.nusages] = self.usage(unsigned, size);
+                self.local.nusages += 1;
+                Ok(None)
+            }
+            (LOCAL, USAGE_MINIMUM) => {
+                self.local.usage_minimum = Some(self.usage(unsigned, size));
+                Ok(None)
+            }
+            (LOCAL, USAGE_MAXIMUM) => {
+                self.local.usage_maximum = Some(self.usage(unsigned, size));
+                Ok(None)
+            }
+
+            // other items don't affect the report layout
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<Field, ()>;
+
+    fn next(&mut self) -> Option<Result<Field, ()>> {
+        loop {
+            if let Some(pending) = self.pending.as_mut() {
+                let index = pending.index;
+                let mut field = pending.field;
+                field.offset += (index * u32::from(field.size)) as u16;
+
+                if !field.is_array() {
+                    let usage = if (index as usize) < pending.nusages {
+                        pending.usages[index as usize]
+                    } else if pending.nusages == 0 && field.usage_maximum > field.usage_minimum {
+                        // usage range; the last usage is reused once the range is exhausted
+                        (field.usage_minimum + index).min(field.usage_maximum)
+                    } else if pending.nusages != 0 {
+                        pending.usages[pending.nusages - 1]
+                    } else {
+                        field.usage_minimum
+                    };
+                    field.usage_minimum = usage;
+                    field.usage_maximum = usage;
+                }
+
+                pending.index += 1;
+                if pending.index == pending.count {
+                    self.pending = None;
+                }
+
+                return Some(Ok(field));
+            }
+
+            if self.done || self.bytes.is_empty() {
+                return None;
+            }
+
+            match self.next_item() {
+                Ok(pending) => self.pending = pending,
+                Err(()) => {
+                    self.done = true;
+                    return Some(Err(()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU8;
+
+    use super::{Field, Fields, Type};
+
+    // 3 buttons + 5 bits of padding, and two relative axes (X, Y) in a report with ID 2
+    const MOUSE: &[u8] = &[
+        0x05, 0x01, // USAGE_PAGE (Generic Desktop)
+        0x09, 0x02, // USAGE (Mouse)
+        0xa1, 0x01, // COLLECTION (Application)
+        0x85, 0x02, //   REPORT_ID (2)
+        0x05, 0x09, //   USAGE_PAGE (Button)
+        0x19, 0x01, //   USAGE_MINIMUM (Button 1)
+        0x29, 0x03, //   USAGE_MAXIMUM (Button 3)
+        0x15, 0x00, //   LOGICAL_MINIMUM (0)
+        0x25, 0x01, //   LOGICAL_MAXIMUM (1)
+        0x95, 0x03, //   REPORT_COUNT (3)
+        0x75, 0x01, //   REPORT_SIZE (1)
+        0x81, 0x02, //   INPUT (Data,Var,Abs)
+        0x95, 0x01, //   REPORT_COUNT (1)
+        0x75, 0x05, //   REPORT_SIZE (5)
+        0x81, 0x03, //   INPUT (Cnst,Var,Abs)
+        0x05, 0x01, //   USAGE_PAGE (Generic Desktop)
+        0x09, 0x30, //   USAGE (X)
+        0x09, 0x31, //   USAGE (Y)
+        0x15, 0x81, //   LOGICAL_MINIMUM (-127)
+        0x25, 0x7f, //   LOGICAL_MAXIMUM (127)
+        0x75, 0x08, //   REPORT_SIZE (8)
+        0x95, 0x02, //   REPORT_COUNT (2)
+        0x81, 0x06, //   INPUT (Data,Var,Rel)
+        0xc0, // END_COLLECTION
+    ];
+
+    fn field(n: usize) -> Field {
+        Fields::new(MOUSE).nth(n).unwrap().unwrap()
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(Fields::new(MOUSE).count(), 6);
+
+        let button2 = field(1);
+        assert_eq!(button2.report_id, NonZeroU8::new(2));
+        assert_eq!(button2.offset, 1);
+        assert_eq!(button2.size, 1);
+        assert_eq!(button2.usage_minimum, 0x0009_0002);
+
+        let y = field(5);
+        assert_eq!(y.offset, 16);
+        assert_eq!(y.usage_minimum, 0x0001_0031);
+        assert!(y.is_relative());
+
+        assert_eq!(super::length(MOUSE, Type::Input, NonZeroU8::new(2)), Ok(4));
+    }
+
+    #[test]
+    fn extract() {
+        let report = [2, 0b101, 0x05, 0xfe];
+
+        assert_eq!(super::extract(&report, &field(0)), Ok(1));
+        assert_eq!(super::extract(&report, &field(1)), Ok(0));
+        assert_eq!(super::extract(&report, &field(4)), Ok(5));
+        // sign extended
+        assert_eq!(super::extract(&report, &field(5)), Ok(-2));
+
+        // wrong report ID
+        assert!(super::extract(&[1, 0, 0, 0], &field(0)).is_err());
+    }
+
+    #[test]
+    fn pack() {
+        let mut report = [0; 4];
+
+        super::pack(&mut report, &field(2), 1).unwrap();
+        super::pack(&mut report, &field(5), -127).unwrap();
+        assert_eq!(report, [2, 0b100, 0, 0x81]);
+
+        // doesn't fit
+        assert!(super::pack(&mut report, &field(0), 2).is_err());
+        assert!(super::pack(&mut report, &field(4), -129).is_err());
+    }
+
+    #[test]
+    fn array() {
+        const KEYS: &[u8] = &[
+            0x05, 0x07, // USAGE_PAGE (Keyboard)
+            0x19, 0x00, // USAGE_MINIMUM (0)
+            0x29, 0x65, // USAGE_MAXIMUM (101)
+            0x15, 0x00, // LOGICAL_MINIMUM (0)
+            0x25, 0x65, // LOGICAL_MAXIMUM (101)
+            0x75, 0x08, // REPORT_SIZE (8)
+            0x95, 0x06, // REPORT_COUNT (6)
+            0x81, 0x00, // INPUT (Data,Ary,Abs)
+        ];
+
+        let key = Fields::new(KEYS).nth(1).unwrap().unwrap();
+        assert!(key.is_array());
+        assert_eq!(key.offset, 8);
+        assert_eq!(key.usage(4), Some(0x0007_0004));
+        assert_eq!(key.usage(0xff), None);
+    }
+
+    #[test]
+    fn usage_list() {
+        const SELECTOR: &[u8] = &[
+            0x05, 0x0c, // USAGE_PAGE (Consumer)
+            0x09, 0xe9, // USAGE (Volume Increment)
+            0x09, 0xea, // USAGE (Volume Decrement)
+            0x09, 0xe2, // USAGE (Mute)
+            0x15, 0x01, // LOGICAL_MINIMUM (1)
+            0x25, 0x03, // LOGICAL_MAXIMUM (3)
+            0x75, 0x02, // REPORT_SIZE (2)
+            0x95, 0x01, // REPORT_COUNT (1)
+            0x81, 0x00, // INPUT (Data,Ary,Abs)
+        ];
+
+        let field = Fields::new(SELECTOR).next().unwrap().unwrap();
+        assert!(field.is_array());
+        assert_eq!(field.usage(0), None);
+        assert_eq!(field.usage(1), Some(0x000c_00e9));
+        assert_eq!(field.usage(2), Some(0x000c_00ea));
+        assert_eq!(field.usage(3), Some(0x000c_00e2));
+        assert_eq!(field.usage(4), None);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut wide = field(0);
+        wide.logical_minimum = i32::MIN;
+        wide.logical_maximum = i32::MAX;
+        wide.flags = 0;
+        wide.usage_minimum = 0;
+        wide.usage_maximum = u32::MAX;
+        // no overflow
+        assert_eq!(wide.usage(i32::MAX), Some(u32::MAX));
+
+        let mut empty = field(0);
+        empty.size = 0;
+        assert!(super::extract(&[2, 0, 0, 0], &empty).is_err());
+        assert!(super::pack(&mut [0; 4], &empty, 0).is_err());
+    }
+}
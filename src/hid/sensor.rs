@@ -0,0 +1,276 @@
+//! HID sensors: temperature, humidity and 3D accelerometer
+//!
+//! Every sensor uses a single report ID for both its input report (the reading) and its feature
+//! report (report interval and change sensitivity)
+
+use core::num::NonZeroU8;
+
+use super::{
+    report::{self, Collection, Writer, VARIABLE},
+    usage::{self, Page},
+};
+
+/// Sensor type
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Type {
+    /// Temperature in hundredths of degree Celsius
+    Temperature,
+    /// Relative humidity in hundredths of percent
+    Humidity,
+    /// Acceleration along the X, Y and Z axes in thousandths of G
+    Accelerometer3D,
+}
+
+impl Type {
+    fn usage(&self) -> usage::Sensor {
+        match self {
+            Type::Temperature => usage::Sensor::EnvironmentalTemperature,
+            Type::Humidity => usage::Sensor::EnvironmentalHumidity,
+            Type::Accelerometer3D => usage::Sensor::MotionAccelerometer3D,
+        }
+    }
+
+    fn data_fields(&self) -> &'static [usage::Sensor] {
+        match self {
+            Type::Temperature => &[usage::Sensor::Temperature],
+            Type::Humidity => &[usage::Sensor::RelativeHumidity],
+            Type::Accelerometer3D => &[
+                usage::Sensor::AccelerationAxisX,
+                usage::Sensor::AccelerationAxisY,
+                usage::Sensor::AccelerationAxisZ,
+            ],
+        }
+    }
+
+    fn unit_exponent(&self) -> i8 {
+        match self {
+            Type::Temperature | Type::Humidity => -2,
+            Type::Accelerometer3D => -3,
+        }
+    }
+}
+
+/// Sensor description
+#[derive(Clone, Copy)]
+pub struct Sensor {
+    /// Sensor type
+    pub ty: Type,
+    /// ID of the input and feature reports
+    pub report_id: NonZeroU8,
+}
+
+impl Sensor {
+    /// Size, in bytes, of the input report including the report ID
+    pub fn report_size(&self) -> usize {
+        1 + 2 * self.ty.data_fields().len()
+    }
+
+    /// Writes the report descriptor into `buf`
+    ///
+    /// Returns the size of the report descriptor, or an error if `buf` is too small
+    pub fn report_descriptor(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let mut w = Writer::new(buf);
+        w.usage_page(Page::Sensor)
+            .usage(self.ty.usage() as u16)
+            .collection(Collection::Application)
+            .report_id(self.report_id)
+            // feature report
+            .usage(usage::Sensor::ReportInterval as u16)
+            .logical_minimum(0)
+            .logical_maximum(i32::MAX)
+            .report_size(32)
+            .report_count(1)
+            .unit_exponent(0)
+            .feature(VARIABLE)
+            .usage(usage::Sensor::ChangeSensitivityAbsolute as u16)
+            .logical_maximum(i32::from(u16::MAX))
+            .report_size(16)
+            .unit_exponent(self.ty.unit_exponent())
+            .feature(VARIABLE)
+            // input report
+            .logical_minimum(-i32::from(i16::MAX))
+            .logical_maximum(i32::from(i16::MAX));
+
+        for field in self.ty.data_fields() {
+            w.usage(*field as u16);
+        }
+
+        w.report_count(self.ty.data_fields().len() as u16)
+            .input(VARIABLE)
+            .end_collection()
+            .finish()
+    }
+}
+
+/// Sensor reading (input report)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Reading {
+    /// Values of the data fields; temperature and humidity only use the first element
+    pub values: [i16; 3],
+}
+
+impl Reading {
+    /// Writes the wire representation of this report into `buf`
+    ///
+    /// Returns the size of the report or an error if `buf` is too small or a value is `i16::MIN`
+    pub fn bytes(&self, sensor: &Sensor, buf: &mut [u8]) -> Result<usize, ()> {
+        let size = sensor.report_size();
+        let buf = buf.get_mut(..size).ok_or(())?;
+
+        buf[0] = sensor.report_id.get();
+        for (i, value) in self.values[..sensor.ty.data_fields().len()]
+            .iter()
+            .enumerate()
+        {
+            // outside the logical range
+            if *value == i16::MIN {
+                return Err(());
+            }
+
+            report::write_bits(&mut buf[1..], 16 * i as u16, 16, *value as u32)?;
+        }
+
+        Ok(size)
+    }
+}
+
+/// Sensor properties (feature report)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Properties {
+    /// Report interval in milliseconds
+    pub report_interval: u32,
+    /// Minimum change of the reading, in the units of the reading, that triggers a report
+    pub sensitivity: u16,
+}
+
+impl Properties {
+    /// The size of this report on the wire, including the report ID
+    pub const SIZE: u8 = 7;
+
+    /// Returns the wire representation of this report
+    pub fn bytes(&self, sensor: &Sensor) -> [u8; Self::SIZE as usize] {
+        let interval = self.report_interval.to_le_bytes();
+        let sensitivity = self.sensitivity.to_le_bytes();
+
+        [
+            sensor.report_id.get(),
+            interval[0],
+            interval[1],
+            interval[2],
+            interval[3],
+            sensitivity[0],
+            sensitivity[1],
+        ]
+    }
+
+    /// Parses the feature report sent by the host using SET_REPORT
+    pub fn parse(sensor: &Sensor, bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) || bytes[0] != sensor.report_id.get() {
+            return Err(());
+        }
+
+        let report_interval = report::read_bits(&bytes[1..], 0, 32)?;
+        if report_interval > i32::MAX as u32 {
+            return Err(());
+        }
+
+        Ok(Properties {
+            report_interval,
+            sensitivity: report::read_bits(&bytes[1..], 32, 16)? as u16,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::num::NonZeroU8;
+
+    use crate::hid::report::{self, Fields, Type as ReportType};
+
+    use super::{Properties, Reading, Sensor, Type};
+
+    #[test]
+    fn descriptors() {
+        for ty in &[Type::Temperature, Type::Humidity, Type::Accelerometer3D] {
+            let sensor = Sensor {
+                ty: *ty,
+                report_id: NonZeroU8::new(4).unwrap(),
+            };
+
+            let mut desc = [0; 128];
+            let n = sensor.report_descriptor(&mut desc).unwrap();
+            let desc = &desc[..n];
+
+            assert_eq!(
+                report::length(desc, ReportType::Input, Some(sensor.report_id)),
+                Ok(sensor.report_size())
+            );
+            assert_eq!(
+                report::length(desc, ReportType::Feature, Some(sensor.report_id)),
+                Ok(usize::from(Properties::SIZE))
+            );
+        }
+    }
+
+    #[test]
+    fn accelerometer() {
+        let sensor = Sensor {
+            ty: Type::Accelerometer3D,
+            report_id: NonZeroU8::new(1).unwrap(),
+        };
+
+        let mut desc = [0; 128];
+        let n = sensor.report_descriptor(&mut desc).unwrap();
+        let desc = &desc[..n];
+        assert_eq!(
+            desc,
+            &[
+                0x05, 0x20, // USAGE_PAGE (Sensor)
+                0x09, 0x73, // USAGE (Motion: Accelerometer 3D)
+                0xa1, 0x01, // COLLECTION (Application)
+                0x85, 0x01, //   REPORT_ID (1)
+                0x0a, 0x0e, 0x03, //   USAGE (Report Interval)
+                0x15, 0x00, //   LOGICAL_MINIMUM (0)
+                0x27, 0xff, 0xff, 0xff, 0x7f, //   LOGICAL_MAXIMUM (2147483647)
+                0x75, 0x20, //   REPORT_SIZE (32)
+                0x95, 0x01, //   REPORT_COUNT (1)
+                0x55, 0x00, //   UNIT_EXPONENT (0)
+                0xb1, 0x02, //   FEATURE (Data,Var,Abs)
+                0x0a, 0x0f, 0x03, //   USAGE (Change Sensitivity Absolute)
+                0x27, 0xff, 0xff, 0x00, 0x00, //   LOGICAL_MAXIMUM (65535)
+                0x75, 0x10, //   REPORT_SIZE (16)
+                0x55, 0x0d, //   UNIT_EXPONENT (-3)
+                0xb1, 0x02, //   FEATURE (Data,Var,Abs)
+                0x16, 0x01, 0x80, //   LOGICAL_MINIMUM (-32767)
+                0x26, 0xff, 0x7f, //   LOGICAL_MAXIMUM (32767)
+                0x0a, 0x53, 0x04, //   USAGE (Acceleration Axis X)
+                0x0a, 0x54, 0x04, //   USAGE (Acceleration Axis Y)
+                0x0a, 0x55, 0x04, //   USAGE (Acceleration Axis Z)
+                0x95, 0x03, //   REPORT_COUNT (3)
+                0x81, 0x02, //   INPUT (Data,Var,Abs)
+                0xc0, // END_COLLECTION
+            ][..]
+        );
+
+        let reading = Reading {
+            values: [-1000, 0, 981],
+        };
+        let mut buf = [0; 8];
+        let n = reading.bytes(&sensor, &mut buf).unwrap();
+
+        let values = Fields::new(desc)
+            .map(|field| field.unwrap())
+            .filter(|field| field.ty == ReportType::Input)
+            .map(|field| report::extract(&buf[..n], &field).unwrap());
+        assert!(values.eq([-1000, 0, 981].iter().cloned()));
+
+        let properties = Properties {
+            report_interval: 100,
+            sensitivity: 50,
+        };
+        assert_eq!(
+            Properties::parse(&sensor, &properties.bytes(&sensor)),
+            Ok(properties)
+        );
+    }
+}
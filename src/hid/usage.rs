@@ -0,0 +1,654 @@
+//! Usage pages and usages
+//!
+//! See the HID Usage Tables 1.12 document
+
+repr!(u16,
+      /// Usage pages
+      Page {
+    /// Generic Desktop Controls
+    GenericDesktop = 0x01,
+    /// Keyboard/Keypad
+    Keyboard = 0x07,
+    /// LEDs
+    Led = 0x08,
+    /// Button
+    Button = 0x09,
+    /// Consumer
+    Consumer = 0x0C,
+    /// Digitizers
+    Digitizer = 0x0D,
+    /// Sensors
+    Sensor = 0x20,
+});
+
+/// A usage: a (usage page, usage ID) pair
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Usage {
+    /// Generic Desktop usage
+    GenericDesktop(GenericDesktop),
+    /// Keyboard/Keypad usage
+    Keyboard(Keyboard),
+    /// LED usage
+    Led(Led),
+    /// Button number; `0` means "no button pressed"
+    Button(u16),
+    /// Consumer usage
+    Consumer(Consumer),
+    /// Digitizer usage
+    Digitizer(Digitizer),
+    /// Sensor usage
+    Sensor(Sensor),
+}
+
+impl Usage {
+    /// Returns the usage page of this usage
+    pub fn page(&self) -> Page {
+        match self {
+            Usage::GenericDesktop(..) => Page::GenericDesktop,
+            Usage::Keyboard(..) => Page::Keyboard,
+            Usage::Led(..) => Page::Led,
+            Usage::Button(..) => Page::Button,
+            Usage::Consumer(..) => Page::Consumer,
+            Usage::Digitizer(..) => Page::Digitizer,
+            Usage::Sensor(..) => Page::Sensor,
+        }
+    }
+
+    /// Returns the usage ID of this usage
+    pub fn id(&self) -> u16 {
+        match self {
+            Usage::GenericDesktop(usage) => *usage as u16,
+            Usage::Keyboard(usage) => *usage as u16,
+            Usage::Led(usage) => *usage as u16,
+            Usage::Button(number) => *number,
+            Usage::Consumer(usage) => *usage as u16,
+            Usage::Digitizer(usage) => *usage as u16,
+            Usage::Sensor(usage) => *usage as u16,
+        }
+    }
+
+    /// Returns the (usage page, usage ID) pair
+    pub fn pair(&self) -> (u16, u16) {
+        (self.page() as u16, self.id())
+    }
+
+    /// Returns the extended (32-bit) usage; the usage page is in the high half-word
+    ///
+    /// This is the format used by `report::Field`
+    pub fn extended(&self) -> u32 {
+        u32::from(self.page() as u16) << 16 | u32::from(self.id())
+    }
+
+    /// Converts a (usage page, usage ID) pair into a usage
+    ///
+    /// Returns an error if the page or the usage ID is unknown
+    pub fn from_pair(page: u16, id: u16) -> Result<Self, ()> {
+        Ok(match Page::_from(page).ok_or(())? {
+            Page::GenericDesktop => Usage::GenericDesktop(GenericDesktop::_from(id).ok_or(())?),
+            Page::Keyboard => Usage::Keyboard(Keyboard::_from(id).ok_or(())?),
+            Page::Led => Usage::Led(Led::_from(id).ok_or(())?),
+            Page::Button => Usage::Button(id),
+            Page::Consumer => Usage::Consumer(Consumer::_from(id).ok_or(())?),
+            Page::Digitizer => Usage::Digitizer(Digitizer::_from(id).ok_or(())?),
+            Page::Sensor => Usage::Sensor(Sensor::_from(id).ok_or(())?),
+        })
+    }
+
+    /// Converts an extended (32-bit) usage into a usage
+    pub fn from_extended(usage: u32) -> Result<Self, ()> {
+        Self::from_pair((usage >> 16) as u16, usage as u16)
+    }
+}
+
+repr!(u16,
+      /// Generic Desktop page usages
+      GenericDesktop {
+    /// Pointer
+    Pointer = 0x01,
+    /// Mouse
+    Mouse = 0x02,
+    /// Joystick
+    Joystick = 0x04,
+    /// Game Pad
+    GamePad = 0x05,
+    /// Keyboard
+    Keyboard = 0x06,
+    /// Keypad
+    Keypad = 0x07,
+    /// Multi-axis Controller
+    MultiAxisController = 0x08,
+    /// X
+    X = 0x30,
+    /// Y
+    Y = 0x31,
+    /// Z
+    Z = 0x32,
+    /// Rx
+    Rx = 0x33,
+    /// Ry
+    Ry = 0x34,
+    /// Rz
+    Rz = 0x35,
+    /// Slider
+    Slider = 0x36,
+    /// Dial
+    Dial = 0x37,
+    /// Wheel
+    Wheel = 0x38,
+    /// Hat switch
+    HatSwitch = 0x39,
+    /// System Control
+    SystemControl = 0x80,
+    /// System Power Down
+    SystemPowerDown = 0x81,
+    /// System Sleep
+    SystemSleep = 0x82,
+    /// System Wake Up
+    SystemWakeUp = 0x83,
+    /// D-pad Up
+    DpadUp = 0x90,
+    /// D-pad Down
+    DpadDown = 0x91,
+    /// D-pad Right
+    DpadRight = 0x92,
+    /// D-pad Left
+    DpadLeft = 0x93,
+});
+
+repr!(u16,
+      /// Keyboard/Keypad page usages
+      Keyboard {
+    /// Reserved (no event indicated)
+    NoEvent = 0x00,
+    /// ErrorRollOver
+    ErrorRollOver = 0x01,
+    /// POSTFail
+    PostFail = 0x02,
+    /// ErrorUndefined
+    ErrorUndefined = 0x03,
+    /// Keyboard a and A
+    A = 0x04,
+    /// Keyboard b and B
+    B = 0x05,
+    /// Keyboard c and C
+    C = 0x06,
+    /// Keyboard d and D
+    D = 0x07,
+    /// Keyboard e and E
+    E = 0x08,
+    /// Keyboard f and F
+    F = 0x09,
+    /// Keyboard g and G
+    G = 0x0A,
+    /// Keyboard h and H
+    H = 0x0B,
+    /// Keyboard i and I
+    I = 0x0C,
+    /// Keyboard j and J
+    J = 0x0D,
+    /// Keyboard k and K
+    K = 0x0E,
+    /// Keyboard l and L
+    L = 0x0F,
+    /// Keyboard m and M
+    M = 0x10,
+    /// Keyboard n and N
+    N = 0x11,
+    /// Keyboard o and O
+    O = 0x12,
+    /// Keyboard p and P
+    P = 0x13,
+    /// Keyboard q and Q
+    Q = 0x14,
+    /// Keyboard r and R
+    R = 0x15,
+    /// Keyboard s and S
+    S = 0x16,
+    /// Keyboard t and T
+    T = 0x17,
+    /// Keyboard u and U
+    U = 0x18,
+    /// Keyboard v and V
+    V = 0x19,
+    /// Keyboard w and W
+    W = 0x1A,
+    /// Keyboard x and X
+    X = 0x1B,
+    /// Keyboard y and Y
+    Y = 0x1C,
+    /// Keyboard z and Z
+    Z = 0x1D,
+    /// Keyboard 1 and !
+    _1 = 0x1E,
+    /// Keyboard 2 and @
+    _2 = 0x1F,
+    /// Keyboard 3 and #
+    _3 = 0x20,
+    /// Keyboard 4 and $
+    _4 = 0x21,
+    /// Keyboard 5 and %
+    _5 = 0x22,
+    /// Keyboard 6 and ^
+    _6 = 0x23,
+    /// Keyboard 7 and &
+    _7 = 0x24,
+    /// Keyboard 8 and *
+    _8 = 0x25,
+    /// Keyboard 9 and (
+    _9 = 0x26,
+    /// Keyboard 0 and )
+    _0 = 0x27,
+    /// Keyboard Return (ENTER)
+    Enter = 0x28,
+    /// Keyboard ESCAPE
+    Escape = 0x29,
+    /// Keyboard DELETE (Backspace)
+    Backspace = 0x2A,
+    /// Keyboard Tab
+    Tab = 0x2B,
+    /// Keyboard Spacebar
+    Space = 0x2C,
+    /// Keyboard - and (underscore)
+    Minus = 0x2D,
+    /// Keyboard = and +
+    Equal = 0x2E,
+    /// Keyboard [ and {
+    LeftBracket = 0x2F,
+    /// Keyboard ] and }
+    RightBracket = 0x30,
+    /// Keyboard \ and |
+    Backslash = 0x31,
+    /// Keyboard Non-US # and ~
+    NonUsHash = 0x32,
+    /// Keyboard ; and :
+    Semicolon = 0x33,
+    /// Keyboard ' and "
+    Apostrophe = 0x34,
+    /// Keyboard Grave Accent and Tilde
+    Grave = 0x35,
+    /// Keyboard , and <
+    Comma = 0x36,
+    /// Keyboard . and >
+    Dot = 0x37,
+    /// Keyboard / and ?
+    Slash = 0x38,
+    /// Keyboard Caps Lock
+    CapsLock = 0x39,
+    /// Keyboard F1
+    F1 = 0x3A,
+    /// Keyboard F2
+    F2 = 0x3B,
+    /// Keyboard F3
+    F3 = 0x3C,
+    /// Keyboard F4
+    F4 = 0x3D,
+    /// Keyboard F5
+    F5 = 0x3E,
+    /// Keyboard F6
+    F6 = 0x3F,
+    /// Keyboard F7
+    F7 = 0x40,
+    /// Keyboard F8
+    F8 = 0x41,
+    /// Keyboard F9
+    F9 = 0x42,
+    /// Keyboard F10
+    F10 = 0x43,
+    /// Keyboard F11
+    F11 = 0x44,
+    /// Keyboard F12
+    F12 = 0x45,
+    /// Keyboard PrintScreen
+    PrintScreen = 0x46,
+    /// Keyboard Scroll Lock
+    ScrollLock = 0x47,
+    /// Keyboard Pause
+    Pause = 0x48,
+    /// Keyboard Insert
+    Insert = 0x49,
+    /// Keyboard Home
+    Home = 0x4A,
+    /// Keyboard PageUp
+    PageUp = 0x4B,
+    /// Keyboard Delete Forward
+    Delete = 0x4C,
+    /// Keyboard End
+    End = 0x4D,
+    /// Keyboard PageDown
+    PageDown = 0x4E,
+    /// Keyboard RightArrow
+    RightArrow = 0x4F,
+    /// Keyboard LeftArrow
+    LeftArrow = 0x50,
+    /// Keyboard DownArrow
+    DownArrow = 0x51,
+    /// Keyboard UpArrow
+    UpArrow = 0x52,
+    /// Keypad Num Lock and Clear
+    NumLock = 0x53,
+    /// Keypad /
+    KeypadSlash = 0x54,
+    /// Keypad *
+    KeypadAsterisk = 0x55,
+    /// Keypad -
+    KeypadMinus = 0x56,
+    /// Keypad +
+    KeypadPlus = 0x57,
+    /// Keypad ENTER
+    KeypadEnter = 0x58,
+    /// Keypad 1 and End
+    Keypad1 = 0x59,
+    /// Keypad 2 and Down Arrow
+    Keypad2 = 0x5A,
+    /// Keypad 3 and PageDn
+    Keypad3 = 0x5B,
+    /// Keypad 4 and Left Arrow
+    Keypad4 = 0x5C,
+    /// Keypad 5
+    Keypad5 = 0x5D,
+    /// Keypad 6 and Right Arrow
+    Keypad6 = 0x5E,
+    /// Keypad 7 and Home
+    Keypad7 = 0x5F,
+    /// Keypad 8 and Up Arrow
+    Keypad8 = 0x60,
+    /// Keypad 9 and PageUp
+    Keypad9 = 0x61,
+    /// Keypad 0 and Insert
+    Keypad0 = 0x62,
+    /// Keypad . and Delete
+    KeypadDot = 0x63,
+    /// Keyboard Non-US \ and |
+    NonUsBackslash = 0x64,
+    /// Keyboard Application
+    Application = 0x65,
+    /// Keyboard Power
+    Power = 0x66,
+    /// Keypad =
+    KeypadEqual = 0x67,
+    /// Keyboard F13
+    F13 = 0x68,
+    /// Keyboard F14
+    F14 = 0x69,
+    /// Keyboard F15
+    F15 = 0x6A,
+    /// Keyboard F16
+    F16 = 0x6B,
+    /// Keyboard F17
+    F17 = 0x6C,
+    /// Keyboard F18
+    F18 = 0x6D,
+    /// Keyboard F19
+    F19 = 0x6E,
+    /// Keyboard F20
+    F20 = 0x6F,
+    /// Keyboard F21
+    F21 = 0x70,
+    /// Keyboard F22
+    F22 = 0x71,
+    /// Keyboard F23
+    F23 = 0x72,
+    /// Keyboard F24
+    F24 = 0x73,
+    /// Keyboard Mute
+    Mute = 0x7F,
+    /// Keyboard Volume Up
+    VolumeUp = 0x80,
+    /// Keyboard Volume Down
+    VolumeDown = 0x81,
+    /// Keyboard LeftControl
+    LeftControl = 0xE0,
+    /// Keyboard LeftShift
+    LeftShift = 0xE1,
+    /// Keyboard LeftAlt
+    LeftAlt = 0xE2,
+    /// Keyboard Left GUI
+    LeftGui = 0xE3,
+    /// Keyboard RightControl
+    RightControl = 0xE4,
+    /// Keyboard RightShift
+    RightShift = 0xE5,
+    /// Keyboard RightAlt
+    RightAlt = 0xE6,
+    /// Keyboard Right GUI
+    RightGui = 0xE7,
+});
+
+repr!(u16,
+      /// LED page usages
+      Led {
+    /// Num Lock
+    NumLock = 0x01,
+    /// Caps Lock
+    CapsLock = 0x02,
+    /// Scroll Lock
+    ScrollLock = 0x03,
+    /// Compose
+    Compose = 0x04,
+    /// Kana
+    Kana = 0x05,
+    /// Power
+    Power = 0x06,
+    /// Shift
+    Shift = 0x07,
+    /// Do Not Disturb
+    DoNotDisturb = 0x08,
+    /// Mute
+    Mute = 0x09,
+});
+
+repr!(u16,
+      /// Consumer page usages
+      Consumer {
+    /// Unassigned
+    Unassigned = 0x00,
+    /// Consumer Control
+    ConsumerControl = 0x01,
+    /// Power
+    Power = 0x30,
+    /// Sleep
+    Sleep = 0x32,
+    /// Menu
+    Menu = 0x40,
+    /// Display Brightness Increment
+    BrightnessIncrement = 0x6F,
+    /// Display Brightness Decrement
+    BrightnessDecrement = 0x70,
+    /// Play
+    Play = 0xB0,
+    /// Pause
+    Pause = 0xB1,
+    /// Record
+    Record = 0xB2,
+    /// Fast Forward
+    FastForward = 0xB3,
+    /// Rewind
+    Rewind = 0xB4,
+    /// Scan Next Track
+    ScanNextTrack = 0xB5,
+    /// Scan Previous Track
+    ScanPreviousTrack = 0xB6,
+    /// Stop
+    Stop = 0xB7,
+    /// Eject
+    Eject = 0xB8,
+    /// Play/Pause
+    PlayPause = 0xCD,
+    /// Volume
+    Volume = 0xE0,
+    /// Mute
+    Mute = 0xE2,
+    /// Bass Boost
+    BassBoost = 0xE5,
+    /// Volume Increment
+    VolumeIncrement = 0xE9,
+    /// Volume Decrement
+    VolumeDecrement = 0xEA,
+    /// AL Email Reader
+    AlEmailReader = 0x18A,
+    /// AL Calculator
+    AlCalculator = 0x192,
+    /// AL Local Machine Browser
+    AlLocalBrowser = 0x194,
+    /// AC Search
+    AcSearch = 0x221,
+    /// AC Home
+    AcHome = 0x223,
+    /// AC Back
+    AcBack = 0x224,
+    /// AC Forward
+    AcForward = 0x225,
+    /// AC Stop
+    AcStop = 0x226,
+    /// AC Refresh
+    AcRefresh = 0x227,
+    /// AC Bookmarks
+    AcBookmarks = 0x22A,
+    /// AC Pan
+    AcPan = 0x238,
+});
+
+repr!(u16,
+      /// Digitizer page usages
+      Digitizer {
+    /// Digitizer
+    Digitizer = 0x01,
+    /// Pen
+    Pen = 0x02,
+    /// Touch Screen
+    TouchScreen = 0x04,
+    /// Touch Pad
+    TouchPad = 0x05,
+    /// Stylus
+    Stylus = 0x20,
+    /// Finger
+    Finger = 0x22,
+    /// Device settings
+    DeviceSettings = 0x23,
+    /// Tip Pressure
+    TipPressure = 0x30,
+    /// In Range
+    InRange = 0x32,
+    /// Touch
+    Touch = 0x33,
+    /// Untouch
+    Untouch = 0x34,
+    /// Invert
+    Invert = 0x3C,
+    /// X Tilt
+    XTilt = 0x3D,
+    /// Y Tilt
+    YTilt = 0x3E,
+    /// Tip Switch
+    TipSwitch = 0x42,
+    /// Secondary Tip Switch
+    SecondaryTipSwitch = 0x43,
+    /// Barrel Switch
+    BarrelSwitch = 0x44,
+    /// Eraser
+    Eraser = 0x45,
+    /// Confidence
+    Confidence = 0x47,
+    /// Width
+    Width = 0x48,
+    /// Height
+    Height = 0x49,
+    /// Contact Identifier
+    ContactIdentifier = 0x51,
+    /// Device Mode
+    DeviceMode = 0x52,
+    /// Device Identifier
+    DeviceIdentifier = 0x53,
+    /// Contact Count
+    ContactCount = 0x54,
+    /// Contact Count Maximum
+    ContactCountMaximum = 0x55,
+    /// Scan Time
+    ScanTime = 0x56,
+});
+
+repr!(u16,
+      /// Sensor page usages
+      Sensor {
+    /// Sensor
+    Sensor = 0x01,
+    /// Environmental
+    Environmental = 0x30,
+    /// Environmental: Atmospheric Pressure
+    EnvironmentalAtmosphericPressure = 0x31,
+    /// Environmental: Humidity
+    EnvironmentalHumidity = 0x32,
+    /// Environmental: Temperature
+    EnvironmentalTemperature = 0x33,
+    /// Motion
+    Motion = 0x70,
+    /// Motion: Accelerometer 3D
+    MotionAccelerometer3D = 0x73,
+    /// Motion: Gyrometer 3D
+    MotionGyrometer3D = 0x76,
+    /// Event: Sensor State
+    SensorState = 0x201,
+    /// Event: Sensor Event
+    SensorEvent = 0x202,
+    /// Property: Minimum Report Interval
+    MinimumReportInterval = 0x304,
+    /// Property: Report Interval
+    ReportInterval = 0x30E,
+    /// Property: Change Sensitivity Absolute
+    ChangeSensitivityAbsolute = 0x30F,
+    /// Property: Change Sensitivity Percent of Range
+    ChangeSensitivityPercentOfRange = 0x310,
+    /// Property: Change Sensitivity Percent Relative
+    ChangeSensitivityPercentRelative = 0x311,
+    /// Property: Reporting State
+    ReportingState = 0x316,
+    /// Property: Power State
+    PowerState = 0x319,
+    /// Data Field: Atmospheric Pressure
+    AtmosphericPressure = 0x431,
+    /// Data Field: Relative Humidity
+    RelativeHumidity = 0x433,
+    /// Data Field: Temperature
+    Temperature = 0x434,
+    /// Data Field: Acceleration
+    Acceleration = 0x452,
+    /// Data Field: Acceleration Axis X
+    AccelerationAxisX = 0x453,
+    /// Data Field: Acceleration Axis Y
+    AccelerationAxisY = 0x454,
+    /// Data Field: Acceleration Axis Z
+    AccelerationAxisZ = 0x455,
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{GenericDesktop, Keyboard, Page, Sensor, Usage};
+
+    #[test]
+    fn pair() {
+        let usage = Usage::Keyboard(Keyboard::A);
+        assert_eq!(usage.pair(), (0x07, 0x04));
+        assert_eq!(Usage::from_pair(0x07, 0x04), Ok(usage));
+
+        let x = Usage::GenericDesktop(GenericDesktop::X);
+        assert_eq!(x.extended(), 0x0001_0030);
+        assert_eq!(Usage::from_extended(0x0001_0030), Ok(x));
+
+        assert_eq!(Usage::from_pair(0x09, 3), Ok(Usage::Button(3)));
+        assert_eq!(Usage::Button(3).page(), Page::Button);
+
+        // unknown page
+        assert!(Usage::from_pair(0xFF00, 1).is_err());
+    }
+
+    #[test]
+    fn sensor() {
+        // values from the Sensors page (0x20) of the HID Usage Tables
+        assert_eq!(Sensor::Acceleration as u16, 0x452);
+        assert_eq!(Sensor::AccelerationAxisX as u16, 0x453);
+        assert_eq!(Sensor::AccelerationAxisY as u16, 0x454);
+        assert_eq!(Sensor::AccelerationAxisZ as u16, 0x455);
+        assert_eq!(Sensor::Temperature as u16, 0x434);
+        assert_eq!(Sensor::MotionAccelerometer3D as u16, 0x73);
+    }
+}
@@ -7,14 +7,47 @@
 //!
 //! - (USB2) Universal Serial Bus Specification Revision 2.0 (April 27, 2000)
 //! - (USBCDC1.2) Universal Serial Bus Class Definitions for Communications Devices 1.2 (Errata 1)
-//! (November 3, 2010)
+//!   (November 3, 2010)
+//! - (USBECM1.2) Universal Serial Bus Communications Class Subclass Specification for Ethernet
+//!   Control Model Devices Revision 1.2 (February 9, 2007)
+//! - (USBNCM1.0) Universal Serial Bus Communications Class Subclass Specification for Network
+//!   Control Model Devices Revision 1.0 (Errata 1) (November 24, 2010)
+//! - (USBEEM1.0) Universal Serial Bus Communications Class Subclass Specification for Ethernet
+//!   Emulation Model Devices Revision 1.0 (February 2, 2005)
+//! - (MBIM1.0) Universal Serial Bus Communications Class Subclass Specification for Mobile
+//!   Broadband Interface Model Revision 1.0 (Errata 1) (May 1, 2013)
 //! - (USBIAD) Interface Association Descriptors Engineering Change Notice
 //! - (USBPTSN1.2) Universal Serial Bus Communication Class Subclass Specification for PTSN Devices
 //!   Revision 1.2 (February 9, 2007)
+//! - (USBWMC1.1) Universal Serial Bus CDC Subclass Specification for Wireless Mobile
+//!   Communications Devices Revision 1.1 (February 9, 2007)
 //! - (HID1.11) Device Class Definition for Human Interface Devices (HID) version 1.11 (6/27/01)
+//! - (USBMSC1.4) Universal Serial Bus Mass Storage Class Specification Overview Revision 1.4
+//!   (February 19, 2010)
+//! - (USBMSBO1.0) Universal Serial Bus Mass Storage Class Bulk-Only Transport Revision 1.0
+//!   (September 31, 1999)
+//! - (USBUAS1.0) Universal Serial Bus Mass Storage Class USB Attached SCSI Protocol (UASP)
+//!   Revision 1.0 (June 24, 2009)
+//! - (SPC-4) SCSI Primary Commands - 4 (T10/BSR INCITS 513)
+//! - (SBC-3) SCSI Block Commands - 3 (T10/BSR INCITS 514)
+//! - (DFU1.1) Universal Serial Bus Device Class Specification for Device Firmware Upgrade
+//!   Version 1.1 (Aug 5, 2004)
+//! - (UM0391) DfuSe File Format Specification (UM0391, STMicroelectronics)
+//! - (AN3156) USB DFU protocol used in the STM32 bootloader (AN3156, STMicroelectronics)
+//! - (USBAUDIO1.0) Universal Serial Bus Device Class Definition for Audio Devices Release 1.0
+//!   (March 18, 1998)
+//! - (USBAUDIOFMT1.0) Universal Serial Bus Device Class Definition for Audio Data Formats
+//!   Release 1.0 (March 18, 1998)
+//! - (USBAUDIOTERM1.0) Universal Serial Bus Device Class Definition for Terminal Types Release
+//!   1.0 (March 18, 1998)
+//! - (MS-RNDIS) Remote Network Driver Interface Specification (RNDIS) Protocol v20180912
 
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
+#![allow(clippy::result_unit_err)]
+// `div_ceil` and `is_multiple_of` are not available on older toolchains
+#![allow(clippy::manual_div_ceil)]
+#![allow(clippy::manual_is_multiple_of)]
 #![deny(missing_docs)]
 #![no_std]
 
@@ -22,23 +55,28 @@ use core::num::NonZeroU8;
 
 use crate::{
     bmrequesttype::{bmRequestType, Recipient},
-    cdc::acm,
+    cdc::{acm, ecm, mbim, ncm},
 };
 
 #[macro_use]
 mod macros;
 
+pub mod audio;
 mod bmrequesttype;
 mod brequest;
 pub mod cdc;
 pub mod configuration;
+mod crc;
 mod desc;
 pub mod device;
+pub mod dfu;
 pub mod endpoint;
 mod feature;
 pub mod hid;
 pub mod ia;
 pub mod interface;
+pub mod msc;
+pub mod rndis;
 
 /// The state of the USB device
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -91,12 +129,26 @@ pub enum Request {
     Standard(StandardRequest),
     /// CDC Abstract Control Model interface request
     Acm(acm::Request),
+    /// CDC Ethernet Control Model interface request
+    Ecm(ecm::Request),
+    /// CDC Network Control Model interface request
+    Ncm(ncm::Request),
+    /// CDC Mobile Broadband Interface Model interface request
+    Mbim(mbim::Request),
     /// Human Interface Device (HID) request
     Hid(hid::Request),
+    /// Mass Storage Class request
+    Msc(msc::Request),
+    /// USB Audio Class request
+    Audio(audio::Request),
 }
 
 impl Request {
     /// Parses a control endpoint request
+    ///
+    /// Class requests are matched against each class in turn and the first match wins. Firmware
+    /// that knows which class an interface implements should use that class' `Request::parse`
+    /// instead. DFU requests are not matched; use `dfu::Request::parse`
     pub fn parse(
         bmrequesttype: u8,
         brequest: u8,
@@ -120,9 +172,29 @@ impl Request {
 
             Type::Class => acm::Request::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
                 .map(Request::Acm)
+                .or_else(|_| {
+                    ecm::Request::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+                        .map(Request::Ecm)
+                })
+                .or_else(|_| {
+                    ncm::Request::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+                        .map(Request::Ncm)
+                })
+                .or_else(|_| {
+                    mbim::Request::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+                        .map(Request::Mbim)
+                })
                 .or_else(|_| {
                     hid::Request::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
                         .map(Request::Hid)
+                })
+                .or_else(|_| {
+                    msc::Request::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+                        .map(Request::Msc)
+                })
+                .or_else(|_| {
+                    audio::Request::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+                        .map(Request::Audio)
                 }),
 
             _ => Err(()),
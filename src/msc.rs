@@ -0,0 +1,177 @@
+//! Mass Storage Class (MSC)
+//!
+//! For more details see (USBMSC1.4), (USBMSBO1.0), (USBUAS1.0) and (SPC-4)
+
+use core::num::NonZeroU8;
+
+use crate::bmrequesttype::{bmRequestType, Direction, Recipient, Type};
+
+pub mod bot;
+pub mod scsi;
+pub mod uas;
+
+/// Mass Storage interface class
+#[derive(Clone, Copy)]
+pub struct Class {
+    /// Command set
+    pub subclass: SubClass,
+    /// Transport protocol
+    pub protocol: Protocol,
+}
+
+impl Class {
+    /// Class byte
+    pub fn class(&self) -> NonZeroU8 {
+        unsafe { NonZeroU8::new_unchecked(0x08) }
+    }
+
+    /// SubClass byte
+    pub fn subclass(&self) -> u8 {
+        self.subclass as u8
+    }
+
+    /// Protocol byte
+    pub fn protocol(&self) -> u8 {
+        self.protocol as u8
+    }
+}
+
+/// Mass Storage Subclass codes (command set)
+///
+/// See section 2 of (USBMSC1.4)
+#[derive(Clone, Copy)]
+pub enum SubClass {
+    /// SCSI command set not reported
+    ScsiNotReported = 0x00,
+    /// Reduced Block Commands (RBC)
+    Rbc = 0x01,
+    /// MMC-5 (ATAPI)
+    Mmc5 = 0x02,
+    /// UFI
+    Ufi = 0x04,
+    /// SCSI transparent command set
+    ScsiTransparent = 0x06,
+    /// LSD FS
+    LsdFs = 0x07,
+    /// IEEE 1667
+    Ieee1667 = 0x08,
+    /// Vendor-specific
+    VendorSpecific = 0xFF,
+}
+
+/// Mass Storage Protocol codes (transport)
+///
+/// See section 3 of (USBMSC1.4)
+#[derive(Clone, Copy)]
+pub enum Protocol {
+    /// Control/Bulk/Interrupt (CBI) transport with command completion interrupt
+    CbiWithInterrupt = 0x00,
+    /// Control/Bulk/Interrupt (CBI) transport without command completion interrupt
+    Cbi = 0x01,
+    /// Bulk-Only Transport (BOT)
+    BulkOnly = 0x50,
+    /// USB Attached SCSI (UAS)
+    Uas = 0x62,
+    /// Vendor-specific
+    VendorSpecific = 0xFF,
+}
+
+/// Mass Storage class-specific requests
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Request {
+    /// Interface index
+    pub interface: u8,
+    /// Kind of request
+    pub kind: Kind,
+}
+
+/// Mass Storage request kind
+///
+/// See section 3 of (USBMSBO1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// Bulk-Only Mass Storage Reset -- readies the interface for the next CBW
+    BulkOnlyMassStorageReset,
+    /// Get Max LUN -- returns the highest logical unit number (1 byte)
+    GetMaxLun,
+}
+
+const GET_MAX_LUN: u8 = 0xFE;
+const BULK_ONLY_MASS_STORAGE_RESET: u8 = 0xFF;
+
+impl Request {
+    /// Parses a Mass Storage request
+    pub fn parse(
+        bmrequesttype: u8,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        let bmrequesttype = bmRequestType::parse(bmrequesttype)?;
+
+        if bmrequesttype.ty != Type::Class {
+            return Err(());
+        }
+
+        Self::parse2(bmrequesttype, brequest, wvalue, windex, wlength)
+    }
+
+    pub(crate) fn parse2(
+        bmRequestType {
+            direction,
+            recipient,
+            // ty must be `Class`
+            ..
+        }: bmRequestType,
+        brequest: u8,
+        wvalue: u16,
+        windex: u16,
+        wlength: u16,
+    ) -> Result<Self, ()> {
+        if recipient != Recipient::Interface {
+            return Err(());
+        }
+
+        let kind = match (brequest, direction, wvalue, wlength) {
+            (BULK_ONLY_MASS_STORAGE_RESET, Direction::HostToDevice, 0, 0) => {
+                Kind::BulkOnlyMassStorageReset
+            }
+            (GET_MAX_LUN, Direction::DeviceToHost, 0, 1) => Kind::GetMaxLun,
+            _ => return Err(()),
+        };
+
+        Ok(Request {
+            interface: crate::windex2interface(windex)?,
+            kind,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Kind, Request};
+
+    #[test]
+    fn requests() {
+        assert_eq!(
+            Request::parse(0b0010_0001, 0xFF, 0, 2, 0),
+            Ok(Request {
+                interface: 2,
+                kind: Kind::BulkOnlyMassStorageReset,
+            })
+        );
+
+        assert_eq!(
+            Request::parse(0b1010_0001, 0xFE, 0, 0, 1),
+            Ok(Request {
+                interface: 0,
+                kind: Kind::GetMaxLun,
+            })
+        );
+
+        // wrong direction / length
+        assert!(Request::parse(0b0010_0001, 0xFE, 0, 0, 1).is_err());
+        assert!(Request::parse(0b1010_0001, 0xFE, 0, 0, 2).is_err());
+    }
+}
@@ -0,0 +1,347 @@
+//! Bulk-Only Transport (BOT)
+//!
+//! Each command is a Command Block Wrapper (CBW) sent on the bulk OUT endpoint, followed by an
+//! optional data phase and a Command Status Wrapper (CSW) sent on the bulk IN endpoint
+//!
+//! See (USBMSBO1.0)
+
+use crate::Direction;
+
+const CBW_SIGNATURE: u32 = 0x4342_5355;
+const CSW_SIGNATURE: u32 = 0x5342_5355;
+
+/// Largest command block a CBW can carry
+pub const MAX_CB_SIZE: usize = 16;
+
+/// Command Block Wrapper
+///
+/// See section 5.1 of (USBMSBO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CommandBlockWrapper {
+    // dCBWSignature: u32,
+    /// Command tag; echoed in the CSW
+    pub dCBWTag: u32,
+    /// Number of bytes the host expects to transfer in the data phase
+    pub dCBWDataTransferLength: u32,
+    /// Direction of the data phase (bit 7 of bmCBWFlags); ignored if `dCBWDataTransferLength` is
+    /// zero
+    pub bmCBWFlags: Direction,
+    /// Logical unit number (4 bits)
+    pub bCBWLUN: u8,
+    /// Number of valid bytes in `CBWCB` (1..=16)
+    pub bCBWCBLength: u8,
+    /// Command block
+    pub CBWCB: [u8; MAX_CB_SIZE],
+}
+
+impl CommandBlockWrapper {
+    /// Size of a CBW on the wire
+    pub const SIZE: u8 = 31;
+
+    /// Returns the valid bytes of the command block
+    ///
+    /// A `bCBWCBLength` larger than `MAX_CB_SIZE` is clamped to `MAX_CB_SIZE`
+    pub fn command(&self) -> &[u8] {
+        let length = core::cmp::min(usize::from(self.bCBWCBLength), MAX_CB_SIZE);
+        &self.CBWCB[..length]
+    }
+
+    /// Returns the wire representation of this CBW
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[0..4].copy_from_slice(&CBW_SIGNATURE.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.dCBWTag.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.dCBWDataTransferLength.to_le_bytes());
+        bytes[12] = (self.bmCBWFlags as u8) << 7;
+        bytes[13] = self.bCBWLUN & 0xF;
+        bytes[14] = self.bCBWCBLength & 0x1F;
+        bytes[15..].copy_from_slice(&self.CBWCB);
+        bytes
+    }
+
+    /// Parses a CBW
+    ///
+    /// Returns an error if the CBW is not valid (wrong size or signature) or not meaningful
+    /// (reserved bits set or invalid command block length). In either case the device must stall
+    /// both bulk endpoints until a Reset Recovery
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) || read32(bytes, 0) != CBW_SIGNATURE {
+            return Err(());
+        }
+
+        let flags = bytes[12];
+        let lun = bytes[13];
+        let length = bytes[14];
+        if flags & 0x7F != 0 || lun >> 4 != 0 || length == 0 || usize::from(length) > MAX_CB_SIZE {
+            return Err(());
+        }
+
+        let mut cb = [0; MAX_CB_SIZE];
+        cb.copy_from_slice(&bytes[15..]);
+
+        Ok(CommandBlockWrapper {
+            dCBWTag: read32(bytes, 4),
+            dCBWDataTransferLength: read32(bytes, 8),
+            bmCBWFlags: if flags >> 7 == 0 {
+                Direction::Out
+            } else {
+                Direction::In
+            },
+            bCBWLUN: lun,
+            bCBWCBLength: length,
+            CBWCB: cb,
+        })
+    }
+
+    /// Reconciles the data phase the host expects with the one the device intends
+    ///
+    /// Implements the thirteen cases of section 6.7 of (USBMSBO1.0)
+    pub fn disposition(&self, intent: DataPhase) -> Disposition {
+        let expected = self.dCBWDataTransferLength;
+
+        let (length, phase_error) = match (expected, self.bmCBWFlags, intent) {
+            // cases 1 and 4 / 9
+            (_, _, DataPhase::None) => (0, false),
+            // cases 2 and 3
+            (0, _, _) => (0, true),
+            // cases 5, 6 and 7
+            (_, Direction::In, DataPhase::In(n)) => (n.min(expected), n > expected),
+            // cases 11, 12 and 13
+            (_, Direction::Out, DataPhase::Out(n)) => (n.min(expected), n > expected),
+            // cases 8 and 10
+            _ => (0, true),
+        };
+
+        Disposition {
+            length,
+            stall: length < expected,
+            status: if phase_error {
+                Status::PhaseError
+            } else {
+                Status::Passed
+            },
+            residue: if phase_error { 0 } else { expected - length },
+        }
+    }
+
+    /// Returns the CSW that answers this CBW
+    pub fn status(&self, status: Status, residue: u32) -> CommandStatusWrapper {
+        CommandStatusWrapper {
+            dCSWTag: self.dCBWTag,
+            dCSWDataResidue: residue,
+            bCSWStatus: status,
+        }
+    }
+}
+
+/// Data phase intended by the device for a command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DataPhase {
+    /// No data (Dn)
+    None,
+    /// Send this many bytes to the host (Di)
+    In(u32),
+    /// Receive this many bytes from the host (Do)
+    Out(u32),
+}
+
+/// How the device must carry out the data and status phases of a command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Disposition {
+    /// Number of bytes to move in the data phase, in the direction of the CBW
+    pub length: u32,
+    /// After moving `length` bytes, stall the bulk endpoint of the CBW direction; the host clears
+    /// the stall and then reads the CSW
+    pub stall: bool,
+    /// Status to report; `Passed` may be replaced with `Failed` if the command fails
+    pub status: Status,
+    /// Value of `dCSWDataResidue`
+    pub residue: u32,
+}
+
+/// Command Status Wrapper
+///
+/// See section 5.2 of (USBMSBO1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CommandStatusWrapper {
+    // dCSWSignature: u32,
+    /// Tag of the CBW this CSW answers
+    pub dCSWTag: u32,
+    /// Difference between the expected and the processed amount of data
+    pub dCSWDataResidue: u32,
+    /// Command status
+    pub bCSWStatus: Status,
+}
+
+impl CommandStatusWrapper {
+    /// Size of a CSW on the wire
+    pub const SIZE: u8 = 13;
+
+    /// Returns the wire representation of this CSW
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[0..4].copy_from_slice(&CSW_SIGNATURE.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.dCSWTag.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.dCSWDataResidue.to_le_bytes());
+        bytes[12] = self.bCSWStatus as u8;
+        bytes
+    }
+
+    /// Parses a CSW
+    pub fn parse(bytes: &[u8]) -> Result<Self, ()> {
+        if bytes.len() != usize::from(Self::SIZE) || read32(bytes, 0) != CSW_SIGNATURE {
+            return Err(());
+        }
+
+        Ok(CommandStatusWrapper {
+            dCSWTag: read32(bytes, 4),
+            dCSWDataResidue: read32(bytes, 8),
+            bCSWStatus: Status::_from(bytes[12]).ok_or(())?,
+        })
+    }
+
+    /// Checks that this CSW is valid and meaningful for `cbw`
+    ///
+    /// A phase error, like an invalid CSW, requires a Reset Recovery. See section 6.3 of
+    /// (USBMSBO1.0)
+    pub fn check(&self, cbw: &CommandBlockWrapper) -> Result<(), ()> {
+        if self.dCSWTag != cbw.dCBWTag
+            || self.bCSWStatus == Status::PhaseError
+            || self.dCSWDataResidue > cbw.dCBWDataTransferLength
+        {
+            Err(())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+repr!(u8,
+      /// Command status (bCSWStatus)
+      Status {
+    /// Command passed
+    Passed = 0x00,
+    /// Command failed; the host should issue REQUEST SENSE
+    Failed = 0x01,
+    /// Phase error; the host must perform a Reset Recovery
+    PhaseError = 0x02,
+});
+
+fn read32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CommandBlockWrapper, CommandStatusWrapper, DataPhase, Disposition, Status, MAX_CB_SIZE,
+    };
+    use crate::Direction;
+
+    #[rustfmt::skip]
+    const READ_CAPACITY: [u8; 31] = [
+        0x55, 0x53, 0x42, 0x43, // signature
+        0x78, 0x56, 0x34, 0x12, // tag
+        0x08, 0x00, 0x00, 0x00, // length
+        0x80, 0x00, 0x0A,       // flags, LUN, CB length
+        0x25, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn cbw() {
+        let cbw = CommandBlockWrapper::parse(&READ_CAPACITY).unwrap();
+        assert_eq!(cbw.dCBWTag, 0x1234_5678);
+        assert_eq!(cbw.dCBWDataTransferLength, 8);
+        assert_eq!(cbw.bmCBWFlags, Direction::In);
+        assert_eq!(cbw.command(), &[0x25, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&cbw.bytes()[..], &READ_CAPACITY[..]);
+
+        // not valid
+        assert!(CommandBlockWrapper::parse(&READ_CAPACITY[..30]).is_err());
+        let mut bytes = READ_CAPACITY;
+        bytes[3] = 0x53;
+        assert!(CommandBlockWrapper::parse(&bytes).is_err());
+
+        // not meaningful
+        let mut bytes = READ_CAPACITY;
+        bytes[14] = 17;
+        assert!(CommandBlockWrapper::parse(&bytes).is_err());
+        let mut bytes = READ_CAPACITY;
+        bytes[12] = 0x81;
+        assert!(CommandBlockWrapper::parse(&bytes).is_err());
+
+        // out of range length set by hand
+        let cbw = CommandBlockWrapper {
+            bCBWCBLength: 31,
+            ..cbw
+        };
+        assert_eq!(cbw.command().len(), MAX_CB_SIZE);
+    }
+
+    #[test]
+    fn csw() {
+        let cbw = CommandBlockWrapper::parse(&READ_CAPACITY).unwrap();
+        let csw = cbw.status(Status::Passed, 0);
+        let bytes = csw.bytes();
+        assert_eq!(
+            bytes,
+            [0x55, 0x53, 0x42, 0x53, 0x78, 0x56, 0x34, 0x12, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(CommandStatusWrapper::parse(&bytes), Ok(csw));
+        assert_eq!(csw.check(&cbw), Ok(()));
+        assert!(cbw.status(Status::Passed, 9).check(&cbw).is_err());
+        assert!(cbw.status(Status::PhaseError, 0).check(&cbw).is_err());
+    }
+
+    #[test]
+    fn thirteen_cases() {
+        let cbw = |length, direction| CommandBlockWrapper {
+            dCBWTag: 0,
+            dCBWDataTransferLength: length,
+            bmCBWFlags: direction,
+            bCBWLUN: 0,
+            bCBWCBLength: 6,
+            CBWCB: [0; 16],
+        };
+        let ok = |length, stall, residue| Disposition {
+            length,
+            stall,
+            status: Status::Passed,
+            residue,
+        };
+        let phase_error = |length, stall| Disposition {
+            length,
+            stall,
+            status: Status::PhaseError,
+            residue: 0,
+        };
+
+        let hn = cbw(0, Direction::Out);
+        let hi = cbw(64, Direction::In);
+        let ho = cbw(64, Direction::Out);
+
+        // 1..=3
+        assert_eq!(hn.disposition(DataPhase::None), ok(0, false, 0));
+        assert_eq!(hn.disposition(DataPhase::In(8)), phase_error(0, false));
+        assert_eq!(hn.disposition(DataPhase::Out(8)), phase_error(0, false));
+        // 4..=8
+        assert_eq!(hi.disposition(DataPhase::None), ok(0, true, 64));
+        assert_eq!(hi.disposition(DataPhase::In(36)), ok(36, true, 28));
+        assert_eq!(hi.disposition(DataPhase::In(64)), ok(64, false, 0));
+        assert_eq!(hi.disposition(DataPhase::In(96)), phase_error(64, false));
+        assert_eq!(hi.disposition(DataPhase::Out(8)), phase_error(0, true));
+        // 9..=13
+        assert_eq!(ho.disposition(DataPhase::None), ok(0, true, 64));
+        assert_eq!(ho.disposition(DataPhase::In(8)), phase_error(0, true));
+        assert_eq!(ho.disposition(DataPhase::Out(32)), ok(32, true, 32));
+        assert_eq!(ho.disposition(DataPhase::Out(64)), ok(64, false, 0));
+        assert_eq!(ho.disposition(DataPhase::Out(96)), phase_error(64, false));
+    }
+}
@@ -0,0 +1,559 @@
+//! SCSI transparent command set
+//!
+//! Commands arrive in the command block of a CBW (`bot::CommandBlockWrapper::command`); the
+//! responses below are sent in the data phase. All multi-byte fields are big-endian
+//!
+//! See (SPC-4) and (SBC-3)
+
+const TEST_UNIT_READY: u8 = 0x00;
+const REQUEST_SENSE: u8 = 0x03;
+const INQUIRY: u8 = 0x12;
+const MODE_SENSE_6: u8 = 0x1A;
+const START_STOP_UNIT: u8 = 0x1B;
+const PREVENT_ALLOW_MEDIUM_REMOVAL: u8 = 0x1E;
+const READ_CAPACITY_10: u8 = 0x25;
+const READ_10: u8 = 0x28;
+const WRITE_10: u8 = 0x2A;
+const SYNCHRONIZE_CACHE_10: u8 = 0x35;
+const MODE_SENSE_10: u8 = 0x5A;
+const READ_16: u8 = 0x88;
+const WRITE_16: u8 = 0x8A;
+const SYNCHRONIZE_CACHE_16: u8 = 0x91;
+const SERVICE_ACTION_IN_16: u8 = 0x9E;
+const READ_12: u8 = 0xA8;
+const WRITE_12: u8 = 0xAA;
+
+// service action of READ CAPACITY (16)
+const READ_CAPACITY_16: u8 = 0x10;
+
+/// SCSI command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    /// TEST UNIT READY
+    TestUnitReady,
+    /// REQUEST SENSE -- returns `SenseData`
+    RequestSense {
+        /// Descriptor format sense data requested (DESC)
+        descriptor: bool,
+        /// Maximum number of bytes to return
+        allocation_length: u8,
+    },
+    /// INQUIRY -- returns `InquiryData` or a vital product data page
+    Inquiry {
+        /// Vital product data page requested (EVPD)
+        evpd: bool,
+        /// Vital product data page code
+        page_code: u8,
+        /// Maximum number of bytes to return
+        allocation_length: u16,
+    },
+    /// MODE SENSE (6) -- returns a 4-byte `ModeHeader` followed by mode pages
+    ModeSense6(ModeSense),
+    /// MODE SENSE (10) -- returns an 8-byte `ModeHeader` followed by mode pages
+    ModeSense10(ModeSense),
+    /// START STOP UNIT
+    StartStopUnit {
+        /// Return status before the operation completes (IMMED)
+        immediate: bool,
+        /// Power condition; 0 means use `load_eject` and `start`
+        power_condition: u8,
+        /// Load or eject the medium (LOEJ)
+        load_eject: bool,
+        /// Start the unit, or load the medium if `load_eject` is set
+        start: bool,
+    },
+    /// PREVENT ALLOW MEDIUM REMOVAL
+    PreventAllowMediumRemoval {
+        /// Prevent the removal of the medium
+        prevent: bool,
+    },
+    /// READ CAPACITY (10) -- returns `ReadCapacity10`
+    ReadCapacity10,
+    /// READ CAPACITY (16) -- returns `ReadCapacity16`
+    ReadCapacity16 {
+        /// Maximum number of bytes to return
+        allocation_length: u32,
+    },
+    /// READ (10), READ (12) or READ (16)
+    Read(Transfer),
+    /// WRITE (10), WRITE (12) or WRITE (16)
+    Write(Transfer),
+    /// SYNCHRONIZE CACHE (10) or SYNCHRONIZE CACHE (16)
+    SynchronizeCache {
+        /// First block to synchronize
+        lba: u64,
+        /// Number of blocks to synchronize; 0 means up to the last block
+        blocks: u32,
+        /// Return status before the operation completes (IMMED)
+        immediate: bool,
+    },
+}
+
+/// Parameters of the MODE SENSE commands
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModeSense {
+    /// Don't return block descriptors (DBD)
+    pub disable_block_descriptors: bool,
+    /// Page control (PC): 0 = current, 1 = changeable, 2 = default, 3 = saved values
+    pub page_control: u8,
+    /// Page code; 0x3F means all pages
+    pub page_code: u8,
+    /// Subpage code
+    pub subpage_code: u8,
+    /// Maximum number of bytes to return
+    pub allocation_length: u16,
+}
+
+/// Parameters of the READ and WRITE commands
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transfer {
+    /// Logical block address of the first block
+    pub lba: u64,
+    /// Number of blocks to transfer
+    pub blocks: u32,
+    /// Force unit access (FUA) -- bypass the volatile cache
+    pub fua: bool,
+}
+
+impl Command {
+    /// Parses a command descriptor block
+    ///
+    /// Returns an error for unsupported operation codes and truncated commands; the device should
+    /// then report CHECK CONDITION with `SenseKey::IllegalRequest` and
+    /// `AdditionalSense::INVALID_COMMAND_OPERATION_CODE`
+    pub fn parse(cdb: &[u8]) -> Result<Self, ()> {
+        let opcode = *cdb.first().ok_or(())?;
+        let size = match opcode {
+            0x00..=0x1F => 6,
+            0x20..=0x5F => 10,
+            0x80..=0x9F => 16,
+            0xA0..=0xBF => 12,
+            _ => return Err(()),
+        };
+        let cdb = cdb.get(..size).ok_or(())?;
+
+        Ok(match opcode {
+            TEST_UNIT_READY => Command::TestUnitReady,
+            REQUEST_SENSE => Command::RequestSense {
+                descriptor: cdb[1] & 1 != 0,
+                allocation_length: cdb[4],
+            },
+            INQUIRY => Command::Inquiry {
+                evpd: cdb[1] & 1 != 0,
+                page_code: cdb[2],
+                allocation_length: read16(cdb, 3),
+            },
+            MODE_SENSE_6 | MODE_SENSE_10 => {
+                let mut mode_sense = ModeSense {
+                    disable_block_descriptors: cdb[1] & (1 << 3) != 0,
+                    page_control: cdb[2] >> 6,
+                    page_code: cdb[2] & 0x3F,
+                    subpage_code: cdb[3],
+                    allocation_length: u16::from(cdb[4]),
+                };
+                if opcode == MODE_SENSE_6 {
+                    Command::ModeSense6(mode_sense)
+                } else {
+                    mode_sense.allocation_length = read16(cdb, 7);
+                    Command::ModeSense10(mode_sense)
+                }
+            }
+            START_STOP_UNIT => Command::StartStopUnit {
+                immediate: cdb[1] & 1 != 0,
+                power_condition: cdb[4] >> 4,
+                load_eject: cdb[4] & (1 << 1) != 0,
+                start: cdb[4] & 1 != 0,
+            },
+            PREVENT_ALLOW_MEDIUM_REMOVAL => Command::PreventAllowMediumRemoval {
+                prevent: cdb[4] & 0b11 != 0,
+            },
+            READ_CAPACITY_10 => Command::ReadCapacity10,
+            SERVICE_ACTION_IN_16 if cdb[1] & 0x1F == READ_CAPACITY_16 => Command::ReadCapacity16 {
+                allocation_length: read32(cdb, 10),
+            },
+            READ_10 | READ_12 | READ_16 => Command::Read(Transfer::parse(cdb)),
+            WRITE_10 | WRITE_12 | WRITE_16 => Command::Write(Transfer::parse(cdb)),
+            SYNCHRONIZE_CACHE_10 | SYNCHRONIZE_CACHE_16 => {
+                let Transfer { lba, blocks, .. } = Transfer::parse(cdb);
+                Command::SynchronizeCache {
+                    lba,
+                    blocks,
+                    immediate: cdb[1] & (1 << 1) != 0,
+                }
+            }
+            _ => return Err(()),
+        })
+    }
+}
+
+impl Transfer {
+    // `cdb` is 10, 12 or 16 bytes long
+    fn parse(cdb: &[u8]) -> Self {
+        let (lba, blocks) = match cdb.len() {
+            10 => (u64::from(read32(cdb, 2)), u32::from(read16(cdb, 7))),
+            12 => (u64::from(read32(cdb, 2)), read32(cdb, 6)),
+            _ => (
+                u64::from(read32(cdb, 2)) << 32 | u64::from(read32(cdb, 6)),
+                read32(cdb, 10),
+            ),
+        };
+
+        Transfer {
+            lba,
+            blocks,
+            fua: cdb[1] & (1 << 3) != 0,
+        }
+    }
+}
+
+/// Standard INQUIRY data
+///
+/// See section 6.4.2 of (SPC-4)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InquiryData {
+    /// Peripheral device type; 0x00 = direct access block device
+    pub peripheral_device_type: u8,
+    /// Removable medium (RMB)
+    pub removable: bool,
+    /// Version of the standard the device conforms to; e.g. 0x04 = SPC-2, 0x06 = SPC-4
+    pub version: u8,
+    /// Vendor identification (ASCII, space padded)
+    pub vendor: [u8; 8],
+    /// Product identification (ASCII, space padded)
+    pub product: [u8; 16],
+    /// Product revision level (ASCII, space padded)
+    pub revision: [u8; 4],
+}
+
+impl InquiryData {
+    /// Size of the standard INQUIRY data
+    pub const SIZE: u8 = 36;
+
+    /// Returns the wire representation of this data
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[0] = self.peripheral_device_type & 0x1F;
+        bytes[1] = (self.removable as u8) << 7;
+        bytes[2] = self.version;
+        // RESPONSE DATA FORMAT
+        bytes[3] = 2;
+        // ADDITIONAL LENGTH
+        bytes[4] = Self::SIZE - 5;
+        bytes[8..16].copy_from_slice(&self.vendor);
+        bytes[16..32].copy_from_slice(&self.product);
+        bytes[32..36].copy_from_slice(&self.revision);
+        bytes
+    }
+}
+
+/// Fixed format sense data, returned by REQUEST SENSE
+///
+/// See section 4.5.3 of (SPC-4)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SenseData {
+    /// Sense key
+    pub sense_key: SenseKey,
+    /// Additional sense code and qualifier
+    pub additional_sense: AdditionalSense,
+    /// Command-specific INFORMATION field; e.g. the first LBA that failed
+    pub information: Option<u32>,
+}
+
+impl SenseData {
+    /// Size of the fixed format sense data
+    pub const SIZE: u8 = 18;
+
+    /// Returns the wire representation of this data
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        // RESPONSE CODE: current errors, fixed format
+        bytes[0] = 0x70;
+        if let Some(information) = self.information {
+            // VALID
+            bytes[0] |= 1 << 7;
+            bytes[3..7].copy_from_slice(&information.to_be_bytes());
+        }
+        bytes[2] = self.sense_key as u8;
+        // ADDITIONAL SENSE LENGTH
+        bytes[7] = Self::SIZE - 8;
+        bytes[12] = self.additional_sense.asc;
+        bytes[13] = self.additional_sense.ascq;
+        bytes
+    }
+}
+
+repr!(u8,
+      /// Status of a completed command
+      Status {
+    /// GOOD
+    Good = 0x00,
+    /// CHECK CONDITION -- sense data is available
+    CheckCondition = 0x02,
+    /// CONDITION MET
+    ConditionMet = 0x04,
+    /// BUSY
+    Busy = 0x08,
+    /// RESERVATION CONFLICT
+    ReservationConflict = 0x18,
+    /// TASK SET FULL
+    TaskSetFull = 0x28,
+    /// ACA ACTIVE
+    AcaActive = 0x30,
+    /// TASK ABORTED
+    TaskAborted = 0x40,
+});
+
+repr!(u8,
+      /// Sense key
+      SenseKey {
+    /// No specific sense key information
+    NoSense = 0x0,
+    /// The command completed after some recovery action
+    RecoveredError = 0x1,
+    /// The logical unit is not accessible
+    NotReady = 0x2,
+    /// Non-recoverable error caused by the medium
+    MediumError = 0x3,
+    /// Non-recoverable hardware failure
+    HardwareError = 0x4,
+    /// Invalid command or parameter
+    IllegalRequest = 0x5,
+    /// The medium may have changed or the device was reset
+    UnitAttention = 0x6,
+    /// The medium is write protected
+    DataProtect = 0x7,
+    /// Blank or non-blank medium encountered
+    BlankCheck = 0x8,
+    /// Vendor-specific
+    VendorSpecific = 0x9,
+    /// A copy command was aborted
+    CopyAborted = 0xA,
+    /// The device aborted the command
+    AbortedCommand = 0xB,
+    /// The end of a partition was reached
+    VolumeOverflow = 0xD,
+    /// The source data didn't match the data on the medium
+    Miscompare = 0xE,
+});
+
+/// Additional sense code (ASC) and qualifier (ASCQ)
+///
+/// See section 4.5.6 of (SPC-4)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdditionalSense {
+    /// Additional sense code
+    pub asc: u8,
+    /// Additional sense code qualifier
+    pub ascq: u8,
+}
+
+impl AdditionalSense {
+    /// NO ADDITIONAL SENSE INFORMATION
+    pub const NO_ADDITIONAL_SENSE_INFORMATION: Self = Self::new(0x00, 0x00);
+    /// WRITE ERROR
+    pub const WRITE_ERROR: Self = Self::new(0x0C, 0x00);
+    /// UNRECOVERED READ ERROR
+    pub const UNRECOVERED_READ_ERROR: Self = Self::new(0x11, 0x00);
+    /// INVALID COMMAND OPERATION CODE
+    pub const INVALID_COMMAND_OPERATION_CODE: Self = Self::new(0x20, 0x00);
+    /// LOGICAL BLOCK ADDRESS OUT OF RANGE
+    pub const LBA_OUT_OF_RANGE: Self = Self::new(0x21, 0x00);
+    /// INVALID FIELD IN CDB
+    pub const INVALID_FIELD_IN_CDB: Self = Self::new(0x24, 0x00);
+    /// WRITE PROTECTED
+    pub const WRITE_PROTECTED: Self = Self::new(0x27, 0x00);
+    /// NOT READY TO READY CHANGE, MEDIUM MAY HAVE CHANGED
+    pub const MEDIUM_MAY_HAVE_CHANGED: Self = Self::new(0x28, 0x00);
+    /// POWER ON, RESET, OR BUS DEVICE RESET OCCURRED
+    pub const POWER_ON_RESET: Self = Self::new(0x29, 0x00);
+    /// MEDIUM NOT PRESENT
+    pub const MEDIUM_NOT_PRESENT: Self = Self::new(0x3A, 0x00);
+
+    const fn new(asc: u8, ascq: u8) -> Self {
+        AdditionalSense { asc, ascq }
+    }
+}
+
+/// READ CAPACITY (10) data
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReadCapacity10 {
+    /// Address of the last block; 0xFFFF_FFFF if it doesn't fit (use READ CAPACITY (16))
+    pub last_lba: u32,
+    /// Block size in bytes
+    pub block_length: u32,
+}
+
+impl ReadCapacity10 {
+    /// Size of this data on the wire
+    pub const SIZE: u8 = 8;
+
+    /// Returns the wire representation of this data
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[0..4].copy_from_slice(&self.last_lba.to_be_bytes());
+        bytes[4..8].copy_from_slice(&self.block_length.to_be_bytes());
+        bytes
+    }
+}
+
+/// READ CAPACITY (16) data
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReadCapacity16 {
+    /// Address of the last block
+    pub last_lba: u64,
+    /// Block size in bytes
+    pub block_length: u32,
+}
+
+impl ReadCapacity16 {
+    /// Size of this data on the wire
+    pub const SIZE: u8 = 32;
+
+    /// Returns the wire representation of this data
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let mut bytes = [0; Self::SIZE as usize];
+        bytes[0..8].copy_from_slice(&self.last_lba.to_be_bytes());
+        bytes[8..12].copy_from_slice(&self.block_length.to_be_bytes());
+        bytes
+    }
+}
+
+/// Mode parameter header, without block descriptors
+///
+/// See section 7.5.5 of (SPC-4)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModeHeader {
+    /// The medium is write protected (WP)
+    pub write_protect: bool,
+}
+
+impl ModeHeader {
+    /// Returns the MODE SENSE (6) header for `pages` bytes of mode pages
+    ///
+    /// Returns an error if the MODE DATA LENGTH doesn't fit in its field
+    pub fn bytes6(&self, pages: u8) -> Result<[u8; 4], ()> {
+        // MODE DATA LENGTH excludes itself
+        let length = pages.checked_add(3).ok_or(())?;
+        Ok([length, 0, self.device_specific(), 0])
+    }
+
+    /// Returns the MODE SENSE (10) header for `pages` bytes of mode pages
+    ///
+    /// Returns an error if the MODE DATA LENGTH doesn't fit in its field
+    pub fn bytes10(&self, pages: u16) -> Result<[u8; 8], ()> {
+        let length = pages.checked_add(6).ok_or(())?.to_be_bytes();
+        Ok([length[0], length[1], 0, self.device_specific(), 0, 0, 0, 0])
+    }
+
+    fn device_specific(&self) -> u8 {
+        (self.write_protect as u8) << 7
+    }
+}
+
+fn read16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AdditionalSense, Command, InquiryData, ModeHeader, ModeSense, SenseData, SenseKey, Transfer,
+    };
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Command::parse(&[0x12, 0, 0, 0, 36, 0]),
+            Ok(Command::Inquiry {
+                evpd: false,
+                page_code: 0,
+                allocation_length: 36,
+            })
+        );
+
+        assert_eq!(
+            Command::parse(&[0x28, 0x08, 0, 0, 0x10, 0, 0, 0, 8, 0]),
+            Ok(Command::Read(Transfer {
+                lba: 0x1000,
+                blocks: 8,
+                fua: true,
+            }))
+        );
+
+        #[rustfmt::skip]
+        let write16 = [
+            0x8A, 0,
+            0, 0, 0, 1, 0, 0, 0, 2,
+            0, 0, 1, 0,
+            0, 0,
+        ];
+        assert_eq!(
+            Command::parse(&write16),
+            Ok(Command::Write(Transfer {
+                lba: 0x1_0000_0002,
+                blocks: 0x100,
+                fua: false,
+            }))
+        );
+
+        assert_eq!(
+            Command::parse(&[0x5A, 0, 0x3F, 0, 0, 0, 0, 0, 0xC0, 0]),
+            Ok(Command::ModeSense10(ModeSense {
+                disable_block_descriptors: false,
+                page_control: 0,
+                page_code: 0x3F,
+                subpage_code: 0,
+                allocation_length: 0xC0,
+            }))
+        );
+
+        // truncated
+        assert!(Command::parse(&[0x25, 0, 0, 0]).is_err());
+        // unsupported
+        assert!(Command::parse(&[0x04, 0, 0, 0, 0, 0]).is_err());
+        // SERVICE ACTION IN (16) other than READ CAPACITY (16)
+        assert!(Command::parse(&[0x9E, 0x11, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn responses() {
+        let inquiry = InquiryData {
+            peripheral_device_type: 0,
+            removable: true,
+            version: 0x04,
+            vendor: *b"usb2    ",
+            product: *b"data logger     ",
+            revision: *b"0.1 ",
+        };
+        let bytes = inquiry.bytes();
+        assert_eq!(&bytes[..5], &[0x00, 0x80, 0x04, 0x02, 31]);
+        assert_eq!(&bytes[8..16], b"usb2    ");
+
+        let sense = SenseData {
+            sense_key: SenseKey::IllegalRequest,
+            additional_sense: AdditionalSense::INVALID_FIELD_IN_CDB,
+            information: None,
+        };
+        let bytes = sense.bytes();
+        assert_eq!(&bytes[..8], &[0x70, 0, 0x05, 0, 0, 0, 0, 10]);
+        assert_eq!(&bytes[12..14], &[0x24, 0x00]);
+
+        let header = ModeHeader {
+            write_protect: true,
+        };
+        assert_eq!(header.bytes6(0), Ok([3, 0, 0x80, 0]));
+        assert_eq!(header.bytes10(0), Ok([0, 6, 0, 0x80, 0, 0, 0, 0]));
+        assert_eq!(header.bytes6(252), Ok([255, 0, 0x80, 0]));
+        assert!(header.bytes6(253).is_err());
+        assert!(header.bytes10(u16::MAX - 5).is_err());
+    }
+}
@@ -0,0 +1,549 @@
+//! USB Attached SCSI (UAS)
+//!
+//! A UAS function uses four bulk pipes: Command (OUT), Status (IN), Data-In and Data-Out. Each
+//! exchange is made of information units (IU); all multi-byte fields are big-endian
+//!
+//! Without streams (USB 2.0) a command proceeds as follows:
+//!
+//! - the host sends a `Command` IU on the Command pipe
+//! - if the command has a data phase, the device sends a `ReadReady` or `WriteReady` IU with the
+//!   tag of the command on the Status pipe (see `ready`), and the data is then moved on the
+//!   Data-In or Data-Out pipe
+//! - the device completes the command with a `Sense` IU on the Status pipe
+//!
+//! Task management requests are answered with a `Response` IU on the Status pipe
+//!
+//! See (USBUAS1.0)
+
+use core::{convert::TryFrom, num::NonZeroU8};
+
+use super::{scsi, Class, Protocol, SubClass};
+use crate::{endpoint, interface, Direction, Endpoint};
+
+const COMMAND_IU: u8 = 0x01;
+const SENSE_IU: u8 = 0x03;
+const RESPONSE_IU: u8 = 0x04;
+const TASK_MANAGEMENT_IU: u8 = 0x05;
+const READ_READY_IU: u8 = 0x06;
+const WRITE_READY_IU: u8 = 0x07;
+
+// IU ID, reserved and tag
+const HEADER_SIZE: usize = 4;
+
+const PIPE_USAGE: u8 = 0x24;
+
+/// Size of the command block field of a Command IU, excluding the additional CDB bytes
+pub const CDB_SIZE: usize = 16;
+
+/// Logical unit number, in the 8-byte SAM format
+pub type Lun = [u8; 8];
+
+/// Pipe Usage descriptor; follows each endpoint descriptor of the UAS alternate setting
+///
+/// See section 5.3.3.1 of (USBUAS1.0)
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct PipeUsage {
+    // bLength: u8,
+    // bDescriptorType: u8,
+    /// Pipe this endpoint implements
+    pub bPipeID: PipeId,
+}
+
+impl PipeUsage {
+    /// Size of this descriptor on the wire
+    pub const SIZE: u8 = 4;
+
+    /// Returns the wire representation of this descriptor
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        [Self::SIZE, PIPE_USAGE, self.bPipeID as u8, 0]
+    }
+}
+
+repr!(u8,
+      /// UAS pipe
+      PipeId {
+    /// Command pipe (bulk OUT)
+    Command = 1,
+    /// Status pipe (bulk IN)
+    Status = 2,
+    /// Data-In pipe (bulk IN)
+    DataIn = 3,
+    /// Data-Out pipe (bulk OUT)
+    DataOut = 4,
+});
+
+/// Mass storage interface with a Bulk-Only alternate setting (0) and a UAS alternate setting (1)
+///
+/// The Bulk-Only setting uses the Data-In and Data-Out endpoints
+#[allow(non_snake_case)]
+#[derive(Clone, Copy)]
+pub struct Interface {
+    /// Interface number
+    pub interface: u8,
+    /// Interface string descriptor index
+    pub iInterface: Option<NonZeroU8>,
+    /// Command pipe endpoint number (bulk OUT)
+    pub command_endpoint: u8,
+    /// Status pipe endpoint number (bulk IN)
+    pub status_endpoint: u8,
+    /// Data-In pipe endpoint number (bulk IN)
+    pub data_in_endpoint: u8,
+    /// Data-Out pipe endpoint number (bulk OUT)
+    pub data_out_endpoint: u8,
+    /// Maximum packet size of the bulk endpoints
+    pub max_packet_size: u16,
+}
+
+impl Interface {
+    /// Size of the descriptors of this interface on the wire
+    pub const SIZE: u8 =
+        2 * interface::Descriptor::SIZE + 6 * endpoint::Descriptor::SIZE + 4 * PipeUsage::SIZE;
+
+    /// Returns the wire representation of the descriptors of this interface
+    pub fn bytes(&self) -> [u8; Self::SIZE as usize] {
+        let interface = |alternate, protocol, endpoints| {
+            let class = Class {
+                subclass: SubClass::ScsiTransparent,
+                protocol,
+            };
+
+            interface::Descriptor {
+                bInterfaceNumber: self.interface,
+                bAlternativeSetting: alternate,
+                bNumEndpoints: endpoints,
+                bInterfaceClass: class.class().get(),
+                bInterfaceSubClass: class.subclass(),
+                bInterfaceProtocol: class.protocol(),
+                iInterface: self.iInterface,
+            }
+            .bytes()
+        };
+
+        let bulk = |direction, number| {
+            endpoint::Descriptor {
+                bEndpointAddress: Endpoint { direction, number },
+                ty: endpoint::Type::Bulk,
+                max_packet_size: self.max_packet_size,
+                bInterval: 0,
+            }
+            .bytes()
+        };
+
+        let pipe = |id| PipeUsage { bPipeID: id }.bytes();
+
+        let mut bytes = [0; Self::SIZE as usize];
+        let mut pos = 0;
+        for part in &[
+            &interface(0, Protocol::BulkOnly, 2)[..],
+            &bulk(Direction::In, self.data_in_endpoint),
+            &bulk(Direction::Out, self.data_out_endpoint),
+            &interface(1, Protocol::Uas, 4),
+            &bulk(Direction::Out, self.command_endpoint),
+            &pipe(PipeId::Command),
+            &bulk(Direction::In, self.status_endpoint),
+            &pipe(PipeId::Status),
+            &bulk(Direction::In, self.data_in_endpoint),
+            &pipe(PipeId::DataIn),
+            &bulk(Direction::Out, self.data_out_endpoint),
+            &pipe(PipeId::DataOut),
+        ] {
+            bytes[pos..pos + part.len()].copy_from_slice(part);
+            pos += part.len();
+        }
+
+        bytes
+    }
+}
+
+/// Information unit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Iu<'a> {
+    /// Command IU (host to device)
+    Command(Command<'a>),
+    /// Sense IU (device to host)
+    Sense(Sense<'a>),
+    /// Response IU (device to host)
+    Response(Response),
+    /// Task Management IU (host to device)
+    TaskManagement(TaskManagement),
+    /// Read Ready IU -- the device is ready to send the data of the command
+    ReadReady {
+        /// Tag of the command
+        tag: u16,
+    },
+    /// Write Ready IU -- the device is ready to receive the data of the command
+    WriteReady {
+        /// Tag of the command
+        tag: u16,
+    },
+}
+
+/// Returns the IU that starts the data phase of the command `tag` on a stream-less (USB 2.0)
+/// connection
+///
+/// `direction` is the direction of the data phase
+pub fn ready(tag: u16, direction: Direction) -> Iu<'static> {
+    match direction {
+        Direction::In => Iu::ReadReady { tag },
+        Direction::Out => Iu::WriteReady { tag },
+    }
+}
+
+/// Command IU
+///
+/// See section 6.2.2 of (USBUAS1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Command<'a> {
+    /// Command tag
+    pub tag: u16,
+    /// Command priority (4 bits)
+    pub priority: u8,
+    /// Task attribute
+    pub attribute: TaskAttribute,
+    /// Logical unit
+    pub lun: Lun,
+    /// Command descriptor block; see `scsi::Command::parse`
+    ///
+    /// When parsed this is 16 bytes plus the additional CDB bytes; when encoded, shorter CDBs are
+    /// zero-padded to 16 bytes
+    pub cdb: &'a [u8],
+}
+
+/// Sense IU
+///
+/// See section 6.2.5 of (USBUAS1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sense<'a> {
+    /// Command tag
+    pub tag: u16,
+    /// Status qualifier
+    pub status_qualifier: u16,
+    /// SCSI status
+    pub status: scsi::Status,
+    /// Sense data; e.g. `scsi::SenseData`. Empty unless `status` is `CheckCondition`
+    pub sense: &'a [u8],
+}
+
+/// Response IU
+///
+/// See section 6.2.6 of (USBUAS1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Response {
+    /// Tag of the task management function
+    pub tag: u16,
+    /// Additional response information
+    pub additional_information: [u8; 3],
+    /// Response code
+    pub code: ResponseCode,
+}
+
+/// Task Management IU
+///
+/// See section 6.2.3 of (USBUAS1.0)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TaskManagement {
+    /// Tag of this task management function
+    pub tag: u16,
+    /// Task management function
+    pub function: TaskManagementFunction,
+    /// Tag of the task to manage; used by ABORT TASK and QUERY TASK
+    pub task_tag: u16,
+    /// Logical unit
+    pub lun: Lun,
+}
+
+repr!(u8,
+      /// Task attribute of a command
+      TaskAttribute {
+    /// SIMPLE
+    Simple = 0,
+    /// HEAD OF QUEUE
+    HeadOfQueue = 1,
+    /// ORDERED
+    Ordered = 2,
+    /// ACA
+    Aca = 4,
+});
+
+repr!(u8,
+      /// Task management function
+      TaskManagementFunction {
+    /// ABORT TASK
+    AbortTask = 0x01,
+    /// ABORT TASK SET
+    AbortTaskSet = 0x02,
+    /// CLEAR TASK SET
+    ClearTaskSet = 0x04,
+    /// LOGICAL UNIT RESET
+    LogicalUnitReset = 0x08,
+    /// I_T NEXUS RESET
+    ItNexusReset = 0x10,
+    /// CLEAR ACA
+    ClearAca = 0x40,
+    /// QUERY TASK
+    QueryTask = 0x80,
+    /// QUERY TASK SET
+    QueryTaskSet = 0x81,
+    /// QUERY ASYNCHRONOUS EVENT
+    QueryAsynchronousEvent = 0x82,
+});
+
+repr!(u8,
+      /// Response code of a Response IU
+      ResponseCode {
+    /// TASK MANAGEMENT FUNCTION COMPLETE
+    Complete = 0x00,
+    /// INVALID INFORMATION UNIT
+    InvalidIu = 0x02,
+    /// TASK MANAGEMENT FUNCTION NOT SUPPORTED
+    NotSupported = 0x04,
+    /// TASK MANAGEMENT FUNCTION FAILED
+    Failed = 0x05,
+    /// TASK MANAGEMENT FUNCTION SUCCEEDED
+    Succeeded = 0x08,
+    /// INCORRECT LOGICAL UNIT NUMBER
+    IncorrectLun = 0x09,
+    /// OVERLAPPED TAG ATTEMPTED
+    OverlappedTag = 0x0A,
+});
+
+impl<'a> Iu<'a> {
+    /// Returns the tag of this IU
+    pub fn tag(&self) -> u16 {
+        match self {
+            Iu::Command(command) => command.tag,
+            Iu::Sense(sense) => sense.tag,
+            Iu::Response(response) => response.tag,
+            Iu::TaskManagement(tm) => tm.tag,
+            Iu::ReadReady { tag } | Iu::WriteReady { tag } => *tag,
+        }
+    }
+
+    /// Parses an information unit
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ()> {
+        let header = bytes.get(..HEADER_SIZE).ok_or(())?;
+        let tag = read16(header, 2);
+
+        Ok(match header[0] {
+            COMMAND_IU => {
+                let fixed = bytes.get(..HEADER_SIZE + 12 + CDB_SIZE).ok_or(())?;
+                if fixed[6] & 0b11 != 0 {
+                    return Err(());
+                }
+                let additional = usize::from(fixed[6] >> 2) * 4;
+
+                let mut lun = [0; 8];
+                lun.copy_from_slice(&fixed[8..16]);
+                Iu::Command(Command {
+                    tag,
+                    priority: (fixed[4] >> 3) & 0xF,
+                    attribute: TaskAttribute::_from(fixed[4] & 0b111).ok_or(())?,
+                    lun,
+                    cdb: bytes.get(16..32 + additional).ok_or(())?,
+                })
+            }
+            SENSE_IU => {
+                let fixed = bytes.get(..16).ok_or(())?;
+                let length = usize::from(read16(fixed, 14));
+                Iu::Sense(Sense {
+                    tag,
+                    status_qualifier: read16(fixed, 4),
+                    status: scsi::Status::_from(fixed[6]).ok_or(())?,
+                    sense: bytes.get(16..16 + length).ok_or(())?,
+                })
+            }
+            RESPONSE_IU => {
+                let bytes = bytes.get(..8).ok_or(())?;
+                Iu::Response(Response {
+                    tag,
+                    additional_information: [bytes[4], bytes[5], bytes[6]],
+                    code: ResponseCode::_from(bytes[7]).ok_or(())?,
+                })
+            }
+            TASK_MANAGEMENT_IU => {
+                let bytes = bytes.get(..16).ok_or(())?;
+                let mut lun = [0; 8];
+                lun.copy_from_slice(&bytes[8..16]);
+                Iu::TaskManagement(TaskManagement {
+                    tag,
+                    function: TaskManagementFunction::_from(bytes[4]).ok_or(())?,
+                    task_tag: read16(bytes, 6),
+                    lun,
+                })
+            }
+            READ_READY_IU => Iu::ReadReady { tag },
+            WRITE_READY_IU => Iu::WriteReady { tag },
+            _ => return Err(()),
+        })
+    }
+
+    /// Writes the wire representation of this IU into `buf`
+    ///
+    /// Returns the size of the IU, or an error if `buf` is too small or a field is out of range
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let (id, size) = match self {
+            Iu::Command(command) => {
+                let additional = command.cdb.len().saturating_sub(CDB_SIZE);
+                // the additional CDB length is a 6-bit count of dwords
+                if additional % 4 != 0 || additional > 0x3F * 4 || command.priority > 0xF {
+                    return Err(());
+                }
+                (COMMAND_IU, 32 + additional)
+            }
+            Iu::Sense(sense) => (SENSE_IU, 16 + sense.sense.len()),
+            Iu::Response(_) => (RESPONSE_IU, 8),
+            Iu::TaskManagement(_) => (TASK_MANAGEMENT_IU, 16),
+            Iu::ReadReady { .. } => (READ_READY_IU, HEADER_SIZE),
+            Iu::WriteReady { .. } => (WRITE_READY_IU, HEADER_SIZE),
+        };
+
+        let buf = buf.get_mut(..size).ok_or(())?;
+        for byte in buf.iter_mut() {
+            *byte = 0;
+        }
+        buf[0] = id;
+        buf[2..4].copy_from_slice(&self.tag().to_be_bytes());
+
+        match self {
+            Iu::Command(command) => {
+                buf[4] = command.priority << 3 | command.attribute as u8;
+                // ADDITIONAL CDB LENGTH, in dwords, lives in bits 7:2
+                buf[6] = command.cdb.len().saturating_sub(CDB_SIZE) as u8;
+                buf[8..16].copy_from_slice(&command.lun);
+                buf[16..16 + command.cdb.len()].copy_from_slice(command.cdb);
+            }
+            Iu::Sense(sense) => {
+                let length = u16::try_from(sense.sense.len()).map_err(|_| ())?;
+                buf[4..6].copy_from_slice(&sense.status_qualifier.to_be_bytes());
+                buf[6] = sense.status as u8;
+                buf[14..16].copy_from_slice(&length.to_be_bytes());
+                buf[16..].copy_from_slice(sense.sense);
+            }
+            Iu::Response(response) => {
+                buf[4..7].copy_from_slice(&response.additional_information);
+                buf[7] = response.code as u8;
+            }
+            Iu::TaskManagement(tm) => {
+                buf[4] = tm.function as u8;
+                buf[6..8].copy_from_slice(&tm.task_tag.to_be_bytes());
+                buf[8..16].copy_from_slice(&tm.lun);
+            }
+            Iu::ReadReady { .. } | Iu::WriteReady { .. } => {}
+        }
+
+        Ok(size)
+    }
+}
+
+fn read16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ready, Command, Interface, Iu, Response, ResponseCode, Sense, TaskAttribute,
+        TaskManagement, TaskManagementFunction,
+    };
+    use crate::{msc::scsi, Direction};
+
+    #[test]
+    fn command() {
+        let cdb = [0x28, 0, 0, 0, 0, 0, 0, 0, 1, 0];
+        let iu = Iu::Command(Command {
+            tag: 3,
+            priority: 0,
+            attribute: TaskAttribute::Simple,
+            lun: [0; 8],
+            cdb: &cdb,
+        });
+
+        let mut buf = [0xff; 64];
+        assert_eq!(iu.bytes(&mut buf), Ok(32));
+        assert_eq!(&buf[..8], &[0x01, 0, 0, 3, 0, 0, 0, 0]);
+
+        match Iu::parse(&buf[..32]).unwrap() {
+            Iu::Command(command) => {
+                assert_eq!(command.tag, 3);
+                assert_eq!(command.cdb.len(), 16);
+                assert_eq!(&command.cdb[..10], &cdb[..]);
+                assert!(scsi::Command::parse(command.cdb).is_ok());
+            }
+            _ => panic!(),
+        }
+
+        // additional CDB bytes must come in dwords
+        let cdb = [0; 18];
+        let iu = Iu::Command(Command {
+            tag: 3,
+            priority: 0,
+            attribute: TaskAttribute::Simple,
+            lun: [0; 8],
+            cdb: &cdb,
+        });
+        assert!(iu.bytes(&mut buf).is_err());
+    }
+
+    #[test]
+    fn status() {
+        let mut buf = [0; 64];
+
+        assert_eq!(ready(7, Direction::In).bytes(&mut buf), Ok(4));
+        assert_eq!(&buf[..4], &[0x06, 0, 0, 7]);
+        assert_eq!(Iu::parse(&buf[..4]), Ok(Iu::ReadReady { tag: 7 }));
+
+        let sense = Iu::Sense(Sense {
+            tag: 7,
+            status_qualifier: 0,
+            status: scsi::Status::CheckCondition,
+            sense: &[0x70, 0, 0x05],
+        });
+        let n = sense.bytes(&mut buf).unwrap();
+        assert_eq!(n, 19);
+        assert_eq!(
+            &buf[..16],
+            &[3, 0, 0, 7, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 3]
+        );
+        assert_eq!(Iu::parse(&buf[..n]), Ok(sense));
+
+        let tm = Iu::TaskManagement(TaskManagement {
+            tag: 8,
+            function: TaskManagementFunction::AbortTask,
+            task_tag: 7,
+            lun: [0; 8],
+        });
+        let n = tm.bytes(&mut buf).unwrap();
+        assert_eq!(Iu::parse(&buf[..n]), Ok(tm));
+
+        let response = Iu::Response(Response {
+            tag: 8,
+            additional_information: [0; 3],
+            code: ResponseCode::Complete,
+        });
+        let n = response.bytes(&mut buf).unwrap();
+        assert_eq!(&buf[..n], &[4, 0, 0, 8, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn interface() {
+        let bytes = Interface {
+            interface: 0,
+            iInterface: None,
+            command_endpoint: 1,
+            status_endpoint: 2,
+            data_in_endpoint: 3,
+            data_out_endpoint: 4,
+            max_packet_size: 512,
+        }
+        .bytes();
+
+        assert_eq!(bytes.len(), 76);
+        // Bulk-Only alternate setting
+        assert_eq!(&bytes[3..8], &[0, 2, 0x08, 0x06, 0x50]);
+        // UAS alternate setting
+        assert_eq!(&bytes[26..31], &[1, 4, 0x08, 0x06, 0x62]);
+        // Command pipe
+        assert_eq!(&bytes[32..43], &[7, 5, 0x01, 2, 0, 2, 0, 4, 0x24, 1, 0]);
+    }
+}
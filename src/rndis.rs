@@ -0,0 +1,639 @@
+//! Remote NDIS (RNDIS)
+//!
+//! RNDIS functions use the CDC descriptors of an ACM function with a vendor-specific protocol; see
+//! `function`. Control messages are sent by the host with SEND_ENCAPSULATED_COMMAND and read with
+//! GET_ENCAPSULATED_RESPONSE (see `cdc::acm::Kind`); Ethernet frames are exchanged over the bulk
+//! endpoints wrapped in `Packet`s
+//!
+//! See (MS-RNDIS)
+
+use crate::cdc::{acm, Protocol};
+
+/// Notification sent on the interrupt endpoint when a response is available
+pub const RESPONSE_AVAILABLE: [u8; 8] = [1, 0, 0, 0, 0, 0, 0, 0];
+
+/// Status: success
+pub const STATUS_SUCCESS: u32 = 0x0000_0000;
+/// Status: unspecified failure
+pub const STATUS_FAILURE: u32 = 0xC000_0001;
+/// Status: the request contains invalid data
+pub const STATUS_INVALID_DATA: u32 = 0xC001_0015;
+/// Status: the request (OID) is not supported
+pub const STATUS_NOT_SUPPORTED: u32 = 0xC000_00BB;
+/// Status indication: the link is up
+pub const STATUS_MEDIA_CONNECT: u32 = 0x4001_000B;
+/// Status indication: the link is down
+pub const STATUS_MEDIA_DISCONNECT: u32 = 0x4001_000C;
+
+/// Object identifiers (OID) used in QUERY and SET messages
+pub mod oid {
+    /// List of supported OIDs
+    pub const GEN_SUPPORTED_LIST: u32 = 0x0001_0101;
+    /// Hardware status
+    pub const GEN_HARDWARE_STATUS: u32 = 0x0001_0102;
+    /// Supported media types
+    pub const GEN_MEDIA_SUPPORTED: u32 = 0x0001_0103;
+    /// Media type in use
+    pub const GEN_MEDIA_IN_USE: u32 = 0x0001_0104;
+    /// Maximum frame size, excluding the Ethernet header
+    pub const GEN_MAXIMUM_FRAME_SIZE: u32 = 0x0001_0106;
+    /// Link speed in units of 100 bps
+    pub const GEN_LINK_SPEED: u32 = 0x0001_0107;
+    /// Transmit block size
+    pub const GEN_TRANSMIT_BLOCK_SIZE: u32 = 0x0001_010A;
+    /// Receive block size
+    pub const GEN_RECEIVE_BLOCK_SIZE: u32 = 0x0001_010B;
+    /// IEEE vendor ID
+    pub const GEN_VENDOR_ID: u32 = 0x0001_010C;
+    /// Vendor description (NUL-terminated string)
+    pub const GEN_VENDOR_DESCRIPTION: u32 = 0x0001_010D;
+    /// Packet filter
+    pub const GEN_CURRENT_PACKET_FILTER: u32 = 0x0001_010E;
+    /// Maximum total packet size, including the Ethernet header
+    pub const GEN_MAXIMUM_TOTAL_SIZE: u32 = 0x0001_0111;
+    /// Link state: 0 = connected, 1 = disconnected
+    pub const GEN_MEDIA_CONNECT_STATUS: u32 = 0x0001_0114;
+    /// Physical medium
+    pub const GEN_PHYSICAL_MEDIUM: u32 = 0x0001_0202;
+    /// Frames transmitted without errors
+    pub const GEN_XMIT_OK: u32 = 0x0002_0101;
+    /// Frames received without errors
+    pub const GEN_RCV_OK: u32 = 0x0002_0102;
+    /// Frames not transmitted, or transmitted with errors
+    pub const GEN_XMIT_ERROR: u32 = 0x0002_0103;
+    /// Frames received with errors
+    pub const GEN_RCV_ERROR: u32 = 0x0002_0104;
+    /// Frames missed due to lack of buffers
+    pub const GEN_RCV_NO_BUFFER: u32 = 0x0002_0105;
+    /// MAC address burnt into the device
+    pub const _802_3_PERMANENT_ADDRESS: u32 = 0x0101_0101;
+    /// MAC address currently in use
+    pub const _802_3_CURRENT_ADDRESS: u32 = 0x0101_0102;
+    /// Multicast address list
+    pub const _802_3_MULTICAST_LIST: u32 = 0x0101_0103;
+    /// Maximum size of the multicast address list
+    pub const _802_3_MAXIMUM_LIST_SIZE: u32 = 0x0101_0104;
+    /// Frames received with alignment errors
+    pub const _802_3_RCV_ERROR_ALIGNMENT: u32 = 0x0102_0101;
+    /// Frames transmitted with one collision
+    pub const _802_3_XMIT_ONE_COLLISION: u32 = 0x0102_0102;
+    /// Frames transmitted with more than one collision
+    pub const _802_3_XMIT_MORE_COLLISIONS: u32 = 0x0102_0103;
+}
+
+/// Returns the ACM function that carries an RNDIS function
+///
+/// The communications interface uses the ACM subclass with a vendor-specific protocol and no ACM
+/// capabilities
+pub fn function(
+    first_interface: u8,
+    notification_endpoint: u8,
+    data_in_endpoint: u8,
+    data_out_endpoint: u8,
+    data_max_packet_size: u16,
+) -> acm::Function {
+    acm::Function {
+        first_interface,
+        protocol: Protocol::VendorSpecific,
+        capabilities: acm::Capabilities {
+            comm_features: false,
+            line_serial: false,
+            send_break: false,
+            network_connection: false,
+        },
+        iFunction: None,
+        notification_endpoint,
+        notification_max_packet_size: RESPONSE_AVAILABLE.len() as u16,
+        notification_interval: 1,
+        data_in_endpoint,
+        data_out_endpoint,
+        data_max_packet_size,
+    }
+}
+
+const PACKET_MSG: u32 = 0x0000_0001;
+const INITIALIZE_MSG: u32 = 0x0000_0002;
+const HALT_MSG: u32 = 0x0000_0003;
+const QUERY_MSG: u32 = 0x0000_0004;
+const SET_MSG: u32 = 0x0000_0005;
+const RESET_MSG: u32 = 0x0000_0006;
+const INDICATE_STATUS_MSG: u32 = 0x0000_0007;
+const KEEPALIVE_MSG: u32 = 0x0000_0008;
+// set in the type of completion messages
+const COMPLETION: u32 = 0x8000_0000;
+
+// MessageType and MessageLength
+const HEADER_SIZE: usize = 8;
+
+/// Device flag: connectionless (802.3) device
+pub const DF_CONNECTIONLESS: u32 = 0x0000_0001;
+
+/// Medium: 802.3 (Ethernet)
+pub const MEDIUM_802_3: u32 = 0;
+
+/// REMOTE_NDIS_INITIALIZE_CMPLT body
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InitializeComplete {
+    /// ID of the INITIALIZE request
+    pub request_id: u32,
+    /// Status; see `STATUS_SUCCESS`
+    pub status: u32,
+    /// RNDIS major version; `1`
+    pub major_version: u32,
+    /// RNDIS minor version; `0`
+    pub minor_version: u32,
+    /// Device flags; see `DF_CONNECTIONLESS`
+    pub device_flags: u32,
+    /// Medium; see `MEDIUM_802_3`
+    pub medium: u32,
+    /// Maximum number of packets per bulk transfer
+    pub max_packets_per_transfer: u32,
+    /// Maximum size of a bulk transfer the device accepts
+    pub max_transfer_size: u32,
+    /// Packets in a multi-packet transfer are aligned to `1 << packet_alignment_factor` bytes
+    pub packet_alignment_factor: u32,
+}
+
+/// RNDIS control message
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Message<'a> {
+    /// REMOTE_NDIS_INITIALIZE_MSG
+    Initialize {
+        /// Request ID
+        request_id: u32,
+        /// RNDIS major version
+        major_version: u32,
+        /// RNDIS minor version
+        minor_version: u32,
+        /// Maximum size of a bulk transfer the host accepts
+        max_transfer_size: u32,
+    },
+    /// REMOTE_NDIS_INITIALIZE_CMPLT
+    InitializeComplete(InitializeComplete),
+    /// REMOTE_NDIS_HALT_MSG
+    Halt {
+        /// Request ID
+        request_id: u32,
+    },
+    /// REMOTE_NDIS_QUERY_MSG
+    Query {
+        /// Request ID
+        request_id: u32,
+        /// Object being queried; see the `oid` module
+        oid: u32,
+        /// Input buffer; usually empty
+        information: &'a [u8],
+    },
+    /// REMOTE_NDIS_QUERY_CMPLT
+    QueryComplete {
+        /// ID of the QUERY request
+        request_id: u32,
+        /// Status; see `STATUS_SUCCESS`
+        status: u32,
+        /// Value of the object
+        information: &'a [u8],
+    },
+    /// REMOTE_NDIS_SET_MSG
+    Set {
+        /// Request ID
+        request_id: u32,
+        /// Object being set; see the `oid` module
+        oid: u32,
+        /// New value of the object
+        information: &'a [u8],
+    },
+    /// REMOTE_NDIS_SET_CMPLT
+    SetComplete {
+        /// ID of the SET request
+        request_id: u32,
+        /// Status; see `STATUS_SUCCESS`
+        status: u32,
+    },
+    /// REMOTE_NDIS_RESET_MSG
+    Reset,
+    /// REMOTE_NDIS_RESET_CMPLT
+    ResetComplete {
+        /// Status; see `STATUS_SUCCESS`
+        status: u32,
+        /// The host must send the multicast address list and packet filter again
+        addressing_reset: bool,
+    },
+    /// REMOTE_NDIS_INDICATE_STATUS_MSG
+    IndicateStatus {
+        /// Status being indicated; e.g. `STATUS_MEDIA_CONNECT`
+        status: u32,
+        /// Status buffer
+        buffer: &'a [u8],
+    },
+    /// REMOTE_NDIS_KEEPALIVE_MSG
+    KeepAlive {
+        /// Request ID
+        request_id: u32,
+    },
+    /// REMOTE_NDIS_KEEPALIVE_CMPLT
+    KeepAliveComplete {
+        /// ID of the KEEPALIVE request
+        request_id: u32,
+        /// Status; see `STATUS_SUCCESS`
+        status: u32,
+    },
+}
+
+impl<'a> Message<'a> {
+    /// Parses a control message
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, ()> {
+        let ty = read32(bytes, 0)?;
+        if read32(bytes, 4)? as usize != bytes.len() {
+            return Err(());
+        }
+
+        // fixed-size messages
+        let fixed = |size: usize| -> Result<(), ()> {
+            if bytes.len() == size {
+                Ok(())
+            } else {
+                Err(())
+            }
+        };
+        let word = |i: usize| read32(bytes, HEADER_SIZE + 4 * i);
+
+        Ok(match ty {
+            INITIALIZE_MSG => {
+                fixed(24)?;
+                Message::Initialize {
+                    request_id: word(0)?,
+                    major_version: word(1)?,
+                    minor_version: word(2)?,
+                    max_transfer_size: word(3)?,
+                }
+            }
+            t if t == INITIALIZE_MSG | COMPLETION => {
+                fixed(52)?;
+                Message::InitializeComplete(InitializeComplete {
+                    request_id: word(0)?,
+                    status: word(1)?,
+                    major_version: word(2)?,
+                    minor_version: word(3)?,
+                    device_flags: word(4)?,
+                    medium: word(5)?,
+                    max_packets_per_transfer: word(6)?,
+                    max_transfer_size: word(7)?,
+                    packet_alignment_factor: word(8)?,
+                })
+            }
+            HALT_MSG => {
+                fixed(12)?;
+                Message::Halt {
+                    request_id: word(0)?,
+                }
+            }
+            QUERY_MSG | SET_MSG => {
+                let information = buffer(bytes, word(2)?, word(3)?, 28)?;
+                let (request_id, oid) = (word(0)?, word(1)?);
+                if ty == QUERY_MSG {
+                    Message::Query {
+                        request_id,
+                        oid,
+                        information,
+                    }
+                } else {
+                    Message::Set {
+                        request_id,
+                        oid,
+                        information,
+                    }
+                }
+            }
+            t if t == QUERY_MSG | COMPLETION => Message::QueryComplete {
+                request_id: word(0)?,
+                status: word(1)?,
+                information: buffer(bytes, word(2)?, word(3)?, 24)?,
+            },
+            t if t == SET_MSG | COMPLETION => {
+                fixed(16)?;
+                Message::SetComplete {
+                    request_id: word(0)?,
+                    status: word(1)?,
+                }
+            }
+            RESET_MSG => {
+                fixed(12)?;
+                Message::Reset
+            }
+            t if t == RESET_MSG | COMPLETION => {
+                fixed(16)?;
+                Message::ResetComplete {
+                    status: word(0)?,
+                    addressing_reset: word(1)? != 0,
+                }
+            }
+            INDICATE_STATUS_MSG => Message::IndicateStatus {
+                status: word(0)?,
+                buffer: buffer(bytes, word(1)?, word(2)?, 20)?,
+            },
+            KEEPALIVE_MSG => {
+                fixed(12)?;
+                Message::KeepAlive {
+                    request_id: word(0)?,
+                }
+            }
+            t if t == KEEPALIVE_MSG | COMPLETION => {
+                fixed(16)?;
+                Message::KeepAliveComplete {
+                    request_id: word(0)?,
+                    status: word(1)?,
+                }
+            }
+            _ => return Err(()),
+        })
+    }
+
+    /// Writes the wire representation of this message into `buf`
+    ///
+    /// Returns the size of the message, or an error if `buf` is too small
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let mut words = [0; 11];
+        let (ty, nwords, buffer): (u32, usize, &[u8]) = match *self {
+            Message::Initialize {
+                request_id,
+                major_version,
+                minor_version,
+                max_transfer_size,
+            } => {
+                words[..4].copy_from_slice(&[
+                    request_id,
+                    major_version,
+                    minor_version,
+                    max_transfer_size,
+                ]);
+                (INITIALIZE_MSG, 4, &[])
+            }
+            Message::InitializeComplete(ref cmplt) => {
+                // AFListOffset and AFListSize are zero
+                words[..9].copy_from_slice(&[
+                    cmplt.request_id,
+                    cmplt.status,
+                    cmplt.major_version,
+                    cmplt.minor_version,
+                    cmplt.device_flags,
+                    cmplt.medium,
+                    cmplt.max_packets_per_transfer,
+                    cmplt.max_transfer_size,
+                    cmplt.packet_alignment_factor,
+                ]);
+                (INITIALIZE_MSG | COMPLETION, 11, &[])
+            }
+            Message::Halt { request_id } => {
+                words[0] = request_id;
+                (HALT_MSG, 1, &[])
+            }
+            Message::Query {
+                request_id,
+                oid,
+                information,
+            }
+            | Message::Set {
+                request_id,
+                oid,
+                information,
+            } => {
+                let ty = if let Message::Query { .. } = self {
+                    QUERY_MSG
+                } else {
+                    SET_MSG
+                };
+                // DeviceVcHandle is zero
+                words[..4].copy_from_slice(&[
+                    request_id,
+                    oid,
+                    information.len() as u32,
+                    offset(information, 28),
+                ]);
+                (ty, 5, information)
+            }
+            Message::QueryComplete {
+                request_id,
+                status,
+                information,
+            } => {
+                words[..4].copy_from_slice(&[
+                    request_id,
+                    status,
+                    information.len() as u32,
+                    offset(information, 24),
+                ]);
+                (QUERY_MSG | COMPLETION, 4, information)
+            }
+            Message::SetComplete { request_id, status } => {
+                words[..2].copy_from_slice(&[request_id, status]);
+                (SET_MSG | COMPLETION, 2, &[])
+            }
+            Message::Reset => (RESET_MSG, 1, &[]),
+            Message::ResetComplete {
+                status,
+                addressing_reset,
+            } => {
+                words[..2].copy_from_slice(&[status, addressing_reset as u32]);
+                (RESET_MSG | COMPLETION, 2, &[])
+            }
+            Message::IndicateStatus { status, buffer } => {
+                words[..3].copy_from_slice(&[status, buffer.len() as u32, offset(buffer, 20)]);
+                (INDICATE_STATUS_MSG, 3, buffer)
+            }
+            Message::KeepAlive { request_id } => {
+                words[0] = request_id;
+                (KEEPALIVE_MSG, 1, &[])
+            }
+            Message::KeepAliveComplete { request_id, status } => {
+                words[..2].copy_from_slice(&[request_id, status]);
+                (KEEPALIVE_MSG | COMPLETION, 2, &[])
+            }
+        };
+
+        let fixed = HEADER_SIZE + 4 * nwords;
+        let size = fixed + buffer.len();
+        let buf = buf.get_mut(..size).ok_or(())?;
+
+        buf[0..4].copy_from_slice(&ty.to_le_bytes());
+        buf[4..8].copy_from_slice(&(size as u32).to_le_bytes());
+        for (i, word) in words[..nwords].iter().enumerate() {
+            buf[HEADER_SIZE + 4 * i..][..4].copy_from_slice(&word.to_le_bytes());
+        }
+        buf[fixed..].copy_from_slice(buffer);
+
+        Ok(size)
+    }
+}
+
+// offset field of a buffer that follows a `fixed`-byte message; offsets are relative to the end
+// of the message header
+fn offset(buffer: &[u8], fixed: usize) -> u32 {
+    if buffer.is_empty() {
+        0
+    } else {
+        (fixed - HEADER_SIZE) as u32
+    }
+}
+
+// returns the buffer described by a length / offset pair
+fn buffer(bytes: &[u8], length: u32, offset: u32, fixed: usize) -> Result<&[u8], ()> {
+    if bytes.len() < fixed {
+        return Err(());
+    }
+
+    if length == 0 {
+        return Ok(&[]);
+    }
+
+    let start = HEADER_SIZE.checked_add(offset as usize).ok_or(())?;
+    let end = start.checked_add(length as usize).ok_or(())?;
+    if start < fixed {
+        return Err(());
+    }
+
+    bytes.get(start..end).ok_or(())
+}
+
+/// RNDIS data packet (REMOTE_NDIS_PACKET_MSG)
+///
+/// Out-of-band data and per-packet information are not supported
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Packet<'a> {
+    /// Ethernet frame
+    pub data: &'a [u8],
+}
+
+impl<'a> Packet<'a> {
+    /// Size of the packet header on the wire
+    pub const HEADER_SIZE: u8 = 44;
+
+    /// Writes the wire representation of this packet into `buf`
+    ///
+    /// Returns the size of the packet, or an error if `buf` is too small
+    pub fn bytes(&self, buf: &mut [u8]) -> Result<usize, ()> {
+        let header = usize::from(Self::HEADER_SIZE);
+        let size = header + self.data.len();
+        let buf = buf.get_mut(..size).ok_or(())?;
+
+        for byte in &mut buf[..header] {
+            *byte = 0;
+        }
+        buf[0..4].copy_from_slice(&PACKET_MSG.to_le_bytes());
+        buf[4..8].copy_from_slice(&(size as u32).to_le_bytes());
+        buf[8..12].copy_from_slice(&((header - HEADER_SIZE) as u32).to_le_bytes());
+        buf[12..16].copy_from_slice(&(self.data.len() as u32).to_le_bytes());
+        buf[header..].copy_from_slice(self.data);
+
+        Ok(size)
+    }
+
+    /// Parses the packet at the start of `bytes`
+    ///
+    /// A bulk transfer may contain several packets. Returns the packet and its size on the wire
+    pub fn parse(bytes: &'a [u8]) -> Result<(Self, usize), ()> {
+        if read32(bytes, 0)? != PACKET_MSG {
+            return Err(());
+        }
+
+        let size = read32(bytes, 4)? as usize;
+        let bytes = bytes.get(..size).ok_or(())?;
+        let data = buffer(
+            bytes,
+            read32(bytes, 12)?,
+            read32(bytes, 8)?,
+            usize::from(Self::HEADER_SIZE),
+        )?;
+
+        Ok((Packet { data }, size))
+    }
+}
+
+fn read32(bytes: &[u8], offset: usize) -> Result<u32, ()> {
+    let bytes = bytes.get(offset..offset + 4).ok_or(())?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{oid, InitializeComplete, Message, Packet, STATUS_SUCCESS};
+
+    #[test]
+    fn initialize() {
+        #[rustfmt::skip]
+        let bytes = [
+            2, 0, 0, 0, 24, 0, 0, 0,
+            1, 0, 0, 0,
+            1, 0, 0, 0,
+            0, 0, 0, 0,
+            0, 0x40, 0, 0,
+        ];
+        let message = Message::parse(&bytes).unwrap();
+        assert_eq!(
+            message,
+            Message::Initialize {
+                request_id: 1,
+                major_version: 1,
+                minor_version: 0,
+                max_transfer_size: 0x4000,
+            }
+        );
+
+        let mut buf = [0; 64];
+        assert_eq!(message.bytes(&mut buf), Ok(24));
+        assert_eq!(&buf[..24], &bytes[..]);
+
+        let cmplt = Message::InitializeComplete(InitializeComplete {
+            request_id: 1,
+            status: STATUS_SUCCESS,
+            major_version: 1,
+            minor_version: 0,
+            device_flags: super::DF_CONNECTIONLESS,
+            medium: super::MEDIUM_802_3,
+            max_packets_per_transfer: 1,
+            max_transfer_size: 1558,
+            packet_alignment_factor: 0,
+        });
+        let n = cmplt.bytes(&mut buf).unwrap();
+        assert_eq!(n, 52);
+        assert_eq!(Message::parse(&buf[..n]), Ok(cmplt));
+    }
+
+    #[test]
+    fn query() {
+        let query = Message::Query {
+            request_id: 7,
+            oid: oid::GEN_SUPPORTED_LIST,
+            information: &[],
+        };
+        let mut buf = [0; 64];
+        let n = query.bytes(&mut buf).unwrap();
+        assert_eq!(n, 28);
+        assert_eq!(Message::parse(&buf[..n]), Ok(query));
+
+        let cmplt = Message::QueryComplete {
+            request_id: 7,
+            status: STATUS_SUCCESS,
+            information: &[0x02, 0x01, 0x01, 0x01],
+        };
+        let n = cmplt.bytes(&mut buf).unwrap();
+        assert_eq!(&buf[16..24], &[4, 0, 0, 0, 16, 0, 0, 0]);
+        assert_eq!(Message::parse(&buf[..n]), Ok(cmplt));
+
+        // buffer outside the message
+        buf[20] = 17;
+        assert!(Message::parse(&buf[..n]).is_err());
+    }
+
+    #[test]
+    fn packets() {
+        let mut buf = [0; 128];
+        let n = Packet { data: &[1, 2, 3] }.bytes(&mut buf).unwrap();
+        let m = Packet { data: &[4; 14] }.bytes(&mut buf[n..]).unwrap();
+        assert_eq!(&buf[8..16], &[36, 0, 0, 0, 3, 0, 0, 0]);
+
+        let (first, size) = Packet::parse(&buf[..n + m]).unwrap();
+        assert_eq!((first.data, size), (&[1, 2, 3][..], n));
+        let (second, size) = Packet::parse(&buf[n..n + m]).unwrap();
+        assert_eq!((second.data, size), (&[4; 14][..], m));
+    }
+}